@@ -32,8 +32,18 @@ att!(MINX, "minx");
 att!(MINY, "miny");
 att!(GROWX, "growx");
 att!(GROWY, "growy");
+att!(EQUALX, "equalx");
+att!(EQUALY, "equaly");
+att!(ASPECT, "aspect");
+att!(AREAS, "areas");
+att!(AREA, "area");
 
 att!(PLACE, "place");
+att!(DIRECTION, "direction");
+att!(GAP, "gap");
+att!(WRAPCOUNT, "wrap-count");
+att!(JUSTIFY, "justify");
+att!(ALIGN, "align");
 
 att!(ANCHOR, "anchor");
 att!(EXPAND, "expand");
@@ -53,6 +63,7 @@ att!(STROKEWIDTH, "stroke-width");
 att!(WIDTH, "width");
 att!(HEIGHT, "height");
 att!(COORDS, "coords");
+att!(SEGMENTS, "segments");
 att!(ROUND, "round");
 att!(STELLATE, "stellate");
 att!(MARKERS, "markers");
@@ -60,11 +71,22 @@ att!(FONT, "font-family");
 att!(FONTSIZE, "font-size");
 att!(FONTWEIGHT, "font-weight");
 att!(FONTSTYLE, "font-style");
+att!(MAXWIDTH, "max-width");
+att!(WRAP, "wrap");
+att!(TEXTALIGN, "text-align");
+att!(LINESPACING, "line-spacing");
+att!(MARKUP, "markup");
 att!(VERTICES, "vertices");
 att!(FLAGS, "flags");
 att!(REF, "ref");
 att!(POINT, "point");
 att!(RELIEF, "relief");
+att!(FROM, "from");
+att!(TO, "to");
+att!(TARGET, "target");
+att!(ALONG, "along");
+att!(OFFSET, "offset");
+att!(CALLOUT, "callout");
 
 /*
 pub enum Typ {
@@ -111,6 +133,12 @@ pub enum Typ {
     Ref,
     Point,
     Relief,
+    From,
+    To,
+    Target,
+    Along,
+    Offset,
+    Callout,
 
 }
 */
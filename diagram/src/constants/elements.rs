@@ -32,6 +32,8 @@ tag!(CIRCLE, "circle");
 tag!(POLYGON, "polygon");
 tag!(TEXT, "text");
 tag!(PATH, "path");
+tag!(CONNECTOR, "connector");
+tag!(LABEL, "label");
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Typ {
@@ -46,6 +48,8 @@ pub enum Typ {
     Polygon,
     Text,
     Path,
+    Connector,
+    Label,
 }
 
 impl Typ {
@@ -62,6 +66,8 @@ impl Typ {
             Self::Polygon => POLYGON,
             Self::Text => TEXT,
             Self::Path => PATH,
+            Self::Connector => CONNECTOR,
+            Self::Label => LABEL,
         }
     }
 }
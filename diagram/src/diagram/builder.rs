@@ -0,0 +1,571 @@
+/*a Copyright
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+
+@file    builder.rs
+@brief   Programmatic construction of diagrams
+ */
+
+//a Imports
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::constants::attributes as at;
+use crate::constants::elements as el;
+use crate::diagram::{Diagram, Element, StyleVariables};
+use crate::DiagramDescriptor;
+
+//a BuildError
+//tp BuildError
+/// An error in building a diagram, with the path to the element (or
+/// variable) that has the error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildError {
+    /// The path of element types and ids from the diagram to the
+    /// element, such as `diagram > layout 'blocks' > rect 'src'`
+    pub path: String,
+    /// The reason for the error
+    pub reason: String,
+}
+
+//ip Display for BuildError
+impl std::fmt::Display for BuildError {
+    //mp fmt - format error for display
+    /// Display the error
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.reason)
+    }
+}
+
+//a ElementBuilder
+//tp ElementBuilder
+/// A handle on the description of an element to be built, with its
+/// attributes, its strings (for text) and its content elements
+///
+/// The setters return the handle so that they may be chained;
+/// methods that add a content element return the handle of that
+/// element. As handles do not borrow the builder they came from, a
+/// closure that adds content may return one.
+#[derive(Debug, Clone)]
+pub struct ElementBuilder(Rc<RefCell<ElementData>>);
+
+//ip ElementBuilder
+impl ElementBuilder {
+    //fi new
+    fn new(typ: el::Typ, id: &str) -> Self {
+        let e = Self(Rc::new(RefCell::new(ElementData {
+            typ,
+            attributes: Vec::new(),
+            strings: Vec::new(),
+            elements: Vec::new(),
+        })));
+        if !id.is_empty() {
+            e.attr("id", id);
+        }
+        e
+    }
+
+    //mp attr
+    /// Set an attribute of the element, as in markup
+    pub fn attr(&self, name: &str, value: &str) -> Self {
+        self.0
+            .borrow_mut()
+            .attributes
+            .push((name.to_string(), value.to_string()));
+        self.clone()
+    }
+
+    //mi attr_f64
+    fn attr_f64(&self, name: &str, value: f64) -> Self {
+        self.attr(name, &value.to_string())
+    }
+
+    //mp class
+    /// Add classes (separated by whitespace) to the element
+    pub fn class(&self, classes: &str) -> Self {
+        self.attr("class", classes)
+    }
+
+    //mp grid
+    /// Place the element in a cell of the grid of its layout
+    pub fn grid(&self, x: isize, y: isize) -> Self {
+        self.attr(at::GRID, &format!("{},{}", x, y))
+    }
+
+    //mp grid_span
+    /// Place the element in the grid of its layout, spanning from
+    /// one cell to (but not including) another
+    pub fn grid_span(&self, x0: isize, y0: isize, x1: isize, y1: isize) -> Self {
+        self.attr(at::GRID, &format!("{},{},{},{}", x0, y0, x1, y1))
+    }
+
+    //mp area
+    /// Place the element in a named area of the grid of its layout
+    pub fn area(&self, name: &str) -> Self {
+        self.attr(at::AREA, name)
+    }
+
+    //mp fill
+    /// Set the fill color of the element
+    pub fn fill(&self, color: &str) -> Self {
+        self.attr(at::FILL, color)
+    }
+
+    //mp stroke
+    /// Set the stroke color of the element
+    pub fn stroke(&self, color: &str) -> Self {
+        self.attr(at::STROKE, color)
+    }
+
+    //mp stroke_width
+    /// Set the stroke width of the element
+    pub fn stroke_width(&self, width: f64) -> Self {
+        self.attr_f64(at::STROKEWIDTH, width)
+    }
+
+    //mp width
+    /// Set the width of the element
+    pub fn width(&self, width: f64) -> Self {
+        self.attr_f64(at::WIDTH, width)
+    }
+
+    //mp height
+    /// Set the height of the element
+    pub fn height(&self, height: f64) -> Self {
+        self.attr_f64(at::HEIGHT, height)
+    }
+
+    //mp pad
+    /// Set the padding of the element on all sides
+    pub fn pad(&self, pad: f64) -> Self {
+        self.attr_f64(at::PAD, pad)
+    }
+
+    //mp margin
+    /// Set the margin of the element on all sides
+    pub fn margin(&self, margin: f64) -> Self {
+        self.attr_f64(at::MARGIN, margin)
+    }
+
+    //mp font_size
+    /// Set the font size of the element
+    pub fn font_size(&self, size: f64) -> Self {
+        self.attr_f64(at::FONTSIZE, size)
+    }
+
+    //mp string
+    /// Add a string to the element; for text this is a line of the
+    /// text
+    pub fn string(&self, s: &str) -> Self {
+        self.0.borrow_mut().strings.push(s.to_string());
+        self.clone()
+    }
+
+    //mi add
+    fn add(&self, typ: el::Typ, id: &str) -> ElementBuilder {
+        let e = ElementBuilder::new(typ, id);
+        self.0.borrow_mut().elements.push(e.clone());
+        e
+    }
+
+    //mi add_with
+    fn add_with<F, R>(&self, typ: el::Typ, id: &str, f: F) -> ElementBuilder
+    where
+        F: FnOnce(&mut ElementBuilder) -> R,
+    {
+        let mut e = self.add(typ, id);
+        f(&mut e);
+        e
+    }
+
+    //mp rect
+    /// Add a rect element with an id (which may be empty)
+    pub fn rect(&self, id: &str) -> ElementBuilder {
+        self.add(el::Typ::Rect, id)
+    }
+
+    //mp circle
+    /// Add a circle element with an id (which may be empty)
+    pub fn circle(&self, id: &str) -> ElementBuilder {
+        self.add(el::Typ::Circle, id)
+    }
+
+    //mp polygon
+    /// Add a polygon element with an id (which may be empty)
+    pub fn polygon(&self, id: &str) -> ElementBuilder {
+        self.add(el::Typ::Polygon, id)
+    }
+
+    //mp path
+    /// Add a path element with an id (which may be empty)
+    pub fn path(&self, id: &str) -> ElementBuilder {
+        self.add(el::Typ::Path, id)
+    }
+
+    //mp text
+    /// Add a text element with an id (which may be empty) and its
+    /// lines of text
+    pub fn text(&self, id: &str, lines: &[&str]) -> ElementBuilder {
+        let e = self.add(el::Typ::Text, id);
+        for l in lines {
+            e.string(l);
+        }
+        e
+    }
+
+    //mp use_ref
+    /// Add a use element with an id (which may be empty) that is a
+    /// copy of the definition with the id `reference`
+    pub fn use_ref(&self, id: &str, reference: &str) -> ElementBuilder {
+        self.add(el::Typ::Use, id).attr(at::REF, reference)
+    }
+
+    //mp connector
+    /// Add a connector element with an id (which may be empty)
+    /// between two anchors of elements
+    pub fn connector(&self, id: &str, from: &str, to: &str) -> ElementBuilder {
+        self.add(el::Typ::Connector, id)
+            .attr(at::FROM, from)
+            .attr(at::TO, to)
+    }
+
+    //mp label
+    /// Add a label element with an id (which may be empty) attached
+    /// to a target element
+    pub fn label(&self, id: &str, target: &str) -> ElementBuilder {
+        self.add(el::Typ::Label, id).attr(at::TARGET, target)
+    }
+
+    //mp group
+    /// Add a group element with an id (which may be empty), whose
+    /// content is added by the closure
+    pub fn group<F, R>(&self, id: &str, f: F) -> ElementBuilder
+    where
+        F: FnOnce(&mut ElementBuilder) -> R,
+    {
+        self.add_with(el::Typ::Group, id, f)
+    }
+
+    //mp layout
+    /// Add a layout element with an id (which may be empty), whose
+    /// content is added by the closure
+    pub fn layout<F, R>(&self, id: &str, f: F) -> ElementBuilder
+    where
+        F: FnOnce(&mut ElementBuilder) -> R,
+    {
+        self.add_with(el::Typ::Layout, id, f)
+    }
+
+    //mp marker
+    /// Add a marker element with an id, whose content is added by the
+    /// closure; markers are only permitted in the definitions
+    pub fn marker<F, R>(&self, id: &str, f: F) -> ElementBuilder
+    where
+        F: FnOnce(&mut ElementBuilder) -> R,
+    {
+        self.add_with(el::Typ::Marker, id, f)
+    }
+}
+
+//a ElementData
+//ti ElementData
+/// The description of an element to be built, which is shared by the
+/// handles on it
+#[derive(Debug)]
+struct ElementData {
+    typ: el::Typ,
+    attributes: Vec<(String, String)>,
+    strings: Vec<String>,
+    elements: Vec<ElementBuilder>,
+}
+
+//ii ElementData
+impl ElementData {
+    //mi path_name
+    fn path_name(&self, parent: &str) -> String {
+        let id = self
+            .attributes
+            .iter()
+            .rev()
+            .find(|(n, _)| n == "id")
+            .map(|(_, v)| v.as_str());
+        let mut path = String::new();
+        if !parent.is_empty() {
+            path.push_str(parent);
+            path.push_str(" > ");
+        }
+        path.push_str(self.typ.as_str());
+        if let Some(id) = id {
+            path.push_str(&format!(" '{}'", id));
+        }
+        path
+    }
+
+    //mi build
+    /// Build the element and its content as `DiagramML` would from
+    /// markup, adding any errors to the list
+    fn build<'a>(
+        &self,
+        descriptor: &'a DiagramDescriptor,
+        variables: &StyleVariables,
+        parent: &str,
+        errors: &mut Vec<BuildError>,
+    ) -> Option<Element<'a>> {
+        let path = self.path_name(parent);
+        let mut name_values = self
+            .attributes
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.as_str()));
+        let mut element = match Element::new(descriptor, variables, self.typ, &mut name_values) {
+            Ok(element) => element,
+            Err(e) => {
+                errors.push(BuildError {
+                    path,
+                    reason: e.to_string(),
+                });
+                return None;
+            }
+        };
+        for s in &self.strings {
+            if let Err(e) = element.add_string(s) {
+                errors.push(BuildError {
+                    path: path.clone(),
+                    reason: e.to_string(),
+                });
+            }
+        }
+        for e in &self.elements {
+            let e = e.0.borrow();
+            if e.typ == el::Typ::Marker {
+                errors.push(BuildError {
+                    path: e.path_name(&path),
+                    reason: "markers are only permitted in the definitions".into(),
+                });
+            } else if let Some(e) = e.build(descriptor, variables, &path, errors) {
+                element.add_element(e);
+            }
+        }
+        Some(element)
+    }
+}
+
+//a DiagramBuilder
+//tp DiagramBuilder
+/// A builder for a `Diagram`, for constructing diagrams in Rust
+/// rather than from markup
+///
+/// The elements are built when the diagram is, in the same way as
+/// `DiagramML` builds them from markup, and with the same checks of
+/// the attributes against the style set; all of the errors are
+/// returned, with the path to the element that has each error.
+///
+/// Whatever the closures that add content return (such as the
+/// builder of the last element they add) is ignored.
+///
+/// # Example
+///
+/// ```
+/// extern crate diagram;
+/// use diagram::{Diagram, DiagramDescriptor};
+/// let style_set = DiagramDescriptor::create_style_set();
+/// let diagram_descriptor = DiagramDescriptor::new(&style_set);
+/// let diagram = Diagram::builder(&diagram_descriptor)
+///     .var("accent", "#3366cc")
+///     .layout(|l| {
+///         l.rect("a").grid(1, 1).fill("red").width(10.);
+///         l.text("b", &["Hello"]).grid(2, 1).fill("$accent");
+///     })
+///     .build()
+///     .unwrap();
+/// ```
+pub struct DiagramBuilder<'a> {
+    descriptor: &'a DiagramDescriptor<'a>,
+    variables: Vec<(String, String)>,
+    definitions: ElementBuilder,
+    diagram: ElementBuilder,
+}
+
+//ip DiagramBuilder
+impl<'a> DiagramBuilder<'a> {
+    //fp new
+    /// Create a new builder of a diagram using a `DiagramDescriptor`
+    pub fn new(descriptor: &'a DiagramDescriptor<'a>) -> Self {
+        Self {
+            descriptor,
+            variables: Vec::new(),
+            definitions: ElementBuilder::new(el::Typ::Group, ""),
+            diagram: ElementBuilder::new(el::Typ::Diagram, ""),
+        }
+    }
+
+    //cp attr
+    /// Set an attribute of the diagram element
+    pub fn attr(self, name: &str, value: &str) -> Self {
+        self.diagram.attr(name, value);
+        self
+    }
+
+    //cp var
+    /// Set a variable that may be used in the attribute values of
+    /// the elements; variables are set before any element is built
+    pub fn var(mut self, name: &str, value: &str) -> Self {
+        self.variables.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    //cp defs
+    /// Add definitions (and markers) with the closure
+    pub fn defs<F, R>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut ElementBuilder) -> R,
+    {
+        f(&mut self.definitions);
+        self
+    }
+
+    //cp layout
+    /// Add the content of the diagram element with the closure; the
+    /// diagram is itself a layout
+    pub fn layout<F, R>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut ElementBuilder) -> R,
+    {
+        f(&mut self.diagram);
+        self
+    }
+
+    //mp build
+    /// Build the diagram, returning all of the errors if there are
+    /// any
+    pub fn build(self) -> Result<Diagram<'a>, Vec<BuildError>> {
+        let mut diagram = Diagram::new(self.descriptor);
+        let mut errors = Vec::new();
+        let (descriptor, contents, stylesheet) = diagram.borrow_contents_descriptor();
+        for (name, value) in &self.variables {
+            if let Err(e) = stylesheet.set_variable(name, value) {
+                errors.push(BuildError {
+                    path: format!("var '{}'", name),
+                    reason: e.to_string(),
+                });
+            }
+        }
+        let variables = stylesheet.variables();
+        for e in &self.definitions.0.borrow().elements {
+            let e = e.0.borrow();
+            if let Some(element) = e.build(descriptor, variables, "defs", &mut errors) {
+                if e.typ == el::Typ::Marker {
+                    contents.markers.push(element);
+                } else {
+                    contents.definitions.push(element);
+                }
+            }
+        }
+        if let Some(element) = self
+            .diagram
+            .0
+            .borrow()
+            .build(descriptor, variables, "", &mut errors)
+        {
+            contents.set_root_element(element);
+        }
+        if errors.is_empty() {
+            Ok(diagram)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+//a Test
+#[cfg(test)]
+mod tests {
+    use crate::{Diagram, DiagramDescriptor, DiagramML, IndentOptions};
+    use indent_display::{IndentedDisplay, Indenter};
+    fn display(diagram: &Diagram) -> String {
+        let mut s = Vec::new();
+        let mut ind = Indenter::new(&mut s, "  ", &IndentOptions {});
+        diagram.indent(&mut ind).unwrap();
+        drop(ind);
+        String::from_utf8(s).unwrap()
+    }
+    #[test]
+    fn test_same_as_markup() {
+        let style_set = DiagramDescriptor::create_style_set();
+        let diagram_descriptor = DiagramDescriptor::new(&style_set);
+        let mut built = Diagram::builder(&diagram_descriptor)
+            .var("w", "10")
+            .defs(|d| {
+                d.rect("box").width(5.).height(2.);
+            })
+            .layout(|l| {
+                l.rect("a").grid(1, 1).fill("red").attr("width", "$w * 2");
+                l.layout("l", |l| {
+                    l.use_ref("b", "box").grid(1, 1);
+                    l.text("", &["two", "lines"]).grid(2, 1);
+                })
+                .grid(2, 1);
+            })
+            .build()
+            .unwrap();
+        let mut parsed = Diagram::new(&diagram_descriptor);
+        let mut dml = DiagramML::new(&mut parsed);
+        dml.read_file(
+            "#diagram ##var w=10 ##defs ###rect id=box width=5 height=2 ##rect id=a grid=1,1 fill-color=red width='$w * 2' ##layout id=l grid=2,1 ###use id=b ref=box grid=1,1 ###text grid=2,1 \"two\" \"lines\"".as_bytes(),
+            false,
+        )
+        .unwrap();
+        built.uniquify().ok().unwrap();
+        parsed.uniquify().ok().unwrap();
+        assert_eq!(display(&parsed), display(&built));
+    }
+    #[test]
+    fn test_expression_closure() {
+        let style_set = DiagramDescriptor::create_style_set();
+        let diagram_descriptor = DiagramDescriptor::new(&style_set);
+        let mut built = Diagram::builder(&diagram_descriptor)
+            .layout(|l| l.rect("a").grid(1, 1).fill("red"))
+            .build()
+            .unwrap();
+        let mut parsed = Diagram::new(&diagram_descriptor);
+        let mut dml = DiagramML::new(&mut parsed);
+        dml.read_file(
+            "#diagram ##rect id=a grid=1,1 fill-color=red".as_bytes(),
+            false,
+        )
+        .unwrap();
+        built.uniquify().ok().unwrap();
+        parsed.uniquify().ok().unwrap();
+        assert_eq!(display(&parsed), display(&built));
+    }
+    #[test]
+    fn test_errors() {
+        let style_set = DiagramDescriptor::create_style_set();
+        let diagram_descriptor = DiagramDescriptor::new(&style_set);
+        let errors = Diagram::builder(&diagram_descriptor)
+            .layout(|l| {
+                l.rect("a").attr("fill-colour", "red");
+                l.group("g", |g| {
+                    g.circle("c").width(3.).attr("colour", "red");
+                })
+                .marker("m", |_| ());
+            })
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(3, errors.len(), "{:?}", errors);
+        assert_eq!(errors[0].path, "diagram > rect 'a'");
+        assert!(errors[0].reason.contains("fill-colour"), "{}", errors[0]);
+        assert_eq!(errors[1].path, "diagram > group 'g' > circle 'c'");
+        assert_eq!(errors[2].path, "diagram > group 'g' > marker 'm'");
+    }
+}
@@ -18,6 +18,7 @@ limitations under the License.
 
 //a Imports
 use super::font::*;
+use super::font_file::FontError;
 use super::types::*;
 use super::{Element, ElementHeader};
 use crate::constants::attributes as at;
@@ -40,7 +41,7 @@ use std::rc::Rc;
 pub struct DiagramDescriptor<'a> {
     pub(super) style_set: &'a StyleSet,
     descriptors: HashMap<el::Typ, StyleDescriptor<'a>>,
-    fonts: HashMap<&'a str, RrcFont>,
+    fonts: HashMap<String, RrcFont>,
 }
 
 //ti DiagramDescriptor
@@ -67,37 +68,59 @@ impl<'a> DiagramDescriptor<'a> {
             .add_type(at::GRIDX, int_list_type.clone(), false)
             .add_type(at::GRIDY, int_list_type, false)
             .add_type(at::MINX, string_comma_list_type.clone(), false)
-            .add_type(at::MINY, string_comma_list_type, false)
+            .add_type(at::MINY, string_comma_list_type.clone(), false)
+            .add_type(at::EQUALX, string_comma_list_type.clone(), false)
+            .add_type(at::EQUALY, string_comma_list_type.clone(), false)
+            .add_type(at::ASPECT, string_comma_list_type.clone(), false)
+            .add_type(at::AREAS, string_comma_list_type.clone(), false)
+            .add_type(at::AREA, string_comma_list_type, false)
             .add_type(at::PLACE, float_list_type.clone(), false)
-            .add_type(at::ANCHOR, point_type.clone(), true)
-            .add_type(at::EXPAND, point_type.clone(), true)
+            .add_type(at::DIRECTION, string_type.clone(), false)
+            .add_type(at::GAP, float_type.clone(), false)
+            .add_type(at::WRAPCOUNT, int_type.clone(), false)
+            .add_type(at::JUSTIFY, string_type.clone(), false)
+            .add_type(at::ALIGN, string_type.clone(), false)
+            .add_type(at::ANCHOR, point_type.clone(), false)
+            .add_type(at::EXPAND, point_type.clone(), false)
             .add_type(at::PAD, box_type.clone(), false)
-            .add_type(at::MARGIN, box_type, true)
-            .add_type(at::BORDERWIDTH, float_type.clone(), true)
-            .add_type(at::BORDERROUND, float_type.clone(), true)
-            .add_type(at::BORDERCOLOR, color_type.as_type(), true)
-            .add_type(at::BG, color_type.as_type(), true)
-            .add_type(at::SCALE, float_type.clone(), true)
-            .add_type(at::ROTATE, float_type.clone(), true)
-            .add_type(at::TRANSLATE, point_type.clone(), true)
-            .add_type(at::POINT, point_type.clone(), true)
-            .add_type(at::RELIEF, point_type, true)
+            .add_type(at::MARGIN, box_type, false)
+            .add_type(at::BORDERWIDTH, float_type.clone(), false)
+            .add_type(at::BORDERROUND, float_type.clone(), false)
+            .add_type(at::BORDERCOLOR, color_type.as_type(), false)
+            .add_type(at::BG, color_type.as_type(), false)
+            .add_type(at::SCALE, float_type.clone(), false)
+            .add_type(at::ROTATE, float_type.clone(), false)
+            .add_type(at::TRANSLATE, point_type.clone(), false)
+            .add_type(at::POINT, point_type.clone(), false)
+            .add_type(at::RELIEF, point_type.clone(), false)
             .add_type(at::FILL, color_type.as_type(), true)
             .add_type(at::STROKE, color_type.as_type(), true)
             .add_type(at::STROKEWIDTH, float_type.clone(), true)
-            .add_type(at::WIDTH, float_type.clone(), true)
-            .add_type(at::HEIGHT, float_type.clone(), true)
+            .add_type(at::WIDTH, float_type.clone(), false)
+            .add_type(at::HEIGHT, float_type.clone(), false)
             .add_type(at::COORDS, float_list_type, false)
-            .add_type(at::ROUND, float_type.clone(), true)
-            .add_type(at::STELLATE, float_type.clone(), true)
-            .add_type(at::MARKERS, string_list_type, true)
+            .add_type(at::SEGMENTS, string_type.clone(), false)
+            .add_type(at::ROUND, float_type.clone(), false)
+            .add_type(at::STELLATE, float_type.clone(), false)
+            .add_type(at::MARKERS, string_list_type.clone(), false)
             .add_type(at::FONT, string_type.clone(), true)
-            .add_type(at::FONTSIZE, float_type, true)
+            .add_type(at::FONTSIZE, float_type.clone(), true)
             .add_type(at::FONTWEIGHT, string_type.clone(), true)
             .add_type(at::FONTSTYLE, string_type.clone(), true)
-            .add_type(at::VERTICES, int_type.clone(), true)
-            .add_type(at::FLAGS, int_type, true)
-            .add_type(at::REF, string_type, false)
+            .add_type(at::MAXWIDTH, float_type.clone(), false)
+            .add_type(at::WRAP, string_list_type.clone(), true)
+            .add_type(at::TEXTALIGN, string_type.clone(), true)
+            .add_type(at::LINESPACING, float_type.clone(), true)
+            .add_type(at::MARKUP, string_type.clone(), true)
+            .add_type(at::VERTICES, int_type.clone(), false)
+            .add_type(at::FLAGS, int_type, false)
+            .add_type(at::REF, string_type.clone(), false)
+            .add_type(at::FROM, string_list_type.clone(), false)
+            .add_type(at::TO, string_list_type, false)
+            .add_type(at::TARGET, string_type.clone(), false)
+            .add_type(at::ALONG, float_type, false)
+            .add_type(at::OFFSET, point_type, false)
+            .add_type(at::CALLOUT, string_type, false)
     }
 
     //fp new
@@ -130,7 +153,7 @@ impl<'a> DiagramDescriptor<'a> {
         Element::add_content_descriptors(&mut descriptor);
         descriptor
             .fonts
-            .insert("default", Rc::new(RefCell::new(Font::default())));
+            .insert("default".into(), Rc::new(RefCell::new(Font::default())));
         descriptor
     }
 
@@ -163,9 +186,48 @@ impl<'a> DiagramDescriptor<'a> {
         }
     }
 
+    //mp add_font_file
+    /// Add a font of a family name, with metrics read from a
+    /// TrueType or OpenType font file
+    ///
+    /// The file provides the regular, bold, italic or bold italic
+    /// variant of the family, as declared by the font file itself, and
+    /// replaces any previous file for that variant of the family. The
+    /// first file added to "default" replaces all of its built-in
+    /// variants; styles that no file of a family supplies use the
+    /// nearest variant that one does (dropping italic, then bold).
+    ///
+    /// This must be invoked before the descriptor is used by a
+    /// `Diagram`.
+    pub fn add_font_file<P: AsRef<std::path::Path>>(
+        &mut self,
+        family: &str,
+        path: P,
+    ) -> Result<(), FontError> {
+        let font = Font::of_file(path)?;
+        match self.fonts.get(family) {
+            Some(rrc_font) => rrc_font.borrow_mut().add_variants(font),
+            None => {
+                self.fonts
+                    .insert(family.to_string(), Rc::new(RefCell::new(font)));
+            }
+        }
+        Ok(())
+    }
+
     //mp get_font
-    /// Get a font
-    pub(crate) fn get_font(&self) -> RrcFont {
+    /// Get the font for a font family list, such as "'DejaVu Sans', sans"
+    ///
+    /// The first family in the list that has been added to the
+    /// descriptor is used; if there is none then the "default" font
+    /// is returned
+    pub(crate) fn get_font(&self, families: Option<&str>) -> RrcFont {
+        for family in families.unwrap_or("").split(',') {
+            let family = family.trim().trim_matches(|c| c == '"' || c == '\'');
+            if let Some(font) = self.fonts.get(family) {
+                return font.clone();
+            }
+        }
         self.fonts.get("default").unwrap().clone()
     }
 
@@ -18,42 +18,18 @@ limitations under the License.
 
 //a Imports
 use indent_display::{IndentedDisplay, Indenter};
+use std::collections::HashMap;
 use stylesheet::{StylableNode, Tree};
 use vg_rs::layout::Layout;
 use vg_rs::BBox;
 
 use crate::constants::elements as el;
-use crate::diagram::{Element, ElementError, ElementScope};
+use crate::diagram::ElementScope;
+use crate::diagram::{ConnectorAnchors, DiagramBuilder, DiagramLint, Element};
+use crate::diagram::{ElementError, ElementErrorList, ElementSpan};
 use crate::IndentOptions;
 use crate::{DiagramDescriptor, StyleSheet};
 
-//a DiagramError
-//tp DiagramError
-pub enum DiagramError {
-    Error(String),
-}
-
-//ip Display for DiagramError
-impl std::fmt::Display for DiagramError {
-    //mp fmt - format error for display
-    /// Display the error
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            Self::Error(s) => write!(f, "DiagramError {}", s),
-        }
-    }
-
-    //zz All done
-}
-
-//ip From std::fmt::Display for DiagramError
-/// Provides an implicit conversion from
-impl From<ElementError> for DiagramError {
-    fn from(e: ElementError) -> DiagramError {
-        DiagramError::Error(e.to_string())
-    }
-}
-
 //a Diagram Definition
 //tp DiagramContents
 /// The contents of a diagram that are constructed; this is mutable
@@ -64,6 +40,16 @@ pub struct DiagramContents<'a> {
     pub markers: Vec<Element<'a>>, // All these elements MUST be markers
     pub root_layout: Option<Element<'a>>,
     pub content_bbox: BBox,
+    /// The ids of the definitions and markers read from libraries,
+    /// which need not all be used by the diagram
+    pub library_ids: Vec<String>,
+    /// The number of definitions and markers read from the diagram
+    /// itself (rather than from libraries), which are kept first
+    diagram_definitions: usize,
+    diagram_markers: usize,
+    /// The spans of the markup of the rules of the stylesheet that
+    /// use a style by its id, to report those whose style is unknown
+    pub rule_spans: HashMap<usize, ElementSpan>,
 }
 
 //ip DiagramContents
@@ -76,6 +62,41 @@ impl<'a> DiagramContents<'a> {
             markers: Vec::new(),
             root_layout: None,
             content_bbox: BBox::none(),
+            library_ids: Vec::new(),
+            diagram_definitions: 0,
+            diagram_markers: 0,
+            rule_spans: HashMap::new(),
+        }
+    }
+
+    //mp add_definition
+    /// Add a definition read from markup; those of the diagram itself
+    /// are kept before those of libraries
+    ///
+    /// The diagram is read after its libraries (so that it may use
+    /// their variables), but its definitions and markers are kept in
+    /// the order they would have if it were read first: the last
+    /// definition of an id is used, so those of libraries take
+    /// precedence, whereas the first marker of an id is used, so
+    /// those of the diagram do
+    pub fn add_definition(&mut self, element: Element<'a>, is_library: bool) {
+        if is_library {
+            self.definitions.push(element);
+        } else {
+            self.definitions.insert(self.diagram_definitions, element);
+            self.diagram_definitions += 1;
+        }
+    }
+
+    //mp add_marker
+    /// Add a marker read from markup; those of the diagram itself are
+    /// kept before those of libraries, as for `add_definition`
+    pub fn add_marker(&mut self, element: Element<'a>, is_library: bool) {
+        if is_library {
+            self.markers.push(element);
+        } else {
+            self.markers.insert(self.diagram_markers, element);
+            self.diagram_markers += 1;
         }
     }
 
@@ -115,6 +136,13 @@ impl<'a> Diagram<'a> {
         }
     }
 
+    //fp builder
+    /// Create a builder of a diagram, to construct its contents in
+    /// Rust rather than from markup
+    pub fn builder(descriptor: &'a DiagramDescriptor<'a>) -> DiagramBuilder<'a> {
+        DiagramBuilder::new(descriptor)
+    }
+
     //fp borrow_contents_descriptor
     /// Borrow the contents and descriptor to build the diagram contents
     ///
@@ -132,6 +160,13 @@ impl<'a> Diagram<'a> {
         (self.descriptor, &mut self.contents, &mut self.stylesheet)
     }
 
+    //fp borrow_contents_stylesheet
+    /// Borrow the contents and stylesheet of the diagram, to
+    /// inspect or write them out
+    pub fn borrow_contents_stylesheet(&self) -> (&DiagramContents<'a>, &StyleSheet<'a>) {
+        (&self.contents, &self.stylesheet)
+    }
+
     //mp find_definition
     /// Find the definition of an id, if it exists in the contents
     /// 'definitions' section
@@ -150,16 +185,20 @@ impl<'a> Diagram<'a> {
     /// Convert all 'use <id_ref>'s in to copies of the definition
     /// that has id==<id_ref>, uniquifying the contents within that
     /// definition too along with the ids therein
-    pub fn uniquify(&mut self) -> Result<(), DiagramError> {
+    ///
+    /// All of the contents are uniquified, and every error found is
+    /// returned
+    pub fn uniquify(&mut self) -> Result<(), ElementErrorList> {
         let scope = ElementScope::new("", &self.contents.definitions);
+        let mut errors = ElementErrorList::new();
         let mut uid = 1;
         if let Some(element) = &mut self.contents.root_layout {
-            uid = element.uniquify(&scope, uid)?;
+            uid = element.uniquify(&scope, uid, &mut errors);
         }
         for element in &mut self.contents.markers {
-            uid = element.uniquify(&scope, uid)?;
+            uid = element.uniquify(&scope, uid, &mut errors);
         }
-        Ok(())
+        errors.as_err(())
     }
 
     //mp apply_stylesheet
@@ -167,10 +206,26 @@ impl<'a> Diagram<'a> {
     ///
     /// This must be invoked after uniquify and before style.
     ///
+    /// It first links the stylesheet, resolving the style ids used
+    /// by its rules (so styles may be defined after the rules that
+    /// use them); an error is returned for every rule whose style is
+    /// not known, and such rules are applied without a style.
+    ///
     /// It updates the element's style attributes based on the
-    /// stylesheet and its rules; the actually styling is then
-    /// appllied in `style`.
-    pub fn apply_stylesheet(&mut self) {
+    /// stylesheet and its rules, and then inherits the values of
+    /// inheritable styles from parent elements to their children
+    /// where the children do not set them; the actually styling is
+    /// then appllied in `style`.
+    pub fn apply_stylesheet(&mut self) -> Result<(), ElementErrorList> {
+        let mut errors = ElementErrorList::new();
+        if let Err(rules) = self.stylesheet.link() {
+            for rule in rules {
+                errors.add(ElementError::unknown_style(
+                    self.stylesheet.style_of_rule(rule).unwrap_or(""),
+                    self.contents.rule_spans.get(&rule).cloned(),
+                ));
+            }
+        }
         let mut x =
             StylableNode::<'a>::new("diagram", self.descriptor.get(el::Typ::Group).unwrap());
         let mut tree = Tree::new(&mut x);
@@ -182,18 +237,24 @@ impl<'a> Diagram<'a> {
         }
         tree.close_container();
         self.stylesheet.apply_rules_to_tree(&mut tree);
+        self.stylesheet.apply_inheritance_to_tree(&mut tree);
+        errors.as_err(())
     }
 
     //mp style
     /// Style the contents of the diagram, using the stylesheet
-    pub fn style(&mut self) -> Result<(), DiagramError> {
+    ///
+    /// All of the contents are styled, and every error found is
+    /// returned
+    pub fn style(&mut self) -> Result<(), ElementErrorList> {
+        let mut errors = ElementErrorList::new();
         if let Some(element) = &mut self.contents.root_layout {
-            element.style(self.descriptor)?;
+            element.style(self.descriptor, &mut errors);
         }
         for element in &mut self.contents.markers {
-            element.style(self.descriptor)?;
+            element.style(self.descriptor, &mut errors);
         }
-        Ok(())
+        errors.as_err(())
     }
 
     //mp layout
@@ -209,7 +270,7 @@ impl<'a> Diagram<'a> {
     /// bbox, which will generate the positions of the grid elements,
     /// and so on
     ///
-    pub fn layout(&mut self, within: &BBox) -> Result<(), DiagramError> {
+    pub fn layout(&mut self, within: &BBox) -> Result<(), ElementErrorList> {
         let mut layout = Layout::default();
         if let Some(element) = &mut self.contents.root_layout {
             element.set_layout_properties(&mut layout);
@@ -242,8 +303,38 @@ impl<'a> Diagram<'a> {
     //mp geometry
     /// Resolve the geometry of the contents of the diagram based on
     /// how it has been laid out
-    pub fn geometry(&mut self) -> Result<(), DiagramError> {
-        Ok(())
+    ///
+    /// This resolves the ends of connectors to the laid-out elements
+    /// they are attached to, and routes them; then it resolves the
+    /// reference points of labels, which may be on connectors; as
+    /// labels have no desired geometry, the bounds of the diagram are
+    /// extended to include them
+    ///
+    /// Every connector and label is resolved, and every error found
+    /// is returned
+    pub fn geometry(&mut self) -> Result<(), ElementErrorList> {
+        let anchors = ConnectorAnchors::default();
+        let mut errors = ElementErrorList::new();
+        if let Some(element) = &mut self.contents.root_layout {
+            element.resolve_connectors(&anchors, &mut errors);
+            let bbox = element.resolve_labels(&anchors, &mut errors);
+            self.contents.content_bbox = self.contents.content_bbox.union(bbox);
+        }
+        for element in &mut self.contents.markers {
+            element.resolve_connectors(&anchors, &mut errors);
+            element.resolve_labels(&anchors, &mut errors);
+        }
+        errors.as_err(())
+    }
+
+    //mp lint
+    /// Check the diagram for things that are probably mistakes (such
+    /// as unused definitions, or rules that match no elements), and
+    /// return them as warnings
+    ///
+    /// This should be invoked after style
+    pub fn lint(&self) -> ElementErrorList {
+        DiagramLint::new(&self.contents, &self.stylesheet).warnings()
     }
 
     //mp display
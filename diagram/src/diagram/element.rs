@@ -25,12 +25,14 @@ use crate::DiagramDescriptor;
 use indent_display::{IndentedDisplay, Indenter};
 use stylesheet::Tree;
 use vg_rs::layout::Layout;
+use vg_rs::{BBox, Transform};
 
-use super::elements::{Group, Path, Shape, Text, Use};
+use super::elements::{Connector, ConnectorAnchors, Group, Label, Path, Shape, Text, Use};
 use super::types::*;
 use super::DiagramElementContent;
 use super::ElementContent;
 use super::ElementError;
+use super::ElementErrorList;
 use super::ElementHeader;
 use super::ElementScope;
 
@@ -76,6 +78,12 @@ impl<'a> Element<'a> {
             Shape::get_style_names(el::CIRCLE),
         );
         descriptor.add_content_descriptor(el::Typ::Path, true, Path::get_style_names(el::PATH));
+        descriptor.add_content_descriptor(
+            el::Typ::Connector,
+            false,
+            Connector::get_style_names(el::CONNECTOR),
+        );
+        descriptor.add_content_descriptor(el::Typ::Label, false, Label::get_style_names(el::LABEL));
     }
 
     //mp borrow_id
@@ -88,32 +96,44 @@ impl<'a> Element<'a> {
         self.header.stylable.has_id(name)
     }
 
+    //mp set_id
+    pub fn set_id(&mut self, id: &str) {
+        self.header.set_id(id);
+    }
+
     //fp new
     pub fn new(
         descriptor: &'a DiagramDescriptor,
+        variables: &StyleVariables,
         name: el::Typ,
         name_values: &mut dyn Iterator<Item = (String, &str)>,
     ) -> Result<Self, ElementError> {
         // println!("New element name '{}'", name);
-        let header = ElementHeader::new(descriptor, name, name_values)?;
+        let header = ElementHeader::new(descriptor, variables, name, name_values)?;
         let content = ElementContent::new(&header, name)?;
         Ok(Self { header, content })
     }
 
     //mp uniquify
     /// Generates a *replacement* if the content requires it
+    ///
+    /// Errors are added to the list, and the rest of the content is
+    /// still uniquified
     pub fn uniquify<'b>(
         &mut self,
         scope: &ElementScope<'a, 'b>,
         uid: usize,
-    ) -> Result<usize, ElementError> {
+        errors: &mut ElementErrorList,
+    ) -> usize {
         self.header.set_uid(uid);
-        let (uniquified, uniq_uid) = self.content.uniquify(&self.header, scope, uid + 1)?;
-        if uniquified {
+        match self.content.uniquify(&self.header, scope, uid + 1, errors) {
             // Updated the content, so uniquify again with the input uid
-            self.uniquify(scope, uid)
-        } else {
-            Ok(uniq_uid)
+            Ok((true, _)) => self.uniquify(scope, uid, errors),
+            Ok((false, uniq_uid)) => uniq_uid,
+            Err(e) => {
+                errors.add(e);
+                uid + 1
+            }
         }
     }
 
@@ -165,11 +185,12 @@ impl<'a> Element<'a> {
     }
 
     //mp style
-    pub fn style(&mut self, descriptor: &DiagramDescriptor) -> Result<(), ElementError> {
+    /// Style the element and its content, adding any errors to the
+    /// list
+    pub fn style(&mut self, descriptor: &DiagramDescriptor, errors: &mut ElementErrorList) {
         // println!("Style  {} ", self.header.borrow_id());
-        self.header.style()?;
-        self.content.style(descriptor, &self.header)?;
-        Ok(())
+        errors.update(self.header.style());
+        self.content.style(descriptor, &self.header, errors);
     }
 
     //mp set_layout_properties
@@ -203,6 +224,62 @@ impl<'a> Element<'a> {
         self.content.apply_placement(layout, &content_rect);
     }
 
+    //mp add_anchors
+    /// This method is invoked after the layout of a diagram, to add
+    /// the bounding box of this element (and its contents) to the
+    /// anchors that connectors in the same layout may attach to
+    ///
+    /// The transform maps the coordinates of the layout this element
+    /// is placed in to those of the anchors. An obstacle is an
+    /// element placed directly in the layout of the anchors, which
+    /// connectors must route around.
+    pub fn add_anchors(
+        &self,
+        transform: &Transform,
+        is_obstacle: bool,
+        anchors: &mut ConnectorAnchors,
+    ) -> BBox {
+        let bbox = self.content.add_anchors(&self.header, transform, anchors);
+        if !bbox.is_none() {
+            anchors.add_bbox(self.borrow_id(), bbox, is_obstacle);
+        }
+        if !self.borrow_id().is_empty() {
+            if let Some(mut path) = self.content.bezier_path() {
+                let transform = match self.header.layout_box.content_transform() {
+                    Some(t) => transform.apply_to_transform(t),
+                    None => *transform,
+                };
+                path.map_pts(|p| transform.apply(p));
+                anchors.add_path(self.borrow_id(), path);
+            }
+        }
+        bbox
+    }
+
+    //mp resolve_connectors
+    /// Resolve and route any connectors in this element, given the
+    /// anchors of the layout that it is placed in
+    pub fn resolve_connectors(
+        &mut self,
+        anchors: &ConnectorAnchors,
+        errors: &mut ElementErrorList,
+    ) {
+        self.content
+            .resolve_connectors(&self.header, anchors, errors)
+    }
+
+    //mp resolve_labels
+    /// Resolve the reference points of any labels in this element,
+    /// given the anchors of the layout that it is placed in, and
+    /// return the bounding box of the labels in that layout
+    pub fn resolve_labels(
+        &mut self,
+        anchors: &ConnectorAnchors,
+        errors: &mut ElementErrorList,
+    ) -> BBox {
+        self.content.resolve_labels(&self.header, anchors, errors)
+    }
+
     //fp display
     pub fn display(&self, indent: usize) {
         const INDENT_STRING: &str = "                                                            ";
@@ -23,16 +23,17 @@ limitations under the License.
 use indent_display::{IndentedDisplay, Indenter};
 use stylesheet::{StylableNode, Tree};
 use vg_rs::layout::Layout;
-use vg_rs::BBox;
+use vg_rs::{BBox, BezierPath, Transform};
 
 use super::DiagramElementContent;
 use super::Element;
 use super::ElementError;
+use super::ElementErrorList;
 use super::ElementHeader;
 use super::ElementScope;
 use super::IndentOptions;
 use crate::constants::elements as el;
-use crate::diagram::elements::{Group, Path, Shape, Text, Use};
+use crate::diagram::elements::{Connector, ConnectorAnchors, Group, Label, Path, Shape, Text, Use};
 use crate::DiagramDescriptor;
 
 //a ElementContent - enumerated union of the above
@@ -47,6 +48,10 @@ pub enum ElementContent<'a> {
     Shape(Shape),
     /// Path is used for custom shapes
     Path(Path),
+    /// Connector is used for paths routed between other elements
+    Connector(Connector),
+    /// Label is used for text attached to a point on another element
+    Label(Label),
     /// Use describes a reference to a defined element
     Use(Use<'a>), // use of a definition
 }
@@ -61,6 +66,8 @@ impl<'a> ElementContent<'a> {
             el::Typ::Layout => Ok(Self::Group(Group::new(header, name)?)),
             el::Typ::Marker => Ok(Self::Group(Group::new(header, name)?)),
             el::Typ::Path => Ok(Self::Path(Path::new(header, name)?)),
+            el::Typ::Connector => Ok(Self::Connector(Connector::new(header, name)?)),
+            el::Typ::Label => Ok(Self::Label(Label::new(header, name)?)),
             el::Typ::Rect => Ok(Self::Shape(Shape::new(header, name)?)),
             el::Typ::Circle => Ok(Self::Shape(Shape::new(header, name)?)),
             el::Typ::Polygon => Ok(Self::Shape(Shape::new(header, name)?)),
@@ -89,10 +96,11 @@ impl<'a> ElementContent<'a> {
         header: &ElementHeader<'a>,
         scope: &ElementScope<'a, 'b>,
         uid: usize,
+        errors: &mut ElementErrorList,
     ) -> Result<(bool, usize), ElementError> {
         match self {
-            Self::Use(ref mut c) => c.uniquify(header, scope, uid),
-            Self::Group(ref mut c) => c.uniquify(header, scope, uid),
+            Self::Use(ref mut c) => c.uniquify(header, scope, uid, errors),
+            Self::Group(ref mut c) => c.uniquify(header, scope, uid, errors),
             _ => Ok((false, uid)),
         }
     }
@@ -116,10 +124,18 @@ impl<'a> ElementContent<'a> {
                 header,
                 c.clone(header, scope),
             )?)),
+            Self::Connector(ref c) => Ok(Self::Connector(ElementError::of_result(
+                header,
+                c.clone(header, scope),
+            )?)),
             Self::Text(ref c) => Ok(Self::Text(ElementError::of_result(
                 header,
                 c.clone(header, scope),
             )?)),
+            Self::Label(ref c) => Ok(Self::Label(ElementError::of_result(
+                header,
+                c.clone(header, scope),
+            )?)),
             Self::Use(ref c) => Ok(Self::Use(ElementError::of_result(
                 header,
                 c.clone(header, scope),
@@ -138,11 +154,23 @@ impl<'a> ElementContent<'a> {
     pub fn add_string(&mut self, header: &ElementHeader, s: &str) -> Result<(), ElementError> {
         match self {
             Self::Text(ref mut c) => ElementError::of_result(header, c.add_string(s)),
+            Self::Label(ref mut c) => ElementError::of_result(header, c.add_string(s)),
             Self::Use(ref mut c) => ElementError::of_result(header, c.add_string(s)),
             _ => Ok(()), // could error - bug in code
         }
     }
 
+    //mp borrow_strings
+    /// Borrow the strings that have been added to the content
+    pub fn borrow_strings(&self) -> &[String] {
+        match self {
+            Self::Text(ref c) => &c.text,
+            Self::Label(ref c) => &c.text.text,
+            Self::Use(ref c) => c.borrow_strings(),
+            _ => &[],
+        }
+    }
+
     //mp borrow_group
     pub fn borrow_group<'z>(&'z self) -> Option<&'z Group<'a>> {
         match self {
@@ -151,6 +179,32 @@ impl<'a> ElementContent<'a> {
         }
     }
 
+    //mp borrow_use
+    pub fn borrow_use<'z>(&'z self) -> Option<&'z Use<'a>> {
+        match self {
+            Self::Use(ref u) => Some(u),
+            _ => None,
+        }
+    }
+
+    //mp borrow_content
+    /// Borrow the elements contained by the content - those of a
+    /// group, or the element a use is bound to
+    pub fn borrow_content(&self) -> &[Element<'a>] {
+        match self {
+            Self::Group(ref g) => &g.content,
+            Self::Use(ref u) => u.borrow_content(),
+            _ => &[],
+        }
+    }
+
+    //mp is_container
+    /// Return true if the content contains other elements, which
+    /// may inherit its styles
+    pub fn is_container(&self) -> bool {
+        matches!(self, Self::Group(_) | Self::Use(_))
+    }
+
     //fp tree_add_element
     pub fn tree_add_element<'b>(
         &'b mut self,
@@ -164,17 +218,28 @@ impl<'a> ElementContent<'a> {
     }
 
     //mp style
+    /// Style the content, and then any elements it contains, adding
+    /// any errors to the list
     pub fn style(
         &mut self,
         descriptor: &DiagramDescriptor,
         header: &ElementHeader,
-    ) -> Result<(), ElementError> {
-        match self {
+        errors: &mut ElementErrorList,
+    ) {
+        let result = match self {
             Self::Shape(ref mut s) => s.style(descriptor, header),
             Self::Path(ref mut s) => s.style(descriptor, header),
+            Self::Connector(ref mut s) => s.style(descriptor, header),
+            Self::Label(ref mut s) => s.style(descriptor, header),
             Self::Group(ref mut g) => g.style(descriptor, header),
             Self::Text(ref mut t) => t.style(descriptor, header),
             Self::Use(ref mut t) => t.style(descriptor, header),
+        };
+        errors.update(result);
+        match self {
+            Self::Group(ref mut g) => g.style_content(descriptor, errors),
+            Self::Use(ref mut u) => u.style_content(descriptor, errors),
+            _ => (),
         }
     }
 
@@ -183,6 +248,8 @@ impl<'a> ElementContent<'a> {
         match self {
             Self::Shape(ref mut s) => s.get_desired_geometry(layout),
             Self::Path(ref mut s) => s.get_desired_geometry(layout),
+            Self::Connector(ref mut s) => s.get_desired_geometry(layout),
+            Self::Label(ref mut s) => s.get_desired_geometry(layout),
             Self::Group(ref mut g) => g.get_desired_geometry(layout),
             Self::Text(ref mut t) => t.get_desired_geometry(layout),
             Self::Use(ref mut t) => t.get_desired_geometry(layout),
@@ -199,12 +266,83 @@ impl<'a> ElementContent<'a> {
     pub fn apply_placement(&mut self, layout: &Layout, rect: &BBox) {
         match self {
             Self::Path(ref mut g) => g.apply_placement(layout, rect),
+            Self::Text(ref mut g) => g.apply_placement(layout, rect),
             Self::Group(ref mut g) => g.apply_placement(layout, rect),
             Self::Use(ref mut g) => g.apply_placement(layout, rect),
             _ => (),
         }
     }
 
+    //mp add_anchors
+    /// Add the anchors for any content of this element to those of
+    /// the layout it is in, returning the bounding box of the
+    /// element itself in the layout's coordinates (after the
+    /// transform is applied)
+    pub fn add_anchors(
+        &self,
+        header: &ElementHeader<'a>,
+        transform: &Transform,
+        anchors: &mut ConnectorAnchors,
+    ) -> BBox {
+        match self {
+            Self::Group(ref g) => g.add_anchors(header, transform, anchors),
+            Self::Use(ref u) => u.add_anchors(transform, anchors),
+            Self::Connector(_) => BBox::none(),
+            Self::Label(_) => BBox::none(),
+            _ => header.laid_out_bbox().transform(transform),
+        }
+    }
+
+    //mp resolve_connectors
+    /// Resolve any connectors within this element given the anchors
+    /// of the layout that the element is in
+    pub fn resolve_connectors(
+        &mut self,
+        header: &ElementHeader<'a>,
+        anchors: &ConnectorAnchors,
+        errors: &mut ElementErrorList,
+    ) {
+        match self {
+            Self::Group(ref mut g) => g.resolve_connectors(anchors, errors),
+            Self::Use(ref mut u) => u.resolve_connectors(anchors, errors),
+            Self::Connector(ref mut c) => errors.update(c.resolve(header, anchors)),
+            _ => (),
+        }
+    }
+
+    //mp resolve_labels
+    /// Resolve any labels within this element given the anchors of
+    /// the layout that the element is in, returning the bounding box
+    /// of the labels in the coordinates of that layout
+    pub fn resolve_labels(
+        &mut self,
+        header: &ElementHeader<'a>,
+        anchors: &ConnectorAnchors,
+        errors: &mut ElementErrorList,
+    ) -> BBox {
+        match self {
+            Self::Group(ref mut g) => g.resolve_labels(header, anchors, errors),
+            Self::Use(ref mut u) => u.resolve_labels(anchors, errors),
+            Self::Label(ref mut l) => {
+                errors.update(l.resolve(header, anchors));
+                l.bbox()
+            }
+            _ => BBox::none(),
+        }
+    }
+
+    //mp bezier_path
+    /// Get the outline of the content as a path, if it has one, in
+    /// the content coordinates of the element
+    pub fn bezier_path(&self) -> Option<BezierPath> {
+        match self {
+            Self::Path(ref p) => Some(p.as_bezier_path()),
+            Self::Connector(ref c) => c.as_bezier_path(),
+            Self::Shape(ref s) => Some(s.polygon.as_paths()),
+            _ => None,
+        }
+    }
+
     //mp display
     pub fn display(&self, indent: usize, indent_str: &str) {
         match self {
@@ -216,6 +354,14 @@ impl<'a> ElementContent<'a> {
                 println!("{}  Path", indent_str);
                 s.display(indent, indent_str);
             }
+            Self::Connector(ref s) => {
+                println!("{}  Connector", indent_str);
+                s.display(indent, indent_str);
+            }
+            Self::Label(ref s) => {
+                println!("{}  Label", indent_str);
+                s.display(indent, indent_str);
+            }
             Self::Group(ref g) => {
                 println!("{}  Group", indent_str);
                 g.display(indent, indent_str);
@@ -240,6 +386,8 @@ impl<'a, 'diag> IndentedDisplay<'a, IndentOptions> for ElementContent<'diag> {
         match self {
             Self::Shape(s) => s.indent(ind),
             Self::Path(s) => s.indent(ind),
+            Self::Connector(s) => s.indent(ind),
+            Self::Label(s) => s.indent(ind),
             Self::Group(g) => g.indent(ind),
             Self::Text(t) => t.indent(ind),
             Self::Use(t) => t.indent(ind),
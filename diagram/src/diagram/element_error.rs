@@ -19,34 +19,130 @@ limitations under the License.
 //a Imports
 use super::ElementHeader;
 
+//a ElementSpan
+//tp ElementSpan
+/// The span of the markup that an element was read from, as the
+/// excerpt of the markup (formatted as for the errors in reading it)
+/// and the position of its start
+///
+/// The excerpt is formatted when the element is read, as the markup
+/// is not kept once it has been read.
+#[derive(Debug, Clone)]
+pub struct ElementSpan {
+    context: String,
+    position: String,
+}
+
+//ip ElementSpan
+impl ElementSpan {
+    //fp new
+    /// Create a new span from the excerpt of the markup and the
+    /// position of its start
+    pub fn new(context: String, position: String) -> Self {
+        Self { context, position }
+    }
+
+    //ap position
+    /// Get the position of the start of the span
+    pub fn position(&self) -> &str {
+        &self.position
+    }
+}
+
 //a ElementError
 //tp ElementError
+/// An error in an element found after it has been read, such as in
+/// styling it or resolving references to other elements; it records
+/// the id of the element and the span of its markup (if it was read
+/// from markup)
+///
+/// A warning is not an error in the diagram, but something that is
+/// probably a mistake (such as a style that is not used); it has an
+/// empty id if it is not about a particular element.
+#[derive(Debug)]
 pub enum ElementError {
-    UnknownId(String, String),
-    Error(String, String),
+    /// An element refers to another element id that is not known
+    UnknownId(String, String, Option<ElementSpan>),
+    /// An error in an element, with its reason
+    Error(String, String, Option<ElementSpan>),
+    /// A warning about an element (or, with an empty id, the diagram)
+    Warning(String, String, Option<ElementSpan>),
+    /// A rule of the stylesheet uses a style id that is not known;
+    /// the span is that of the rule
+    UnknownStyle(String, Option<ElementSpan>),
 }
 
 //ii ElementError
 impl ElementError {
     //fp unknown_id
+    /// Create an error for an element that refers to an unknown id
     pub fn unknown_id(hdr: &ElementHeader, name: &str) -> Self {
-        Self::UnknownId(hdr.borrow_id().to_string(), name.to_string())
+        Self::UnknownId(
+            hdr.borrow_id().to_string(),
+            name.to_string(),
+            hdr.span.clone(),
+        )
     }
     //fp of_string
+    /// Create an error in an element with a reason
     pub fn of_string(hdr: &ElementHeader, s: &str) -> Self {
-        Self::Error(hdr.borrow_id().to_string(), s.to_string())
+        Self::Error(hdr.borrow_id().to_string(), s.to_string(), hdr.span.clone())
+    }
+    //fp warning
+    /// Create a warning about an element
+    pub fn warning(hdr: &ElementHeader, s: &str) -> Self {
+        Self::Warning(hdr.borrow_id().to_string(), s.to_string(), hdr.span.clone())
+    }
+    //fp diagram_warning
+    /// Create a warning about the diagram (such as its stylesheet)
+    /// rather than any of its elements
+    pub fn diagram_warning(s: &str) -> Self {
+        Self::Warning(String::new(), s.to_string(), None)
+    }
+    //fp unknown_style
+    /// Create an error for a rule that uses a style id that is not
+    /// known
+    pub fn unknown_style(name: &str, span: Option<ElementSpan>) -> Self {
+        Self::UnknownStyle(name.to_string(), span)
     }
     //mi of_result
+    /// Convert the error of a result to an error in an element
     pub fn of_result<V, E: std::fmt::Display>(
         hdr: &ElementHeader,
         result: Result<V, E>,
     ) -> Result<V, ElementError> {
         match result {
             Ok(v) => Ok(v),
-            Err(e) => Err(ElementError::Error(
-                hdr.borrow_id().to_string(),
-                e.to_string(),
-            )),
+            Err(e) => Err(ElementError::of_string(hdr, &e.to_string())),
+        }
+    }
+
+    //mp borrow_span
+    /// Borrow the span of the markup of the element, if it was read
+    /// from markup
+    pub fn borrow_span(&self) -> Option<&ElementSpan> {
+        match self {
+            Self::UnknownId(_, _, span) => span.as_ref(),
+            Self::Error(_, _, span) => span.as_ref(),
+            Self::Warning(_, _, span) => span.as_ref(),
+            Self::UnknownStyle(_, span) => span.as_ref(),
+        }
+    }
+
+    //mp is_warning
+    /// Return true if this is a warning rather than an error
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Self::Warning(..))
+    }
+
+    //mp write_with_context
+    /// Write the error preceded by the excerpt of the markup of the
+    /// element and followed by its position, as for errors in
+    /// reading markup
+    pub fn write_with_context(&self, f: &mut dyn std::fmt::Write) -> std::fmt::Result {
+        match self.borrow_span() {
+            Some(span) => write!(f, "{}{} at {}", span.context, self, span.position),
+            None => write!(f, "{}", self),
         }
     }
 
@@ -59,12 +155,112 @@ impl std::fmt::Display for ElementError {
     /// Display the error
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            ElementError::UnknownId(id, s) => {
+            ElementError::UnknownId(id, s, _) => {
                 write!(f, "Element id '{}': Unknown id reference '{}'", id, s)
             }
-            ElementError::Error(id, s) => write!(f, "Element id '{}': {}", id, s),
+            ElementError::Error(id, s, _) => write!(f, "Element id '{}': {}", id, s),
+            ElementError::Warning(id, s, _) if id.is_empty() => write!(f, "Warning: {}", s),
+            ElementError::Warning(id, s, _) => {
+                write!(f, "Warning: element id '{}': {}", id, s)
+            }
+            ElementError::UnknownStyle(s, _) => {
+                write!(f, "Rule: Unknown style id '{}'", s)
+            }
+        }
+    }
+
+    //zz All done
+}
+
+//a ElementErrorList
+//tp ElementErrorList
+/// An accumulation of the errors in the elements of a diagram, from
+/// each of the stages of preparing it (uniquifying, styling, laying
+/// out and resolving its geometry); if it is empty then there is no
+/// error.
+///
+/// Unlike an `MLErrorList` each error is independent, as an error
+/// in an element does not prevent the other elements from being
+/// prepared.
+#[derive(Debug, Default)]
+pub struct ElementErrorList {
+    errors: Vec<ElementError>,
+}
+
+//ip ElementErrorList
+impl ElementErrorList {
+    //fp new
+    /// Create a new ElementErrorList
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //mp add
+    /// Add an error to the list
+    pub fn add(&mut self, e: ElementError) {
+        self.errors.push(e);
+    }
+
+    //mp update
+    /// Update the ElementErrorList from a result; this returns () so
+    /// the error is effectively caught and recorded
+    pub fn update<T>(&mut self, e: Result<T, ElementError>) {
+        if let Err(e) = e {
+            self.errors.push(e);
+        }
+    }
+
+    //mp append
+    /// Append the errors of another list (if it is an error) to this
+    pub fn append<T>(&mut self, e: Result<T, ElementErrorList>) {
+        if let Err(mut e) = e {
+            self.errors.append(&mut e.errors);
+        }
+    }
+
+    //mp is_empty
+    /// Return true if there are no errors in the list
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    //mp iter
+    /// Iterate over the errors in the list
+    pub fn iter(&self) -> std::slice::Iter<'_, ElementError> {
+        self.errors.iter()
+    }
+
+    //mp take
+    /// Take the errors for consumption by caller
+    pub fn take(&mut self) -> Vec<ElementError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    //mp as_err
+    /// Return a result of 'Ok(x)' if this error list is empty, or
+    /// 'Err(ElementErrorList)' if the error list has contents. It
+    /// cleans the current error list.
+    pub fn as_err<T>(&mut self, v: T) -> Result<T, Self> {
+        let errors = self.take();
+        match errors.len() {
+            0 => Ok(v),
+            _ => Err(Self { errors }),
         }
     }
 
     //zz All done
 }
+
+//ip std::fmt::Display for ElementErrorList
+impl std::fmt::Display for ElementErrorList {
+    //mp fmt
+    /// Display the [ElementErrorList] for humans, with the excerpt of
+    /// the markup of each element in error
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for e in &self.errors {
+            e.write_with_context(f)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
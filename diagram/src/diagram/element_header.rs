@@ -25,12 +25,12 @@ use vg_rs::layout::{Layout, LayoutBox};
 use vg_rs::BBox;
 
 use super::types::*;
-use super::ElementError;
 use super::ElementScope;
+use super::{ElementError, ElementSpan};
 use super::{ElementLayout, LayoutPlacement};
 use crate::constants::attributes as at;
 use crate::constants::elements as el;
-use crate::diagram::{StylableNode, StyleTypeValue};
+use crate::diagram::{StylableNode, StyleTypeValue, StyleVariables};
 use crate::DiagramDescriptor;
 
 //a ElementHeader
@@ -42,6 +42,12 @@ pub struct ElementHeader<'a> {
     pub id_name: Option<String>, // replicated from stylable
     pub layout_box: LayoutBox,
     pub layout: ElementLayout,
+    /// The names and (evaluated) values of the attributes the
+    /// element was created with, so that it may be written out again
+    pub attributes: Vec<(String, String)>,
+    /// The span of the markup the element was read from, if any,
+    /// for reporting errors in the element
+    pub span: Option<ElementSpan>,
 }
 
 //ti ElementHeader
@@ -49,6 +55,7 @@ impl<'a> ElementHeader<'a> {
     //fp new
     pub fn new(
         descriptor: &'a DiagramDescriptor,
+        variables: &StyleVariables,
         name: el::Typ,
         name_values: &mut dyn Iterator<Item = (String, &str)>,
     ) -> Result<Self, ElementError> {
@@ -65,10 +72,14 @@ impl<'a> ElementHeader<'a> {
                 id_name,
                 layout_box,
                 layout,
+                attributes: Vec::new(),
+                span: None,
             };
             for (name, value) in name_values {
-                let result = hdr.stylable.add_name_value(&name, value);
+                let result = hdr.stylable.add_name_value_with(&name, value, variables);
                 ElementError::of_result(&hdr, result)?;
+                let value = ElementError::of_result(&hdr, variables.evaluate(value))?;
+                hdr.attributes.push((name, value.into_owned()));
             }
             hdr.id_name = hdr.stylable.id().map(|s| s.into());
             Ok(hdr)
@@ -76,6 +87,7 @@ impl<'a> ElementHeader<'a> {
             Err(ElementError::Error(
                 "".to_string(),
                 format!("Bug - unknown element descriptor {}", name),
+                None,
             ))
         }
     }
@@ -97,6 +109,8 @@ impl<'a> ElementHeader<'a> {
             id_name,
             layout_box,
             layout,
+            attributes: self.attributes.clone(),
+            span: self.span.clone(),
         }
     }
 
@@ -118,7 +132,9 @@ impl<'a> ElementHeader<'a> {
             at::GRID,
             at::GRIDX,
             at::GRIDY,
+            at::AREA,
             at::PLACE,
+            at::POINT,
             at::ANCHOR,
             at::EXPAND,
             at::ROTATE,
@@ -157,6 +173,16 @@ impl<'a> ElementHeader<'a> {
         }
     }
 
+    //mp set_id
+    /// Set the id of the element, replacing any it has
+    pub fn set_id(&mut self, id: &str) {
+        // Setting the id of a stylable node does not fail
+        let _ = self.stylable.add_name_value("id", id);
+        self.id_name = Some(id.into());
+        self.attributes.retain(|(n, _)| n != "id");
+        self.attributes.push(("id".into(), id.into()));
+    }
+
     //mp get_style_value_of_name
     pub fn get_style_value_of_name(&self, name: &str) -> Option<&StyleTypeValue> {
         self.stylable.get_style_value_of_name(name)
@@ -246,13 +272,30 @@ impl<'a> ElementHeader<'a> {
                     let ey = layout.find_grid_id(false, ey).unwrap();
                     layout.grid_bbox((*sx, *sy), (*ex, *ey))
                 }
-                LayoutPlacement::Place(pt) => layout.get_placed_rectangle(pt, &self.layout.ref_pt),
+                LayoutPlacement::Place(pt) => layout.get_placed_rectangle(
+                    pt,
+                    &self.layout.placed_ref_pt(),
+                    &self.layout_box.desired_bbox(),
+                ),
             }
         };
         self.layout_box.layout_within_rectangle(rect);
         self.layout_box.get_content_rectangle()
     }
 
+    //mp laid_out_bbox
+    /// Get the bounding box of the laid-out content in the
+    /// coordinates of the layout that contains the element
+    ///
+    /// Must only be invoked after apply_placement
+    pub fn laid_out_bbox(&self) -> BBox {
+        let rect = self.layout_box.get_content_rectangle();
+        match self.layout_box.content_transform() {
+            Some(transform) => rect.transform(transform),
+            None => rect,
+        }
+    }
+
     //mp display
     pub fn display(&self, indent_str: &str) {
         println!("{}{}: {:?}", indent_str, self.uid, self.id_name);
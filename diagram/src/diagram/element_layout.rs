@@ -23,7 +23,7 @@ limitations under the License.
 use geo_nd::Vector;
 use indent_display::{IndentedDisplay, Indenter};
 use vg_rs::layout::{Layout, LayoutBox};
-use vg_rs::{BBox, Point};
+use vg_rs::{BBox, Point, Transform};
 
 use super::ElementError;
 use super::ElementHeader;
@@ -262,6 +262,12 @@ impl ElementLayout {
         } {
             layout.set_place(x, y);
         }
+        if let Some(v) = header
+            .get_style_value_of_name(at::POINT)
+            .and_then(|x| x.as_floats(&mut floats))
+        {
+            layout.ref_pt = Some(Point::from_array([v[0], v[1]]));
+        }
         Ok(layout)
     }
 
@@ -284,6 +290,17 @@ impl ElementLayout {
         self.placement = LayoutPlacement::Place(Point::from_array([x, y]));
     }
 
+    //mp placed_ref_pt
+    /// Get the reference point of the element for placement, if it
+    /// has one, in the coordinates of its desired bounding box
+    ///
+    /// The reference point is given in the coordinates of the
+    /// content, which is scaled and rotated around its origin
+    pub fn placed_ref_pt(&self) -> Option<Point> {
+        self.ref_pt
+            .map(|pt| Transform::of_trs(Point::zero(), self.rotation, self.scale).apply(pt))
+    }
+
     //mp set_layout_box
     /// This method is invoked to update the [LayoutBox] based on the
     /// properties of this [ElementLayout] and given a desired content
@@ -335,7 +352,7 @@ impl ElementLayout {
                 layout.add_grid_element(eref, (sx, sy), (ex, ey), (bbox.width(), bbox.height()));
             }
             LayoutPlacement::Place(pt) => {
-                layout.add_placed_element(eref, pt, &self.ref_pt, &bbox);
+                layout.add_placed_element(eref, pt, &self.placed_ref_pt(), &bbox);
             }
         }
     }
@@ -0,0 +1,757 @@
+/*a Copyright
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+
+@file    connector.rs
+@brief   Diagram connector element
+ */
+
+//a Imports
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use geo_nd::Vector;
+use indent_display::{IndentedDisplay, Indenter};
+use vg_rs::{BBox, BezierPath, Point};
+
+use super::super::IndentOptions;
+use super::super::{
+    DiagramDescriptor, DiagramElementContent, ElementError, ElementHeader, ElementScope,
+};
+use super::super::{GenerateSvgElement, Svg, SvgError};
+use super::Path;
+use crate::constants::attributes as at;
+use crate::constants::elements as el;
+
+//a Constants
+/// Distance that a route keeps from obstacles, and the length of the
+/// stub that leaves an anchor along its normal
+const ROUTE_CLEARANCE: f64 = 2.;
+
+/// Cost of a bend in a route, in terms of route length
+const ROUTE_BEND_COST: f64 = 4.;
+
+/// Tolerance for comparing route coordinates
+const ROUTE_EPSILON: f64 = 1E-6;
+
+//a ConnectorAnchors
+//tp ConnectorAnchors
+/// The bounding boxes of the elements within a layout that
+/// connectors in that layout may attach to, in the content
+/// coordinates of the layout
+///
+/// Elements that are directly within the layout (rather than within
+/// a sublayout) are also obstacles that connectors route around.
+///
+/// Elements with an outline (paths, shapes and routed connectors)
+/// also record that outline, for labels to be attached to.
+#[derive(Debug, Default)]
+pub struct ConnectorAnchors {
+    bboxes: HashMap<String, BBox>,
+    paths: HashMap<String, BezierPath>,
+    obstacles: Vec<BBox>,
+}
+
+//ip ConnectorAnchors
+impl ConnectorAnchors {
+    //mp add_bbox
+    /// Add the bounding box of an element; an empty id is not
+    /// recorded as an anchor, but the element may still be an obstacle
+    pub fn add_bbox(&mut self, id: &str, bbox: BBox, is_obstacle: bool) {
+        if !id.is_empty() {
+            self.bboxes.insert(id.to_string(), bbox);
+        }
+        if is_obstacle {
+            self.obstacles.push(bbox);
+        }
+    }
+
+    //mp add_path
+    /// Add the outline of an element
+    pub fn add_path(&mut self, id: &str, path: BezierPath) {
+        self.paths.insert(id.to_string(), path);
+    }
+
+    //mp find
+    /// Find the bounding box of an element id
+    pub fn find(&self, id: &str) -> Option<BBox> {
+        self.bboxes.get(id).copied()
+    }
+
+    //mp find_path
+    /// Find the outline of an element id
+    pub fn find_path(&self, id: &str) -> Option<&BezierPath> {
+        self.paths.get(id)
+    }
+
+    //mp obstacles_excluding
+    /// Get the obstacles that a connector between two bounding boxes
+    /// must avoid; anything strictly containing, or contained by,
+    /// either end is not an obstacle, and an end itself is an obstacle
+    /// only if the connector leaves it by a stub
+    pub fn obstacles_excluding(&self, ends: &[(BBox, bool)]) -> Vec<BBox> {
+        self.obstacles
+            .iter()
+            .filter(|o| {
+                !ends.iter().any(|(e, is_obstacle)| {
+                    let (contains, contained) = (bbox_contains(o, e), bbox_contains(e, o));
+                    if contains && contained {
+                        !is_obstacle
+                    } else {
+                        contains || contained
+                    }
+                })
+            })
+            .copied()
+            .collect()
+    }
+
+    //zz All done
+}
+
+//a ConnectorEnd
+//tp ConnectorEnd
+/// One end of a connector - an element id and a compass anchor on
+/// the bounding box of that element
+#[derive(Debug, Default, Clone)]
+pub struct ConnectorEnd {
+    /// Id of the element the connector is attached to
+    pub id: String,
+    /// Name of the anchor (n, s, e, w, ne, nw, se, sw or center)
+    pub anchor: String,
+    /// Position of the anchor within the bounding box, -1 to 1 in each dimension
+    position: Point,
+    /// Direction the connector leaves the anchor; zero for the center
+    normal: Point,
+}
+
+//ip ConnectorEnd
+impl ConnectorEnd {
+    //fp of_style
+    /// Read a connector end from a style value of 'id [anchor]'; the
+    /// anchor defaults to the center
+    fn of_style(header: &ElementHeader, name: &str) -> Result<Self, ElementError> {
+        let v = header
+            .get_style_value_of_name(name)
+            .and_then(|x| x.as_vec_str())
+            .unwrap_or_default();
+        let (id, anchor) = match v.len() {
+            1 => (v[0], "center"),
+            2 => (v[0], v[1]),
+            _ => {
+                return Err(ElementError::of_string(
+                    header,
+                    &format!(
+                        "connector requires '{}' of an id and an optional anchor",
+                        name
+                    ),
+                ));
+            }
+        };
+        let (x, y, nx, ny) = match anchor {
+            "n" => (0., -1., 0., -1.),
+            "s" => (0., 1., 0., 1.),
+            "e" => (1., 0., 1., 0.),
+            "w" => (-1., 0., -1., 0.),
+            "ne" => (1., -1., 0., -1.),
+            "nw" => (-1., -1., 0., -1.),
+            "se" => (1., 1., 0., 1.),
+            "sw" => (-1., 1., 0., 1.),
+            "c" | "center" => (0., 0., 0., 0.),
+            _ => {
+                return Err(ElementError::of_string(
+                    header,
+                    &format!("unknown connector anchor '{}' for '{}'", anchor, name),
+                ));
+            }
+        };
+        Ok(Self {
+            id: id.to_string(),
+            anchor: anchor.to_string(),
+            position: Point::from_array([x, y]),
+            normal: Point::from_array([nx, ny]),
+        })
+    }
+
+    //mp find_bbox
+    /// Find the bounding box of the element this end is attached to
+    fn find_bbox(
+        &self,
+        header: &ElementHeader,
+        anchors: &ConnectorAnchors,
+    ) -> Result<BBox, ElementError> {
+        anchors
+            .find(&self.id)
+            .ok_or_else(|| ElementError::unknown_id(header, &self.id))
+    }
+
+    //mp has_stub
+    /// Return true if the connector leaves the anchor along a normal
+    fn has_stub(&self) -> bool {
+        direction_of_normal(&self.normal).is_some()
+    }
+
+    //mp point_and_normal
+    /// Get the point on a bounding box for the anchor, and the normal
+    /// to leave it by
+    fn point_and_normal(&self, bbox: &BBox) -> (Point, Point) {
+        let (c, w, h) = bbox.get_cwh();
+        let offset = Point::from_array([self.position[0] * w * 0.5, self.position[1] * h * 0.5]);
+        (c + offset, self.normal)
+    }
+
+    //zz All done
+}
+
+//a Connector element
+//tp Connector - an Element that connects two other elements with a path
+/// A connector has no desired geometry; once the diagram has been
+/// laid out its ends are resolved to anchors of elements in the same
+/// layout, and it is routed Manhattan-style between those anchors
+/// around the other elements of the layout.
+///
+/// The route is rendered as a [Path], and so supports markers and
+/// rounding.
+#[derive(Debug)]
+pub struct Connector {
+    pub from: ConnectorEnd,
+    pub to: ConnectorEnd,
+    pub path: Path,
+}
+
+//ip DiagramElementContent for Connector
+impl<'a, 'b> DiagramElementContent<'a, 'b> for Connector {
+    //fp new
+    fn new(header: &ElementHeader, _name: el::Typ) -> Result<Self, ElementError> {
+        Ok(Self {
+            from: ConnectorEnd::default(),
+            to: ConnectorEnd::default(),
+            path: Path::new(header, el::Typ::Path)?,
+        })
+    }
+
+    //fp clone
+    /// Clone element given clone of header within scope
+    fn clone(&self, header: &ElementHeader, _scope: &ElementScope) -> Result<Self, ElementError> {
+        let clone = Self::new(header, el::Typ::Clone)?;
+        Ok(clone)
+    }
+
+    //fp get_style_names
+    fn get_style_names<'z>(_name: &str) -> Vec<&'z str> {
+        vec![
+            at::FROM,
+            at::TO,
+            at::STROKE,
+            at::STROKEWIDTH,
+            at::ROUND,
+            at::MARKERS,
+        ]
+    }
+
+    //mp style
+    fn style(
+        &mut self,
+        descriptor: &DiagramDescriptor,
+        header: &ElementHeader,
+    ) -> Result<(), ElementError> {
+        self.path.style(descriptor, header)?;
+        self.from = ConnectorEnd::of_style(header, at::FROM)?;
+        self.to = ConnectorEnd::of_style(header, at::TO)?;
+        Ok(())
+    }
+
+    //mp display
+    /// Display - using indent_str + 2 indent, or an indent of indent spaces
+    /// Content should be invoked with indent+4
+    fn display(&self, _indent: usize, indent_str: &str) {
+        println!(
+            "{}  connector {}.{} to {}.{}",
+            indent_str, self.from.id, self.from.anchor, self.to.id, self.to.anchor
+        );
+    }
+
+    //zz All done
+}
+
+//ip Connector
+impl Connector {
+    //mp resolve
+    /// Resolve the ends of the connector using the anchors of the
+    /// layout it is within, and route the path between them
+    ///
+    /// The path coordinates are the absolute coordinates of the route
+    /// in the layout, so the path is given a center of the origin and
+    /// a width and height of 2
+    pub fn resolve(
+        &mut self,
+        header: &ElementHeader,
+        anchors: &ConnectorAnchors,
+    ) -> Result<(), ElementError> {
+        let from_bbox = self.from.find_bbox(header, anchors)?;
+        let to_bbox = self.to.find_bbox(header, anchors)?;
+        let obstacles = anchors.obstacles_excluding(&[
+            (from_bbox, self.from.has_stub()),
+            (to_bbox, self.to.has_stub()),
+        ]);
+        let start = self.from.point_and_normal(&from_bbox);
+        let end = self.to.point_and_normal(&to_bbox);
+        self.path.coords = route_manhattan(start, end, &obstacles, ROUTE_CLEARANCE);
+        self.path.center = Point::zero();
+        self.path.width = 2.;
+        self.path.height = 2.;
+        Ok(())
+    }
+
+    //mp as_bezier_path
+    /// Get the route of the connector, once it has been resolved
+    pub fn as_bezier_path(&self) -> Option<BezierPath> {
+        if self.path.coords.is_empty() {
+            None
+        } else {
+            Some(self.path.as_bezier_path())
+        }
+    }
+
+    //zz All done
+}
+
+//ip GenerateSvgElement for Connector
+impl GenerateSvgElement for Connector {
+    fn generate_svg(&self, svg: &mut Svg, header: &ElementHeader) -> Result<(), SvgError> {
+        self.path.generate_svg(svg, header)
+    }
+}
+
+//ti IndentedDisplay for Connector
+impl<'a> IndentedDisplay<'a, IndentOptions> for Connector {
+    fn indent(&self, ind: &mut Indenter<'_, IndentOptions>) -> std::fmt::Result {
+        use std::fmt::Write;
+        writeln!(ind, "Connector")?;
+        let mut sub = ind.sub();
+        writeln!(&mut sub, "from    : {} {}", self.from.id, self.from.anchor)?;
+        writeln!(&mut sub, "to      : {} {}", self.to.id, self.to.anchor)?;
+        self.path.indent(&mut sub)?;
+        Ok(())
+    }
+}
+
+//a Routing
+//fi bbox_contains
+/// Return true if `outer` contains all of `inner`
+fn bbox_contains(outer: &BBox, inner: &BBox) -> bool {
+    outer.x.min() <= inner.x.min() + ROUTE_EPSILON
+        && outer.x.max() >= inner.x.max() - ROUTE_EPSILON
+        && outer.y.min() <= inner.y.min() + ROUTE_EPSILON
+        && outer.y.max() >= inner.y.max() - ROUTE_EPSILON
+}
+
+//fi sorted_coords
+/// Sort and remove duplicates from a set of route coordinates
+fn sorted_coords(mut v: Vec<f64>) -> Vec<f64> {
+    v.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    v.dedup_by(|a, b| (*a - *b).abs() < ROUTE_EPSILON);
+    v
+}
+
+//fi index_of_coord
+fn index_of_coord(v: &[f64], c: f64) -> usize {
+    v.iter()
+        .position(|x| (x - c).abs() < ROUTE_EPSILON)
+        .unwrap()
+}
+
+//fi direction_of_normal
+/// Map a normal to one of the four route directions (+X, -X, +Y, -Y)
+fn direction_of_normal(normal: &Point) -> Option<usize> {
+    if normal[0] > 0.5 {
+        Some(0)
+    } else if normal[0] < -0.5 {
+        Some(1)
+    } else if normal[1] > 0.5 {
+        Some(2)
+    } else if normal[1] < -0.5 {
+        Some(3)
+    } else {
+        None
+    }
+}
+
+//fi turn_cost
+/// The cost of turning from one route direction to another, or None
+/// if the route would double back
+fn turn_cost(from: Option<usize>, to: usize) -> Option<f64> {
+    match from {
+        Some(f) if (f ^ 1) == to => None,
+        Some(f) if f != to => Some(ROUTE_BEND_COST),
+        _ => Some(0.),
+    }
+}
+
+//tp RouteCost
+/// A cost and state for the route search; ordered so that the
+/// [BinaryHeap] pops the lowest cost first
+#[derive(Debug, PartialEq)]
+struct RouteCost(f64, usize);
+impl Eq for RouteCost {}
+impl Ord for RouteCost {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.partial_cmp(&self.0).unwrap_or(Ordering::Equal)
+    }
+}
+impl PartialOrd for RouteCost {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+//fi simplify_route
+/// Remove repeated points and the middle of any three collinear points
+fn simplify_route(pts: Vec<Point>) -> Vec<Point> {
+    let mut result: Vec<Point> = Vec::new();
+    for p in pts {
+        if let Some(l) = result.last() {
+            if (l[0] - p[0]).abs() < ROUTE_EPSILON && (l[1] - p[1]).abs() < ROUTE_EPSILON {
+                continue;
+            }
+        }
+        let n = result.len();
+        if n >= 2 {
+            let (a, b) = (result[n - 2], result[n - 1]);
+            let same_x = (a[0] - b[0]).abs() < ROUTE_EPSILON && (b[0] - p[0]).abs() < ROUTE_EPSILON;
+            let same_y = (a[1] - b[1]).abs() < ROUTE_EPSILON && (b[1] - p[1]).abs() < ROUTE_EPSILON;
+            if same_x || same_y {
+                result[n - 1] = p;
+                continue;
+            }
+        }
+        result.push(p);
+    }
+    result
+}
+
+//tp Router
+/// A router for a single connector, with the obstacles expanded by
+/// half of the clearance
+struct Router {
+    blocked: Vec<BBox>,
+}
+
+//ip Router
+impl Router {
+    //fp new
+    fn new(obstacles: &[BBox], clearance: f64) -> Self {
+        let blocked = obstacles
+            .iter()
+            .map(|o| o.enlarge(clearance * 0.5))
+            .collect();
+        Self { blocked }
+    }
+
+    //mp is_inside
+    /// Return true if a point is strictly within an obstacle
+    fn is_inside(&self, p: &Point) -> bool {
+        self.is_segment_blocked(p, p)
+    }
+
+    //mp is_segment_blocked
+    /// Return true if a horizontal or vertical segment passes through
+    /// the interior of an obstacle
+    fn is_segment_blocked(&self, p0: &Point, p1: &Point) -> bool {
+        let (x0, x1) = (p0[0].min(p1[0]), p0[0].max(p1[0]));
+        let (y0, y1) = (p0[1].min(p1[1]), p0[1].max(p1[1]));
+        self.blocked.iter().any(|b| {
+            x1 > b.x.min() + ROUTE_EPSILON
+                && x0 < b.x.max() - ROUTE_EPSILON
+                && y1 > b.y.min() + ROUTE_EPSILON
+                && y0 < b.y.max() - ROUTE_EPSILON
+        })
+    }
+
+    //mp route_cost
+    /// Find the cost of a simplified route between the ends of the
+    /// stubs - its length plus a cost per bend, including those from
+    /// and to the stubs - or None if it is blocked or doubles back on
+    /// itself
+    fn route_cost(
+        &self,
+        pts: &[Point],
+        start_dirn: Option<usize>,
+        end_dirn: Option<usize>,
+    ) -> Option<f64> {
+        let mut cost = 0.;
+        let mut last_dirn = start_dirn;
+        for i in 1..pts.len() {
+            let d = pts[i] - pts[i - 1];
+            let dirn = direction_of_normal(&d.normalize())?;
+            if self.is_segment_blocked(&pts[i - 1], &pts[i]) {
+                return None;
+            }
+            cost += turn_cost(last_dirn, dirn)? + d[0].abs() + d[1].abs();
+            last_dirn = Some(dirn);
+        }
+        if let Some(dirn) = end_dirn {
+            cost += turn_cost(last_dirn, dirn)?;
+        }
+        Some(cost)
+    }
+
+    //mp search
+    /// Search for the lowest cost route between the ends of two
+    /// stubs across a grid formed from those ends and the edges of the
+    /// obstacles (expanded by the clearance)
+    ///
+    /// The directions are those of the stubs; the route must leave
+    /// the start in that direction and arrive at the end in that
+    /// direction, or pay the cost of a bend
+    fn search(
+        &self,
+        s1: Point,
+        start_dirn: Option<usize>,
+        e1: Point,
+        end_dirn: Option<usize>,
+        obstacles: &[BBox],
+        clearance: f64,
+    ) -> Option<Vec<Point>> {
+        if self.is_inside(&s1) || self.is_inside(&e1) {
+            return None;
+        }
+        let mut xs = vec![s1[0], e1[0], (s1[0] + e1[0]) * 0.5];
+        let mut ys = vec![s1[1], e1[1], (s1[1] + e1[1]) * 0.5];
+        for o in obstacles {
+            xs.push(o.x.min() - clearance);
+            xs.push(o.x.max() + clearance);
+            ys.push(o.y.min() - clearance);
+            ys.push(o.y.max() + clearance);
+        }
+        let xs = sorted_coords(xs);
+        let ys = sorted_coords(ys);
+        let (nx, ny) = (xs.len(), ys.len());
+        let grid_pt = |i: usize, j: usize| Point::from_array([xs[i], ys[j]]);
+
+        // States are (node, direction of arrival), with direction 4 for 'none'
+        let state = |i: usize, j: usize, d: usize| ((j * nx + i) * 5) + d;
+        let start_node = (index_of_coord(&xs, s1[0]), index_of_coord(&ys, s1[1]));
+        let end_node = (index_of_coord(&xs, e1[0]), index_of_coord(&ys, e1[1]));
+        let mut cost = vec![f64::MAX; nx * ny * 5];
+        let mut prev = vec![usize::MAX; nx * ny * 5];
+        let mut heap = BinaryHeap::new();
+        let mut best: Option<(f64, usize)> = None;
+        let s = state(start_node.0, start_node.1, start_dirn.unwrap_or(4));
+        cost[s] = 0.;
+        heap.push(RouteCost(0., s));
+        while let Some(RouteCost(c, s)) = heap.pop() {
+            if c > cost[s] {
+                continue;
+            }
+            if let Some((b, _)) = best {
+                if c >= b {
+                    break;
+                }
+            }
+            let d = s % 5;
+            let (i, j) = ((s / 5) % nx, (s / 5) / nx);
+            if (i, j) == end_node {
+                let penalty = match end_dirn {
+                    Some(ed) if d != 4 && d != ed => ROUTE_BEND_COST,
+                    _ => 0.,
+                };
+                if best.is_none_or(|(b, _)| c + penalty < b) {
+                    best = Some((c + penalty, s));
+                }
+            }
+            let mut neighbors = Vec::new();
+            if i + 1 < nx {
+                neighbors.push((i + 1, j, 0));
+            }
+            if i > 0 {
+                neighbors.push((i - 1, j, 1));
+            }
+            if j + 1 < ny {
+                neighbors.push((i, j + 1, 2));
+            }
+            if j > 0 {
+                neighbors.push((i, j - 1, 3));
+            }
+            for (ni, nj, nd) in neighbors {
+                // Do not double back along the route
+                if d != 4 && (d ^ 1) == nd {
+                    continue;
+                }
+                let (p0, p1) = (grid_pt(i, j), grid_pt(ni, nj));
+                if self.is_inside(&p1) || self.is_segment_blocked(&p0, &p1) {
+                    continue;
+                }
+                let bend = if d != 4 && d != nd {
+                    ROUTE_BEND_COST
+                } else {
+                    0.
+                };
+                let ns = state(ni, nj, nd);
+                let nc = c + (p1[0] - p0[0]).abs() + (p1[1] - p0[1]).abs() + bend;
+                if nc < cost[ns] {
+                    cost[ns] = nc;
+                    prev[ns] = s;
+                    heap.push(RouteCost(nc, ns));
+                }
+            }
+        }
+
+        let (_, mut s) = best?;
+        let mut route = Vec::new();
+        loop {
+            route.push(grid_pt((s / 5) % nx, (s / 5) / nx));
+            if prev[s] == usize::MAX {
+                break;
+            }
+            s = prev[s];
+        }
+        route.reverse();
+        Some(route)
+    }
+
+    //zz All done
+}
+
+//fp route_manhattan
+/// Route a path of horizontal and vertical segments from a start
+/// point and normal to an end point and normal, avoiding the
+/// obstacles
+///
+/// The route leaves the start (and arrives at the end) with a stub
+/// of length `clearance` along the normal. Between the stubs the
+/// simple three-segment routes (bending halfway across or halfway
+/// down) are preferred; if neither is clear, or a route with fewer
+/// bends is, then the lowest cost route across a grid is used.
+pub fn route_manhattan(
+    start: (Point, Point),
+    end: (Point, Point),
+    obstacles: &[BBox],
+    clearance: f64,
+) -> Vec<Point> {
+    let router = Router::new(obstacles, clearance);
+    let (s0, e0) = (start.0, end.0);
+    let s1 = start.0 + start.1 * clearance;
+    let e1 = end.0 + end.1 * clearance;
+    let start_dirn = direction_of_normal(&start.1);
+    // The route arrives at the end travelling opposite to its normal
+    let end_dirn = direction_of_normal(&(-end.1));
+
+    let mx = (s1[0] + e1[0]) * 0.5;
+    let my = (s1[1] + e1[1]) * 0.5;
+    let across = vec![
+        s1,
+        Point::from_array([mx, s1[1]]),
+        Point::from_array([mx, e1[1]]),
+        e1,
+    ];
+    let down = vec![
+        s1,
+        Point::from_array([s1[0], my]),
+        Point::from_array([e1[0], my]),
+        e1,
+    ];
+    let mut candidates = vec![simplify_route(across), simplify_route(down)];
+    if let Some(route) = router.search(s1, start_dirn, e1, end_dirn, obstacles, clearance) {
+        candidates.push(simplify_route(route));
+    }
+    let mut best: Option<(f64, usize)> = None;
+    for (i, c) in candidates.iter().enumerate() {
+        if let Some(cost) = router.route_cost(c, start_dirn, end_dirn) {
+            if best.is_none_or(|(b, _)| cost < b - ROUTE_EPSILON) {
+                best = Some((cost, i));
+            }
+        }
+    }
+    let route = match best {
+        Some((_, i)) => candidates.swap_remove(i),
+        None if start_dirn.is_none_or(|d| d >= 2) => candidates.swap_remove(1),
+        None => candidates.swap_remove(0),
+    };
+    let mut pts = vec![s0];
+    pts.extend(route);
+    pts.push(e0);
+    simplify_route(pts)
+}
+
+//a Test
+#[cfg(test)]
+mod test_route {
+    use super::*;
+    fn pt(x: f64, y: f64) -> Point {
+        Point::from_array([x, y])
+    }
+    fn check_route(route: &[Point], exp: &[(f64, f64)]) {
+        assert_eq!(
+            route.len(),
+            exp.len(),
+            "Route {:?} expected {:?}",
+            route,
+            exp
+        );
+        for (r, e) in route.iter().zip(exp.iter()) {
+            assert!(
+                (r[0] - e.0).abs() < 1E-6 && (r[1] - e.1).abs() < 1E-6,
+                "Route {:?} expected {:?}",
+                route,
+                exp
+            );
+        }
+    }
+    //ft test_straight
+    #[test]
+    fn test_straight() {
+        let route = route_manhattan(
+            (pt(0., 0.), pt(1., 0.)),
+            (pt(20., 0.), pt(-1., 0.)),
+            &[],
+            2.,
+        );
+        check_route(&route, &[(0., 0.), (20., 0.)]);
+    }
+    //ft test_dogleg
+    #[test]
+    fn test_dogleg() {
+        let route = route_manhattan(
+            (pt(0., 0.), pt(1., 0.)),
+            (pt(20., 10.), pt(-1., 0.)),
+            &[],
+            2.,
+        );
+        check_route(&route, &[(0., 0.), (10., 0.), (10., 10.), (20., 10.)]);
+    }
+    //ft test_obstacle
+    #[test]
+    fn test_obstacle() {
+        let route = route_manhattan(
+            (pt(0., 0.), pt(1., 0.)),
+            (pt(20., 0.), pt(-1., 0.)),
+            &[BBox::new(8., -5., 12., 3.)],
+            2.,
+        );
+        assert_eq!(
+            route.len(),
+            6,
+            "Route {:?} should go around the obstacle",
+            route
+        );
+        for p in &route {
+            assert!(p[0] <= 8. || p[0] >= 12. || p[1] <= -5. || p[1] >= 3.);
+        }
+        assert!(
+            (route[2][1] - 5.).abs() < 1E-6,
+            "Route {:?} should pass below",
+            route
+        );
+    }
+}
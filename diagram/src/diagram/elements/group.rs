@@ -22,11 +22,15 @@ use indent_display::{IndentedDisplay, Indenter};
 use stylesheet::{StylableNode, Tree};
 use vg_rs::grid::GridData;
 use vg_rs::layout::{Layout, LayoutRecord};
-use vg_rs::{BBox, Point};
+use vg_rs::{BBox, Point, Transform};
 
+use super::ConnectorAnchors;
 use crate::constants::attributes as at;
 use crate::constants::elements as el;
-use crate::diagram::{DiagramElementContent, Element, ElementError, ElementHeader, ElementScope};
+use crate::diagram::{
+    DiagramElementContent, Element, ElementError, ElementErrorList, Flow, GridAreas,
+};
+use crate::diagram::{ElementHeader, ElementScope};
 use crate::diagram::{GenerateSvg, GenerateSvgElement, Svg, SvgElement, SvgError};
 use crate::{DiagramDescriptor, IndentOptions};
 
@@ -57,6 +61,14 @@ pub struct Group<'a> {
     layout_record: Option<LayoutRecord>,
     x_cell_data: Vec<GridData<usize>>,
     y_cell_data: Vec<GridData<usize>>,
+    /// The ids of the grid cell boundaries given by the cell data
+    x_cell_ids: Vec<String>,
+    y_cell_ids: Vec<String>,
+    /// The flow of the content along rows or columns, if the layout
+    /// has a direction
+    flow: Option<Flow>,
+    /// The named areas of the grid of the layout
+    areas: GridAreas,
     bbox: BBox,
 
     // For markers ONLY
@@ -93,6 +105,10 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Group<'a> {
             layout_record: None,
             x_cell_data: Vec::new(),
             y_cell_data: Vec::new(),
+            x_cell_ids: Vec::new(),
+            y_cell_ids: Vec::new(),
+            flow: None,
+            areas: GridAreas::default(),
             bbox: BBox::none(),
             ref_pt: Point::zero(), // for markers
             relief: (0., 0.),
@@ -124,6 +140,10 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Group<'a> {
             layout_record: None,
             x_cell_data: Vec::new(),
             y_cell_data: Vec::new(),
+            x_cell_ids: Vec::new(),
+            y_cell_ids: Vec::new(),
+            flow: None,
+            areas: GridAreas::default(),
             bbox: BBox::none(),
             ref_pt: Point::zero(), // for markers
             relief: (0., 0.),
@@ -144,9 +164,10 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Group<'a> {
         _header: &ElementHeader<'a>,
         scope: &ElementScope<'a, 'b>,
         mut uid: usize,
+        errors: &mut ElementErrorList,
     ) -> Result<(bool, usize), ElementError> {
         for e in self.content.iter_mut() {
-            uid = e.uniquify(scope, uid)?;
+            uid = e.uniquify(scope, uid, errors);
         }
         Ok((false, uid))
     }
@@ -154,11 +175,24 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Group<'a> {
     //fp get_style_names
     /// Get the style descriptor for this element when referenced by the name
     ///
-    /// Layout supports minx/miny cell size descriptions
+    /// Layout supports minx/miny cell size descriptions, and the flow
+    /// of its content along rows or columns
     fn get_style_names<'z>(name: &str) -> Vec<&'z str> {
         match name {
             el::GROUP => vec![],
-            el::LAYOUT => vec![at::MINX, at::MINY],
+            el::LAYOUT => vec![
+                at::MINX,
+                at::MINY,
+                at::EQUALX,
+                at::EQUALY,
+                at::ASPECT,
+                at::AREAS,
+                at::DIRECTION,
+                at::GAP,
+                at::WRAPCOUNT,
+                at::JUSTIFY,
+                at::ALIGN,
+            ],
             _ => vec![
                 at::MINX,
                 at::MINY,
@@ -174,9 +208,12 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Group<'a> {
     //mp style
     /// Style the element within the Diagram's descriptor, using the
     /// header if required to extract styles
+    ///
+    /// The content of the group is styled afterwards with
+    /// `style_content`
     fn style(
         &mut self,
-        descriptor: &DiagramDescriptor,
+        _descriptor: &DiagramDescriptor,
         header: &ElementHeader,
     ) -> Result<(), ElementError> {
         if let Some(v) = header
@@ -191,9 +228,39 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Group<'a> {
         {
             self.y_cell_data = self.read_cell_data(false, header, v)?;
         }
+        for (x, name) in [(true, at::EQUALX), (false, at::EQUALY)] {
+            if let Some(v) = header
+                .get_style_value_of_name(name)
+                .and_then(|x| x.as_vec_str())
+            {
+                // The ratios are added to the layout only once, as
+                // its desired geometry may be calculated many times
+                let ratios = self.read_equal_spans(x, header, name, v)?;
+                let layout = self.layout.as_mut().unwrap();
+                if x {
+                    layout.add_cell_data(&ratios, &[]);
+                } else {
+                    layout.add_cell_data(&[], &ratios);
+                }
+            }
+        }
+        if let Some(v) = header
+            .get_style_value_of_name(at::ASPECT)
+            .and_then(|x| x.as_vec_str())
+        {
+            let aspects = self.read_spans(header, at::ASPECT, &[true, false, true, false], v)?;
+            let layout = self.layout.as_mut().unwrap();
+            for (ratio, ids) in aspects {
+                layout.add_aspect((ids[0], ids[1]), (ids[2], ids[3]), ratio);
+            }
+        }
         if let Some(layout) = &mut self.layout {
             layout.set_grid_expand(true, header.layout.expand[0]);
             layout.set_grid_expand(false, header.layout.expand[1]);
+            if self.group_type == GroupType::Layout {
+                self.areas = GridAreas::of_header(header)?;
+                self.flow = Flow::of_header(header)?;
+            }
         } else {
             if header.layout.expand[0] != 0. {
                 return Err(ElementError::of_string(header, &format!("X Expand {} specified for element id {} but it is not directly part of a Layout", header.layout.expand[0], header.borrow_id())));
@@ -239,9 +306,6 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Group<'a> {
         self.height = header
             .get_style_of_name_float(at::HEIGHT, Some(self.width))
             .unwrap();
-        for e in self.content.iter_mut() {
-            e.style(descriptor)?;
-        }
         Ok(())
     }
 
@@ -378,6 +442,7 @@ impl<'a> Group<'a> {
         }
         let layout = self.layout.as_mut().unwrap();
         let mut result = Vec::new();
+        let mut cell_ids = Vec::new();
         let mut last_element = 0;
         let mut expecting_data = false;
         let mut pending_min_size = None;
@@ -424,6 +489,7 @@ impl<'a> Group<'a> {
                     return Err(ElementError::of_string(header, &format!("grid cell data hit an id of '{}' which contained +, = or ., which are illegal in a grid cell id", s)));
                 }
                 let e = layout.add_grid_id(x, s.trim());
+                cell_ids.push(s.trim().to_string());
                 if let Some(size) = pending_min_size {
                     result.push(GridData::new_width(last_element, e, size));
                 }
@@ -436,9 +502,152 @@ impl<'a> Group<'a> {
                 pending_growth = None;
             }
         }
+        if x {
+            self.x_cell_ids = cell_ids;
+        } else {
+            self.y_cell_ids = cell_ids;
+        }
+        Ok(result)
+    }
+
+    //mp read_spans
+    /// For styling, read a list of spans of the grid, each of which is
+    /// a grid cell id for each of `axes` (true for X, false for Y),
+    /// optionally preceded by '*' and a positive ratio (which
+    /// defaults to 1)
+    pub fn read_spans(
+        &mut self,
+        header: &ElementHeader,
+        name: &str,
+        axes: &[bool],
+        v: Vec<&str>,
+    ) -> Result<Vec<(f64, Vec<usize>)>, ElementError> {
+        let bad_spans = || {
+            ElementError::of_string(
+                header,
+                &format!(
+                    "'{}' must be groups of {} grid cell ids, each optionally preceded by '*' and a positive ratio",
+                    name,
+                    axes.len()
+                ),
+            )
+        };
+        let layout = self.layout.as_mut().ok_or_else(bad_spans)?;
+        let mut result = Vec::new();
+        let mut ratio = None;
+        let mut ids = Vec::new();
+        for s in v {
+            let s = s.trim();
+            if s.starts_with('*') {
+                let (ns, r) = parse_float(header, s, 1)?;
+                if !ns.is_empty() || r <= 0. || ratio.is_some() || !ids.is_empty() {
+                    return Err(bad_spans());
+                }
+                ratio = Some(r);
+            } else {
+                ids.push(layout.add_grid_id(axes[ids.len()], s));
+                if ids.len() == axes.len() {
+                    result.push((ratio.unwrap_or(1.), std::mem::take(&mut ids)));
+                    ratio = None;
+                }
+            }
+        }
+        if ratio.is_some() || !ids.is_empty() {
+            return Err(bad_spans());
+        }
         Ok(result)
     }
 
+    //mp read_equal_spans
+    /// For styling, read a list of spans of the grid in X or Y that
+    /// must be the same size, or sizes in the ratios given, and
+    /// produce the GridData that requires each to be in its ratio to
+    /// the first
+    pub fn read_equal_spans(
+        &mut self,
+        x: bool,
+        header: &ElementHeader,
+        name: &str,
+        v: Vec<&str>,
+    ) -> Result<Vec<GridData<usize>>, ElementError> {
+        let spans = self.read_spans(header, name, &[x, x], v)?;
+        if spans.len() < 2 {
+            return Err(ElementError::of_string(
+                header,
+                &format!("'{}' must have at least two spans", name),
+            ));
+        }
+        let (first_ratio, first) = &spans[0];
+        Ok(spans[1..]
+            .iter()
+            .map(|(ratio, ids)| {
+                GridData::new_ratio(ids[0], ids[1], first[0], first[1], ratio / first_ratio)
+            })
+            .collect())
+    }
+
+    //mp is_layout
+    /// Return true if the group lays out its content
+    pub fn is_layout(&self) -> bool {
+        self.layout.is_some()
+    }
+
+    //mp constraints_met
+    /// Return true if the group does not lay out its content, or if
+    /// the ratios and aspect ratios of its grid were all met when it
+    /// was laid out
+    pub fn constraints_met(&self) -> bool {
+        self.layout.as_ref().is_none_or(|l| l.constraints_met())
+    }
+
+    //mp borrow_cell_ids
+    /// Borrow the ids of the grid cell boundaries given by the cell
+    /// data of the group for the X or Y dimension
+    pub fn borrow_cell_ids(&self, x: bool) -> &[String] {
+        if x {
+            &self.x_cell_ids
+        } else {
+            &self.y_cell_ids
+        }
+    }
+
+    //mp style_content
+    /// Style the content of the group, adding any errors to the list
+    ///
+    /// Once styled, elements with an 'area' are gridded in it; then,
+    /// if the content flows along rows or columns, each element that
+    /// is not gridded or placed is given its cell
+    pub fn style_content(&mut self, descriptor: &DiagramDescriptor, errors: &mut ElementErrorList) {
+        for e in self.content.iter_mut() {
+            e.style(descriptor, errors);
+            if self.layout.is_some() {
+                errors.update(self.areas.grid_element(
+                    &mut e.header,
+                    &self.x_cell_ids,
+                    &self.y_cell_ids,
+                ));
+            } else if e
+                .header
+                .get_style_value_of_name(at::AREA)
+                .and_then(|x| x.as_vec_str())
+                .is_some()
+            {
+                errors.add(ElementError::of_string(
+                    &e.header,
+                    &format!(
+                        "Area specified for element id {} but it is not directly part of a Layout",
+                        e.header.borrow_id()
+                    ),
+                ));
+            }
+        }
+        if let (Some(flow), Some(layout)) = (&self.flow, &mut self.layout) {
+            let (x_cell_data, y_cell_data) = flow.flow_content(layout, &mut self.content);
+            self.x_cell_data.extend(x_cell_data);
+            self.y_cell_data.extend(y_cell_data);
+        }
+    }
+
     //mp add_element
     /// Add an element to the group; moves the element in to the content
     pub fn add_element(&mut self, element: Element<'a>) {
@@ -454,6 +663,99 @@ impl<'a> Group<'a> {
         }
         tree
     }
+    //mp add_anchors
+    /// Add the anchors of the content of the group, returning the
+    /// bounding box of the group
+    ///
+    /// The content of a layout is within the content coordinates of
+    /// the layout, which require the layout's transform; the content
+    /// of a simple group is in the coordinates of the group's parent
+    pub fn add_anchors(
+        &self,
+        header: &ElementHeader<'a>,
+        transform: &Transform,
+        anchors: &mut ConnectorAnchors,
+    ) -> BBox {
+        if self.layout.is_some() {
+            let content_transform = header
+                .layout_box
+                .content_transform()
+                .map_or(*transform, |t| transform.apply_to_transform(t));
+            for e in self.content.iter() {
+                e.add_anchors(&content_transform, false, anchors);
+            }
+            header.laid_out_bbox().transform(transform)
+        } else {
+            let mut bbox = BBox::none();
+            for e in self.content.iter() {
+                bbox = bbox.union(e.add_anchors(transform, false, anchors));
+            }
+            bbox
+        }
+    }
+
+    //mi layout_anchors
+    /// Build the anchors of the content of a layout, in its content
+    /// coordinates
+    fn layout_anchors(&self) -> ConnectorAnchors {
+        let mut layout_anchors = ConnectorAnchors::default();
+        let transform = Transform::default();
+        for e in self.content.iter() {
+            e.add_anchors(&transform, true, &mut layout_anchors);
+        }
+        layout_anchors
+    }
+
+    //mp resolve_connectors
+    /// Resolve the connectors in the content; a layout provides the
+    /// anchors for its content, a simple group uses those of its parent
+    pub fn resolve_connectors(
+        &mut self,
+        anchors: &ConnectorAnchors,
+        errors: &mut ElementErrorList,
+    ) {
+        if self.layout.is_some() {
+            let layout_anchors = self.layout_anchors();
+            for e in self.content.iter_mut() {
+                e.resolve_connectors(&layout_anchors, errors);
+            }
+        } else {
+            for e in self.content.iter_mut() {
+                e.resolve_connectors(anchors, errors);
+            }
+        }
+    }
+
+    //mp resolve_labels
+    /// Resolve the labels in the content, after the connectors have
+    /// been resolved so that labels may be attached to them, and
+    /// return the bounding box of the labels in the coordinates of
+    /// the group's parent
+    pub fn resolve_labels(
+        &mut self,
+        header: &ElementHeader<'a>,
+        anchors: &ConnectorAnchors,
+        errors: &mut ElementErrorList,
+    ) -> BBox {
+        let mut bbox = BBox::none();
+        if self.layout.is_some() {
+            let layout_anchors = self.layout_anchors();
+            for e in self.content.iter_mut() {
+                bbox = bbox.union(e.resolve_labels(&layout_anchors, errors));
+            }
+            if let Some(transform) = header.layout_box.content_transform() {
+                if !bbox.is_none() {
+                    bbox = bbox.transform(transform);
+                }
+            }
+        } else {
+            for e in self.content.iter_mut() {
+                bbox = bbox.union(e.resolve_labels(anchors, errors));
+            }
+        }
+        bbox
+    }
+
     //mp get_relief
     pub fn get_relief(&self, index: usize) -> f64 {
         if index == 0 {
@@ -571,3 +873,38 @@ impl<'a, 'diag> IndentedDisplay<'a, IndentOptions> for Group<'diag> {
         Ok(())
     }
 }
+
+//a Test
+#[cfg(test)]
+mod tests {
+    use crate::diagram::test_utils::layout_content;
+
+    #[test]
+    fn test_equal_aspect() {
+        let boxes = layout_content(
+            "#diagram ##layout equalx=1,2,2,3 aspect=1,1,2,2 ###rect grid=1,1 width=10 height=4 ###rect grid=2,1 width=4 height=2 ###rect grid=1,2 width=1 height=1",
+        )
+        .unwrap();
+        assert_eq!(
+            boxes,
+            vec![
+                (-10., 0., -2.5, 1.5),
+                (3., 7., -1.5, 0.5),
+                (-5.5, -4.5, 4.5, 5.5)
+            ]
+        );
+        let boxes = layout_content(
+            "#diagram ##layout equalx=*2,1,2,*1,2,3 ###rect grid=1,1 width=4 height=2 ###rect grid=2,1 width=4 height=2",
+        )
+        .unwrap();
+        assert_eq!(boxes, vec![(-4., 0., -1., 1.), (2., 6., -1., 1.)]);
+    }
+
+    #[test]
+    fn test_equal_errors() {
+        let error = |text| layout_content(text).unwrap_err()[0].clone();
+        assert!(error("#diagram ##layout equalx=1,2").contains("at least two spans"));
+        assert!(error("#diagram ##layout equalx=1,2,3").contains("groups of 2 grid cell ids"));
+        assert!(error("#diagram ##layout aspect=*0,1,1,2,2").contains("positive ratio"));
+    }
+}
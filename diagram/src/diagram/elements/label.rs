@@ -0,0 +1,365 @@
+/*a Copyright
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+
+@file    label.rs
+@brief   Diagram label element
+ */
+
+//a Imports
+use geo_nd::Vector;
+use indent_display::{IndentedDisplay, Indenter};
+use vg_rs::layout::Layout;
+use vg_rs::{BBox, Bezier, BezierPath, Point, Transform};
+
+use super::super::IndentOptions;
+use super::super::{
+    DiagramDescriptor, DiagramElementContent, ElementError, ElementHeader, ElementScope,
+};
+use super::super::{GenerateSvgElement, Svg, SvgElement, SvgError};
+use super::text::TextError;
+use super::{ConnectorAnchors, Text};
+use crate::constants::attributes as at;
+use crate::constants::elements as el;
+
+//a Constants
+/// Straightness used in finding the length along a path
+const LABEL_STRAIGHTNESS: f64 = 1E-2;
+
+/// Padding between the text of a label and its bubble, and the
+/// distance the leader line stops short of the text
+const LABEL_PAD: f64 = 1.;
+
+//a Callout
+//tp Callout
+/// The style of callout from the reference point to the text of a label
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Callout {
+    /// No callout - just the text
+    None,
+    /// A leader line from the reference point to the text
+    Line,
+    /// A leader line and a (rounded) box around the text
+    Bubble,
+}
+
+//ip Callout
+impl Callout {
+    //fp of_str
+    fn of_str(s: &str) -> Option<Self> {
+        match s {
+            "none" => Some(Self::None),
+            "line" => Some(Self::Line),
+            "bubble" => Some(Self::Bubble),
+            _ => None,
+        }
+    }
+}
+
+//a Label element
+//tp Label - an Element that labels a point on another element
+/// A label has no desired geometry; once the diagram has been laid
+/// out it is attached to a reference point on the element `target`
+/// in the same layout. For a path, shape or connector the reference
+/// point is the fraction `along` the length of its outline; for
+/// other elements it is the center of their bounding box.
+///
+/// The text is placed centered at `offset` from the reference point,
+/// with a callout (leader line, or line and bubble) back to the
+/// reference point.
+///
+/// As it has no desired geometry, the bounds of the diagram are
+/// extended to include the label once it is resolved.
+#[derive(Debug)]
+pub struct Label {
+    pub target: String,
+    pub along: f64,
+    pub offset: Point,
+    pub callout: Callout,
+    pub stroke: Option<(f64, f64, f64)>,
+    pub stroke_width: f64,
+    pub round: f64,
+    pub text: Text,
+    /// Reference point in the coordinates of the layout, once resolved
+    reference: Point,
+    /// Bounding box of the text in the coordinates of the layout, once resolved
+    text_bbox: BBox,
+}
+
+//ip DiagramElementContent for Label
+impl<'a, 'b> DiagramElementContent<'a, 'b> for Label {
+    //fp new
+    fn new(header: &ElementHeader, _name: el::Typ) -> Result<Self, ElementError> {
+        Ok(Self {
+            target: String::new(),
+            along: 0.5,
+            offset: Point::zero(),
+            callout: Callout::Line,
+            stroke: None,
+            stroke_width: 0.,
+            round: 0.,
+            text: Text::new(header, el::Typ::Text)?,
+            reference: Point::zero(),
+            text_bbox: BBox::none(),
+        })
+    }
+
+    //fp clone
+    /// Clone element given clone of header within scope
+    fn clone(&self, header: &ElementHeader, scope: &ElementScope) -> Result<Self, ElementError> {
+        let mut clone = Self::new(header, el::Typ::Clone)?;
+        clone.text = DiagramElementContent::clone(&self.text, header, scope)?;
+        Ok(clone)
+    }
+
+    //fp get_style_names
+    fn get_style_names<'z>(name: &str) -> Vec<&'z str> {
+        let mut names = vec![
+            at::TARGET,
+            at::ALONG,
+            at::OFFSET,
+            at::CALLOUT,
+            at::STROKE,
+            at::STROKEWIDTH,
+            at::ROUND,
+        ];
+        names.extend(<Text as DiagramElementContent>::get_style_names(name));
+        names
+    }
+
+    //mp style
+    fn style(
+        &mut self,
+        descriptor: &DiagramDescriptor,
+        header: &ElementHeader,
+    ) -> Result<(), ElementError> {
+        self.text.style(descriptor, header)?;
+        self.target = header
+            .get_style_of_name_string(at::TARGET)
+            .ok_or_else(|| ElementError::of_string(header, "label requires a 'target'"))?;
+        self.along = header
+            .get_style_of_name_float(at::ALONG, Some(0.5))
+            .unwrap();
+        let mut floats = [0.; 4];
+        if let Some(v) = header
+            .get_style_value_of_name(at::OFFSET)
+            .and_then(|x| x.as_floats(&mut floats))
+        {
+            self.offset = Point::from_array([v[0], v[1]]);
+        }
+        if let Some(callout) = header.get_style_of_name_string(at::CALLOUT) {
+            self.callout = Callout::of_str(&callout).ok_or_else(|| {
+                ElementError::of_string(header, &format!("unknown label callout '{}'", callout))
+            })?;
+        }
+        if let Some(v) = header
+            .get_style_value_of_name(at::STROKE)
+            .and_then(|x| x.as_floats(&mut floats))
+        {
+            self.stroke = Some((v[0], v[1], v[2]));
+        }
+        self.stroke_width = header
+            .get_style_of_name_float(at::STROKEWIDTH, Some(0.))
+            .unwrap();
+        self.round = header.get_style_of_name_float(at::ROUND, Some(0.)).unwrap();
+        Ok(())
+    }
+
+    //mp get_desired_geometry
+    fn get_desired_geometry(&mut self, _layout: &mut Layout) -> BBox {
+        BBox::none()
+    }
+
+    //mp display
+    /// Display - using indent_str + 2 indent, or an indent of indent spaces
+    /// Content should be invoked with indent+4
+    fn display(&self, indent: usize, indent_str: &str) {
+        println!(
+            "{}  label {} along {} offset {}",
+            indent_str, self.target, self.along, self.offset
+        );
+        self.text.display(indent, indent_str);
+    }
+
+    //zz All done
+}
+
+//ip Label
+impl Label {
+    //mp add_string
+    pub fn add_string(&mut self, s: &str) -> Result<(), TextError> {
+        self.text.add_string(s)
+    }
+
+    //mp resolve
+    /// Resolve the reference point of the label using the anchors of
+    /// the layout it is within, and hence the position of its text
+    pub fn resolve(
+        &mut self,
+        header: &ElementHeader,
+        anchors: &ConnectorAnchors,
+    ) -> Result<(), ElementError> {
+        self.reference = match anchors.find_path(&self.target) {
+            Some(path) => path.point_at_fraction(LABEL_STRAIGHTNESS, self.along).0,
+            None => {
+                let bbox = anchors
+                    .find(&self.target)
+                    .ok_or_else(|| ElementError::unknown_id(header, &self.target))?;
+                bbox.get_cwh().0
+            }
+        };
+        let (w, h) = self.text.text_area.get_bbox();
+        self.text_bbox = BBox::of_cwh(self.reference + self.offset, w, h);
+        Ok(())
+    }
+
+    //mp bbox
+    /// Get the bounding box of the label as drawn (its text, and any
+    /// bubble and leader line) in the coordinates of the layout, once
+    /// resolved
+    pub fn bbox(&self) -> BBox {
+        if self.text_bbox.is_none() {
+            return BBox::none();
+        }
+        let mut bbox = self.text_bbox;
+        if self.callout == Callout::Bubble {
+            bbox = bbox.enlarge(LABEL_PAD);
+        }
+        if self.callout != Callout::None {
+            bbox = bbox.union(BBox::of_cwh(self.reference, 0., 0.));
+        }
+        bbox.enlarge(self.stroke_width * 0.5)
+    }
+
+    //mp leader_end
+    /// Find where the leader line from the reference point meets the
+    /// box around the text, or None if the reference point is within
+    /// that box
+    fn leader_end(&self) -> Option<Point> {
+        let (c, w, h) = self.text_bbox.enlarge(LABEL_PAD).get_cwh();
+        let d = self.reference - c;
+        let mut t = f64::MAX;
+        if d[0].abs() > 1E-9 {
+            t = t.min(w * 0.5 / d[0].abs());
+        }
+        if d[1].abs() > 1E-9 {
+            t = t.min(h * 0.5 / d[1].abs());
+        }
+        if t >= 1. {
+            None
+        } else {
+            Some(c + d * t)
+        }
+    }
+
+    //zz All done
+}
+
+//ip GenerateSvgElement for Label
+impl GenerateSvgElement for Label {
+    fn generate_svg(&self, svg: &mut Svg, header: &ElementHeader) -> Result<(), SvgError> {
+        if self.text_bbox.is_none() {
+            return Ok(());
+        }
+        if self.callout != Callout::None {
+            if let Some(end) = self.leader_end() {
+                let mut ele = SvgElement::new("path");
+                match &self.stroke {
+                    None => ele.add_attribute("stroke", "None"),
+                    Some(rgb) => ele.add_color("stroke", rgb),
+                }
+                ele.add_attribute("fill", "None");
+                ele.add_size("stroke-width", self.stroke_width);
+                let mut path = BezierPath::default();
+                path.add_bezier(Bezier::line(&self.reference, &end));
+                ele.add_bezier_path(&path, false);
+                svg.add_subelement(ele);
+            }
+        }
+        if self.callout == Callout::Bubble {
+            let bbox = self.text_bbox.enlarge(LABEL_PAD);
+            let corners = vec![
+                Point::from_array([bbox.x.min(), bbox.y.min()]),
+                Point::from_array([bbox.x.max(), bbox.y.min()]),
+                Point::from_array([bbox.x.max(), bbox.y.max()]),
+                Point::from_array([bbox.x.min(), bbox.y.max()]),
+            ];
+            let mut ele = SvgElement::new("path");
+            match &self.stroke {
+                None => ele.add_attribute("stroke", "None"),
+                Some(rgb) => ele.add_color("stroke", rgb),
+            }
+            ele.add_attribute("fill", "None");
+            ele.add_size("stroke-width", self.stroke_width);
+            ele.add_bezier_path(&BezierPath::of_points(&corners, self.round), true);
+            svg.add_subelement(ele);
+        }
+        let mut ele = SvgElement::new("g");
+        ele.add_transform(&Transform::of_translation(Point::from_array([
+            self.text_bbox.x.min(),
+            self.text_bbox.y.min(),
+        ])));
+        svg.push_element(ele);
+        self.text.generate_svg(svg, header)?;
+        let ele = svg.pop_element();
+        svg.add_subelement(ele);
+        Ok(())
+    }
+}
+
+//ti IndentedDisplay for Label
+impl<'a> IndentedDisplay<'a, IndentOptions> for Label {
+    fn indent(&self, ind: &mut Indenter<'_, IndentOptions>) -> std::fmt::Result {
+        use std::fmt::Write;
+        writeln!(ind, "Label")?;
+        let mut sub = ind.sub();
+        writeln!(&mut sub, "target  : {}", self.target)?;
+        writeln!(&mut sub, "along   : {}", self.along)?;
+        writeln!(&mut sub, "offset  : {}", self.offset)?;
+        writeln!(&mut sub, "callout : {:?}", self.callout)?;
+        writeln!(&mut sub, "stroke  : {:?}", self.stroke)?;
+        self.text.indent(&mut sub)?;
+        Ok(())
+    }
+}
+
+//a Test
+#[cfg(test)]
+mod tests {
+    use crate::{Diagram, DiagramDescriptor, DiagramML};
+    use vg_rs::BBox;
+
+    //ft test_bounds
+    #[test]
+    fn test_bounds() {
+        let style_set = DiagramDescriptor::create_style_set();
+        let diagram_descriptor = DiagramDescriptor::new(&style_set);
+        let mut diagram = Diagram::new(&diagram_descriptor);
+        let mut dml = DiagramML::new(&mut diagram);
+        dml.read_file(
+            "#diagram ##rect id=a width=10 height=10 ##label target=a offset='30 0' callout=bubble font-size=4 'far'".as_bytes(),
+            false,
+        )
+        .unwrap();
+        assert!(diagram.uniquify().is_ok());
+        assert!(diagram.apply_stylesheet().is_ok());
+        assert!(diagram.style().is_ok());
+        assert!(diagram.layout(&BBox::none()).is_ok());
+        let (contents, _) = diagram.borrow_contents_stylesheet();
+        assert!(contents.content_bbox.x.max() < 10.);
+        assert!(diagram.geometry().is_ok());
+        let (contents, _) = diagram.borrow_contents_stylesheet();
+        assert!(contents.content_bbox.x.max() > 26.);
+        assert!((contents.content_bbox.x.min() + 5.).abs() < 1E-6);
+    }
+}
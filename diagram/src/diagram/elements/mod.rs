@@ -1,10 +1,14 @@
+mod connector;
 mod group;
+mod label;
 mod path;
 mod shape;
 mod text;
 mod use_ref;
 
+pub use self::connector::{Connector, ConnectorAnchors};
 pub use self::group::Group;
+pub use self::label::Label;
 pub use self::path::Path;
 pub use self::shape::Shape;
 pub use self::text::Text;
@@ -33,6 +33,286 @@ use crate::constants::elements as el;
 //a Constants
 const BEZIER_STRAIGHTNESS: f64 = 1E-2;
 
+//a Segments
+//fi segment_tokens
+/// Split path segment data in to commands and numbers; whitespace and
+/// commas separate numbers, and a command letter need not be separated
+/// from its numbers
+fn segment_tokens(s: &str) -> Result<Vec<Result<char, f64>>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c.is_whitespace() || c == ',' {
+            continue;
+        }
+        if c.is_ascii_alphabetic() {
+            tokens.push(Ok(c));
+            continue;
+        }
+        let mut end = i + c.len_utf8();
+        let mut last = c;
+        while let Some((j, d)) = chars.peek() {
+            let d = *d;
+            let in_number = d.is_ascii_digit()
+                || d == '.'
+                || d == 'e'
+                || d == 'E'
+                || ((d == '-' || d == '+') && (last == 'e' || last == 'E'));
+            if !in_number {
+                break;
+            }
+            end = j + d.len_utf8();
+            last = d;
+            chars.next();
+        }
+        let n = s[i..end]
+            .parse::<f64>()
+            .map_err(|_| format!("bad number '{}' in path segments", &s[i..end]))?;
+        tokens.push(Err(n));
+    }
+    Ok(tokens)
+}
+
+//fi arc_beziers
+/// Generate cubic Beziers approximating an elliptical arc from `p0` to
+/// `p1`, using the endpoint parameterization of SVG; `radii` are the
+/// radii of the ellipse, which is rotated by `rotation` degrees, and
+/// the `large` and `sweep` flags select which of the four possible
+/// arcs is used
+fn arc_beziers(
+    p0: Point,
+    p1: Point,
+    radii: (f64, f64),
+    rotation: f64,
+    large: bool,
+    sweep: bool,
+) -> Vec<Bezier> {
+    let (mut rx, mut ry) = (radii.0.abs(), radii.1.abs());
+    if p0.distance(&p1) < 1E-12 {
+        return Vec::new();
+    }
+    if rx < 1E-12 || ry < 1E-12 {
+        return vec![Bezier::line(&p0, &p1)];
+    }
+    let (sin_phi, cos_phi) = rotation.to_radians().sin_cos();
+    let rotate =
+        |x: f64, y: f64| Point::from_array([x * cos_phi - y * sin_phi, x * sin_phi + y * cos_phi]);
+
+    // Midpoint of the chord in the frame of the ellipse axes
+    let d = (p0 - p1) * 0.5;
+    let x1 = d[0] * cos_phi + d[1] * sin_phi;
+    let y1 = -d[0] * sin_phi + d[1] * cos_phi;
+
+    // Enlarge the radii if they cannot span the endpoints
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1. {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut coef = (num / den).max(0.).sqrt();
+    if large == sweep {
+        coef = -coef;
+    }
+    let cx1 = coef * rx * y1 / ry;
+    let cy1 = -coef * ry * x1 / rx;
+    let center = rotate(cx1, cy1) + (p0 + p1) * 0.5;
+
+    let angle = |x: f64, y: f64| y.atan2(x);
+    let theta = angle((x1 - cx1) / rx, (y1 - cy1) / ry);
+    let mut delta = angle((-x1 - cx1) / rx, (-y1 - cy1) / ry) - theta;
+    if sweep && delta < 0. {
+        delta += std::f64::consts::TAU;
+    } else if !sweep && delta > 0. {
+        delta -= std::f64::consts::TAU;
+    }
+
+    // Split in to pieces of at most a quarter turn, each a cubic
+    // approximating a unit circle arc mapped on to the ellipse
+    let n = (delta.abs() / std::f64::consts::FRAC_PI_2 - 1E-9)
+        .ceil()
+        .max(1.) as usize;
+    let step = delta / (n as f64);
+    let k = 4. / 3. * (step / 4.).tan();
+    let on_ellipse = |x: f64, y: f64| center + rotate(x * rx, y * ry);
+    let mut beziers = Vec::new();
+    let mut start = p0;
+    for i in 0..n {
+        let a0 = theta + step * (i as f64);
+        let a1 = a0 + step;
+        let (s0, c0) = a0.sin_cos();
+        let (s1, c1) = a1.sin_cos();
+        let end = if i + 1 == n { p1 } else { on_ellipse(c1, s1) };
+        beziers.push(Bezier::cubic(
+            &start,
+            &on_ellipse(c0 - k * s0, s0 + k * c0),
+            &on_ellipse(c1 + k * s1, s1 - k * c1),
+            &end,
+        ));
+        start = end;
+    }
+    beziers
+}
+
+//fp parse_segments
+/// Parse path segment data, in the style of the data of an SVG path
+/// but in the relative coordinates of a path element, in to Beziers
+/// and whether the path is closed
+///
+/// The commands are M (move to, only at the start), L (line to), H
+/// and V (horizontal and vertical line to), Q and T (quadratic and
+/// smooth quadratic), C and S (cubic and smooth cubic), A (elliptical
+/// arc: radii, rotation, large-arc and sweep flags, and end point), and
+/// Z (close); lower-case commands use coordinates relative to the
+/// current point
+pub fn parse_segments(s: &str) -> Result<(Vec<Bezier>, bool), String> {
+    let tokens = segment_tokens(s)?;
+    let mut beziers = Vec::new();
+    let mut closed = false;
+    let mut start: Option<Point> = None;
+    let mut pt = Point::zero();
+    // Reflection of the last control point, for smooth curves
+    let mut last_control: Option<(char, Point)> = None;
+    let mut i = 0;
+    let mut cmd = ' ';
+    while i < tokens.len() {
+        if let Ok(c) = tokens[i] {
+            cmd = c;
+            i += 1;
+        } else if cmd == ' ' {
+            return Err("path segments must start with a command".into());
+        }
+        if closed {
+            return Err("path segments continue after 'Z'".into());
+        }
+        let op = cmd.to_ascii_uppercase();
+        let n_args = match op {
+            'Z' => 0,
+            'H' | 'V' => 1,
+            'M' | 'L' | 'T' => 2,
+            'Q' | 'S' => 4,
+            'C' => 6,
+            'A' => 7,
+            _ => return Err(format!("unknown path segment command '{}'", cmd)),
+        };
+        let mut args = [0.; 7];
+        for a in args.iter_mut().take(n_args) {
+            match tokens.get(i) {
+                Some(Err(n)) => {
+                    *a = *n;
+                    i += 1;
+                }
+                _ => {
+                    return Err(format!(
+                        "path segment command '{}' requires {} numbers",
+                        cmd, n_args
+                    ))
+                }
+            }
+        }
+        let relative = cmd.is_ascii_lowercase();
+        let abs = |x: f64, y: f64| {
+            if relative {
+                pt + Point::from_array([x, y])
+            } else {
+                Point::from_array([x, y])
+            }
+        };
+        let smooth_control = |kind: char| match last_control {
+            Some((k, c)) if k == kind => pt * 2. - c,
+            _ => pt,
+        };
+        let mut control = None;
+        match op {
+            'M' => {
+                if start.is_some() {
+                    return Err("path segments may only have a 'M' at the start".into());
+                }
+                pt = abs(args[0], args[1]);
+                start = Some(pt);
+                // Further coordinates are implicit line-to commands
+                cmd = if relative { 'l' } else { 'L' };
+                continue;
+            }
+            'Z' => {
+                let p0 = start.unwrap_or(pt);
+                if p0.distance(&pt) > 1E-6 {
+                    beziers.push(Bezier::line(&pt, &p0));
+                }
+                closed = true;
+                continue;
+            }
+            _ => {}
+        }
+        if start.is_none() {
+            start = Some(pt);
+        }
+        let p1 = match op {
+            'L' => {
+                let p1 = abs(args[0], args[1]);
+                beziers.push(Bezier::line(&pt, &p1));
+                p1
+            }
+            'H' => {
+                let x = if relative { pt[0] + args[0] } else { args[0] };
+                let p1 = Point::from_array([x, pt[1]]);
+                beziers.push(Bezier::line(&pt, &p1));
+                p1
+            }
+            'V' => {
+                let y = if relative { pt[1] + args[0] } else { args[0] };
+                let p1 = Point::from_array([pt[0], y]);
+                beziers.push(Bezier::line(&pt, &p1));
+                p1
+            }
+            'Q' | 'T' => {
+                let (c, p1) = if op == 'Q' {
+                    (abs(args[0], args[1]), abs(args[2], args[3]))
+                } else {
+                    (smooth_control('Q'), abs(args[0], args[1]))
+                };
+                beziers.push(Bezier::quadratic(&pt, &c, &p1));
+                control = Some(('Q', c));
+                p1
+            }
+            'C' | 'S' => {
+                let (c0, c1, p1) = if op == 'C' {
+                    (
+                        abs(args[0], args[1]),
+                        abs(args[2], args[3]),
+                        abs(args[4], args[5]),
+                    )
+                } else {
+                    (
+                        smooth_control('C'),
+                        abs(args[0], args[1]),
+                        abs(args[2], args[3]),
+                    )
+                };
+                beziers.push(Bezier::cubic(&pt, &c0, &c1, &p1));
+                control = Some(('C', c1));
+                p1
+            }
+            _ => {
+                let p1 = abs(args[5], args[6]);
+                beziers.extend(arc_beziers(
+                    pt,
+                    p1,
+                    (args[0], args[1]),
+                    args[2],
+                    args[3] != 0.,
+                    args[4] != 0.,
+                ));
+                p1
+            }
+        };
+        pt = p1;
+        last_control = control;
+    }
+    Ok((beziers, closed))
+}
+
 //a Path element
 //tp Path - an Element that contains a path
 #[derive(Debug)]
@@ -45,6 +325,9 @@ pub struct Path {
     pub round: f64,
     pub closed: bool,
     pub coords: Vec<Point>, // relative to actual width and height
+    /// Segments of the path (if given rather than coords), relative to
+    /// actual width and height
+    pub segments: Vec<Bezier>,
     pub fill: Option<(f64, f64, f64)>,
     pub stroke: Option<(f64, f64, f64)>,
     pub stroke_width: f64,
@@ -62,6 +345,7 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Path {
             round: 0.,
             closed: false,
             coords: Vec::new(),
+            segments: Vec::new(),
             stroke_width: 0.,
             stroke: None,
             fill: None,
@@ -87,6 +371,7 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Path {
             at::WIDTH,
             at::HEIGHT,
             at::COORDS,
+            at::SEGMENTS,
             at::FLAGS,
         ]
     }
@@ -125,6 +410,18 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Path {
                 self.coords.push(Point::from_array([x, y]));
             }
         }
+        if let Some(segments) = header.get_style_of_name_string(at::SEGMENTS) {
+            if !self.coords.is_empty() {
+                return Err(ElementError::of_string(
+                    header,
+                    "a path may have 'coords' or 'segments' but not both",
+                ));
+            }
+            let (segments, closed) =
+                parse_segments(&segments).map_err(|e| ElementError::of_string(header, &e))?;
+            self.segments = segments;
+            self.closed |= closed;
+        }
         let mut strs = [""; 4];
         if let Some(v) = header
             .get_style_value_of_name(at::MARKERS)
@@ -191,14 +488,36 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Path {
     //zz All done
 }
 //ip Path
-impl Path {}
+impl Path {
+    //mp as_bezier_path
+    /// Get the segments (or lines between the coords) of the path as
+    /// a [BezierPath] in the content coordinates of the element,
+    /// without any rounding
+    pub fn as_bezier_path(&self) -> BezierPath {
+        let scale_xy = Point::from_array([self.width * 0.5, self.height * 0.5]);
+        let mut path = BezierPath::default();
+        if !self.segments.is_empty() {
+            for b in &self.segments {
+                path.add_bezier(*b);
+            }
+            path.map_pts(|p| p * scale_xy + self.center);
+            return path;
+        }
+        for i in 1..self.coords.len() {
+            let p0 = self.coords[i - 1] * scale_xy + self.center;
+            let p1 = self.coords[i] * scale_xy + self.center;
+            path.add_bezier(Bezier::line(&p0, &p1));
+        }
+        path
+    }
+}
 
 //ip GenerateSvgElement for Path
 impl GenerateSvgElement for Path {
     fn generate_svg(&self, svg: &mut Svg, header: &ElementHeader) -> Result<(), SvgError> {
         let mut ele = SvgElement::new("path");
         header.svg_add_transform(&mut ele);
-        if self.coords.is_empty() {
+        if self.coords.is_empty() && self.segments.is_empty() {
             return Ok(());
         }
         match &self.stroke {
@@ -220,15 +539,7 @@ impl GenerateSvgElement for Path {
         }
         ele.add_markers(&self.markers);
         ele.add_size("stroke-width", self.stroke_width);
-        let scale_xy = Point::from_array([self.width * 0.5, self.height * 0.5]);
-        let mut coords = Vec::new();
-        for c in &self.coords {
-            coords.push((*c) * scale_xy + self.center);
-        }
-        let mut path = BezierPath::default();
-        for i in 0..coords.len() - 1 {
-            path.add_bezier(Bezier::line(&coords[i], &coords[i + 1]));
-        }
+        let mut path = self.as_bezier_path();
         // apply marker relief of stroke-width * relief for start and end markers
         if let Some(m) = &self.markers.0 {
             if let Some((_, m)) = svg
@@ -275,7 +586,74 @@ impl<'a> IndentedDisplay<'a, IndentOptions> for Path {
         writeln!(&mut sub, "stroke  : {:?}", self.stroke)?;
         writeln!(&mut sub, "strokewidth  : {}", self.stroke_width)?;
         writeln!(&mut sub, "markers  : {:?}", self.markers)?;
+        writeln!(&mut sub, "segments: {}", self.segments.len())?;
         // pub coords : Vec<Point>, // relative to actual width and height
         Ok(())
     }
 }
+
+//a Test
+#[cfg(test)]
+mod test_path {
+    use super::*;
+    use crate::diagram::test_utils::layout_content;
+    fn pt_eq(pt: &Point, x: f64, y: f64) {
+        assert!(
+            (pt[0] - x).abs() < 1E-6 && (pt[1] - y).abs() < 1E-6,
+            "mismatch {:?} ({}, {})",
+            pt,
+            x,
+            y
+        );
+    }
+
+    //ft test_segments
+    #[test]
+    fn test_segments() {
+        let (b, closed) = parse_segments("M-1,-1 L 1 -1 v2 Q0,0 -1,1 z").unwrap();
+        assert!(closed);
+        let degrees: Vec<usize> = b.iter().map(|b| b.degree()).collect();
+        assert_eq!(degrees, vec![1, 1, 2, 1]);
+        pt_eq(b[1].borrow_pt(1), 1., 1.);
+        pt_eq(b[3].borrow_pt(1), -1., -1.);
+
+        let (b, closed) = parse_segments("M 0 0 C 0 1 1 1 1 0 S 2 -1 2 0").unwrap();
+        assert!(!closed);
+        assert_eq!(b.len(), 2);
+        // The first control point of S is the reflection of the last of C
+        pt_eq(b[1].borrow_pt(2), 1., -1.);
+
+        assert!(parse_segments("L 1").is_err());
+        assert!(parse_segments("1 1").is_err());
+        assert!(parse_segments("M 0 0 L 1 1 M 2 2").is_err());
+        assert!(parse_segments("M 0 0 X 1 1").is_err());
+
+        let errors =
+            layout_content("#diagram ##layout ###path coords='0 0 1 1' segments='M 0 0 L 1 1'")
+                .unwrap_err();
+        assert!(errors[0].contains("'coords' or 'segments' but not both"));
+    }
+
+    //ft test_arc
+    #[test]
+    fn test_arc() {
+        // Half of a unit circle centered on the origin, through (0, 1)
+        let (b, _) = parse_segments("M -1 0 A 1 1 0 0 0 1 0").unwrap();
+        assert_eq!(b.len(), 2);
+        pt_eq(b[0].borrow_pt(1), 0., 1.);
+        pt_eq(&b[0].point_at(0.5), -(0.5f64.sqrt()), 0.5f64.sqrt());
+        pt_eq(b[1].borrow_pt(1), 1., 0.);
+
+        // With the sweep flag set the arc goes the other way
+        let (b, _) = parse_segments("M -1 0 a 1 1 0 0 1 2 0").unwrap();
+        pt_eq(b[0].borrow_pt(1), 0., -1.);
+
+        // Radii too small are enlarged; a large arc of a circle of
+        // radius 1 between points 1 apart is five sixths of the circle
+        let (b, _) = parse_segments("M 0 0 A 0.1 0.1 0 0 0 1 0").unwrap();
+        assert_eq!(b.len(), 2);
+        pt_eq(b[0].borrow_pt(1), 0.5, 0.5);
+        let (b, _) = parse_segments("M 0 0 A 1 1 0 1 0 1 0").unwrap();
+        assert_eq!(b.len(), 4);
+    }
+}
@@ -17,9 +17,10 @@ limitations under the License.
  */
 
 //a Imports
+use geo_nd::Vector;
 use indent_display::{IndentedDisplay, Indenter};
 use vg_rs::layout::Layout;
-use vg_rs::BBox;
+use vg_rs::{BBox, Point};
 
 use super::super::font::*;
 use super::super::text::*;
@@ -50,6 +51,13 @@ impl std::fmt::Display for TextError {
 
 //a Text element
 //tp Text - an Element that contains text
+/// Text is wrapped to `max_width` if that is given; if `wrap_fill`
+/// then it is rewrapped to fill the width of the content rectangle it
+/// is placed in (which is wider than its desired geometry if the
+/// layout expands it). Without a `max_width`, text that fills its
+/// cell desires only the width of its widest word (with a line for
+/// each word), so that its cell may be narrower than the unwrapped
+/// text.
 #[derive(Debug)]
 pub struct Text {
     pub fill: Option<(f64, f64, f64)>,
@@ -57,8 +65,16 @@ pub struct Text {
     pub font_style: Option<String>,
     pub font_weight: Option<String>,
     pub font_size: f64,
+    pub max_width: Option<f64>,
+    pub wrap: Wrap,
+    pub wrap_fill: bool,
+    pub text_align: TextAlign,
+    pub line_spacing: f64,
+    pub markup: bool,
     pub text: Vec<String>,
     pub text_area: TextArea<Font>,
+    /// Position of the top left of the text in content coordinates
+    origin: Point,
 }
 
 //ip DiagramElementContent for Text
@@ -72,7 +88,14 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Text {
             font_style: None,
             font_weight: None,
             font_size: 10.,
+            max_width: None,
+            wrap: Wrap::default(),
+            wrap_fill: false,
+            text_align: TextAlign::default(),
+            line_spacing: 1.,
+            markup: false,
             text_area: TextArea::default(),
+            origin: Point::zero(),
         })
     }
 
@@ -94,6 +117,11 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Text {
             at::FONTSIZE,
             at::FONTWEIGHT,
             at::FONTSTYLE,
+            at::MAXWIDTH,
+            at::WRAP,
+            at::TEXTALIGN,
+            at::LINESPACING,
+            at::MARKUP,
         ]
     }
 
@@ -105,6 +133,7 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Text {
         descriptor: &DiagramDescriptor,
         header: &ElementHeader,
     ) -> Result<(), ElementError> {
+        self.text_area = TextArea::default();
         let mut floats = [0.; 4];
         if let Some(v) = header
             .get_style_value_of_name(at::FILL)
@@ -123,10 +152,60 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Text {
             self.font_weight.as_ref(),
             self.font_style.as_ref(),
         );
-        let font = descriptor.get_font();
+        self.max_width = header.get_style_of_name_float(at::MAXWIDTH, None);
+        let mut strs = [""; 4];
+        self.wrap = Wrap::default();
+        self.wrap_fill = false;
+        if let Some(v) = header
+            .get_style_value_of_name(at::WRAP)
+            .and_then(|x| x.as_strs(&mut strs))
+        {
+            for s in v {
+                if *s == "fill" {
+                    self.wrap_fill = true;
+                } else {
+                    self.wrap = Wrap::of_str(s).ok_or_else(|| {
+                        ElementError::of_string(header, &format!("unknown text wrap '{}'", s))
+                    })?;
+                }
+            }
+        }
+        if let Some(align) = header.get_style_of_name_string(at::TEXTALIGN) {
+            self.text_align = TextAlign::of_str(&align).ok_or_else(|| {
+                ElementError::of_string(header, &format!("unknown text-align '{}'", align))
+            })?;
+        }
+        self.line_spacing = header
+            .get_style_of_name_float(at::LINESPACING, Some(1.))
+            .unwrap();
+        self.markup = match header.get_style_of_name_string(at::MARKUP).as_deref() {
+            None | Some("none") => false,
+            Some("inline") => true,
+            Some(m) => {
+                return Err(ElementError::of_string(
+                    header,
+                    &format!("unknown text markup '{}'", m),
+                ));
+            }
+        };
+        let font = descriptor.get_font(self.font.as_deref());
+        let code_font = descriptor.get_font(Some("monospace"));
         for t in &self.text {
-            self.text_area.add_text(t, font.clone(), style);
+            if self.markup {
+                self.text_area
+                    .add_markup(t, font.clone(), code_font.clone(), style)
+                    .map_err(|e| ElementError::of_string(header, &e))?;
+            } else {
+                self.text_area.add_text(t, font.clone(), style);
+            }
         }
+        self.text_area.set_align(self.text_align);
+        self.text_area.set_line_spacing(self.line_spacing);
+        let width = match self.max_width {
+            None if self.wrap_fill => Some(0.),
+            width => width,
+        };
+        self.text_area.wrap(width, self.wrap);
         Ok(())
     }
 
@@ -136,6 +215,19 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Text {
         BBox::new(0., 0., w, h)
     }
 
+    //fp apply_placement
+    /// If the text fills its cell then rewrap it to the width of the
+    /// content rectangle, keeping it centered vertically
+    fn apply_placement(&mut self, _layout: &Layout, rect: &BBox) {
+        if !self.wrap_fill {
+            return;
+        }
+        let (c, w, _) = rect.get_cwh();
+        self.text_area.wrap(Some(w), self.wrap);
+        let (_, h) = self.text_area.get_bbox();
+        self.origin = Point::from_array([rect.x.min(), c[1] - h * 0.5]);
+    }
+
     //mp display
     /// Display - using indent_str + 2 indent, or an indent of indent spaces
     /// Content should be invoked with indent+4
@@ -164,9 +256,14 @@ impl Text {
 
 //ip GenerateSvgElement for Text
 impl GenerateSvgElement for Text {
+    /// Generate a 'text' element for each line of the text; if a line
+    /// has more than one span, or its span has markup, then each span
+    /// is a 'tspan' within it
     fn generate_svg(&self, svg: &mut Svg, header: &ElementHeader) -> Result<(), SvgError> {
         let font_size = self.font_size / 72.0 * 25.4;
-        for t in self.text_area.iter_spans() {
+        let spans: Vec<TextSpanElement> = self.text_area.iter_spans().collect();
+        for line in spans.chunk_by(|a, b| a.line == b.line) {
+            let t = &line[0];
             let mut ele = SvgElement::new("text");
             header.svg_add_transform(&mut ele);
             match &self.fill {
@@ -177,9 +274,12 @@ impl GenerateSvgElement for Text {
                     ele.add_color("fill", rgb);
                 }
             }
-            ele.add_size("x", t.x);
-            ele.add_size("y", t.y);
+            ele.add_size("x", self.origin[0] + t.x);
+            ele.add_size("y", self.origin[1] + t.y);
             ele.add_size("font-size", font_size);
+            if t.anchor != TextAnchor::Start {
+                ele.add_attribute("text-anchor", t.anchor.as_str());
+            }
             ele.add_attribute("stroke", "None"); // ImageMagic will stroke it otherwise
             let mut style = String::new();
             if let Some(f) = &self.font {
@@ -194,7 +294,39 @@ impl GenerateSvgElement for Text {
             if !style.is_empty() {
                 ele.add_attribute("style", &style);
             }
-            ele.add_string(t.text);
+            if line.len() == 1 && t.markup.is_none() {
+                ele.add_string(t.text);
+                svg.add_subelement(ele);
+                continue;
+            }
+            svg.push_element(ele);
+            for t in line {
+                let mut ele = SvgElement::new("tspan");
+                ele.add_size("x", self.origin[0] + t.x);
+                ele.add_size("y", self.origin[1] + t.y);
+                if let Some(rgb) = &t.markup.color {
+                    ele.add_color("fill", rgb);
+                }
+                if t.markup.shift != 0 {
+                    ele.add_size("font-size", font_size * t.markup.scale());
+                }
+                let mut style = String::new();
+                if t.markup.code {
+                    style.push_str("font-family:monospace;");
+                }
+                if t.markup.italic {
+                    style.push_str("font-style:italic;");
+                }
+                if t.markup.bold {
+                    style.push_str("font-weight:bold;");
+                }
+                if !style.is_empty() {
+                    ele.add_attribute("style", &style);
+                }
+                ele.add_string(t.text);
+                svg.add_subelement(ele);
+            }
+            let ele = svg.pop_element();
             svg.add_subelement(ele);
         }
         Ok(())
@@ -212,6 +344,76 @@ impl<'a> IndentedDisplay<'a, IndentOptions> for Text {
         writeln!(&mut sub, "font_style : {:?}", self.font_style)?;
         writeln!(&mut sub, "font_weight: {:?}", self.font_weight)?;
         writeln!(&mut sub, "font_size  : {}", self.font_size)?;
+        writeln!(&mut sub, "max_width  : {:?}", self.max_width)?;
+        writeln!(
+            &mut sub,
+            "wrap       : {:?} fill {}",
+            self.wrap, self.wrap_fill
+        )?;
+        writeln!(&mut sub, "text_align : {:?}", self.text_align)?;
+        writeln!(&mut sub, "line_space : {}", self.line_spacing)?;
+        writeln!(&mut sub, "markup     : {}", self.markup)?;
         Ok(())
     }
 }
+
+//a Test
+#[cfg(test)]
+mod tests {
+    use crate::diagram::ElementContent;
+    use crate::{Diagram, DiagramDescriptor, DiagramML};
+    use vg_rs::BBox;
+
+    //fi text_sizes
+    /// Read HML with a single layout into a new diagram, style it
+    /// `n` times and lay it out, and get the width and height of the
+    /// text of each text element in the layout
+    fn text_sizes(text: &str, n: usize) -> Vec<(f64, f64)> {
+        let style_set = DiagramDescriptor::create_style_set();
+        let diagram_descriptor = DiagramDescriptor::new(&style_set);
+        let mut diagram = Diagram::new(&diagram_descriptor);
+        let mut dml = DiagramML::new(&mut diagram);
+        dml.read_file(text.as_bytes(), false).unwrap();
+        assert!(diagram.uniquify().is_ok());
+        assert!(diagram.apply_stylesheet().is_ok());
+        for _ in 0..n {
+            assert!(diagram.style().is_ok());
+        }
+        assert!(diagram.layout(&BBox::none()).is_ok());
+        let (contents, _) = diagram.borrow_contents_stylesheet();
+        let root = contents.root_layout.as_ref().unwrap();
+        let layout = &root.content.borrow_group().unwrap().content[0];
+        layout
+            .content
+            .borrow_group()
+            .unwrap()
+            .content
+            .iter()
+            .filter_map(|e| match &e.content {
+                ElementContent::Text(t) => Some(t.text_area.get_bbox()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    //ft test_restyle
+    #[test]
+    fn test_restyle() {
+        let text = "#diagram ##layout ###text 'one line'";
+        assert_eq!(text_sizes(text, 1), text_sizes(text, 2));
+    }
+
+    //ft test_wrap_fill
+    #[test]
+    fn test_wrap_fill() {
+        let sizes = text_sizes(
+            "#diagram ##layout minx=1,20,2 ###text grid=1,1 wrap=fill 'the quick brown fox jumps over the lazy dog' ###text grid=1,2 'the quick brown fox jumps over the lazy dog'",
+            1,
+        );
+        // The unwrapped text is wider than the cell, but the text
+        // that fills it is rewrapped to it
+        assert!(sizes[1].0 > 20.);
+        assert!(sizes[0].0 <= 20.);
+        assert!(sizes[0].1 > sizes[1].1);
+    }
+}
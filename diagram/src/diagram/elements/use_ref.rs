@@ -20,11 +20,13 @@ limitations under the License.
 use indent_display::{IndentedDisplay, Indenter};
 use stylesheet::{StylableNode, Tree};
 use vg_rs::layout::Layout;
-use vg_rs::BBox;
+use vg_rs::{BBox, Transform};
 
+use super::ConnectorAnchors;
 use crate::constants::attributes as at;
 use crate::constants::elements as el;
-use crate::diagram::{DiagramElementContent, Element, ElementError, ElementHeader, ElementScope};
+use crate::diagram::{DiagramElementContent, Element, ElementError, ElementErrorList};
+use crate::diagram::{ElementHeader, ElementScope};
 use crate::diagram::{GenerateSvg, GenerateSvgElement, Svg, SvgError};
 use crate::{DiagramDescriptor, IndentOptions};
 
@@ -89,6 +91,7 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Use<'a> {
         header: &ElementHeader<'a>,
         scope: &ElementScope<'a, 'b>,
         uid: usize,
+        errors: &mut ElementErrorList,
     ) -> Result<(bool, usize), ElementError> {
         match self.content.len() {
             0 => {
@@ -100,7 +103,7 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Use<'a> {
             }
             _ => {
                 // has content (and it must be the only content), so has been uniqified already
-                let uid = self.content[0].uniquify(scope, uid)?;
+                let uid = self.content[0].uniquify(scope, uid, errors);
                 Ok((false, uid))
             }
         }
@@ -114,14 +117,13 @@ impl<'a, 'b> DiagramElementContent<'a, 'b> for Use<'a> {
     //mp style
     /// Style the element within the Diagram's descriptor, using the
     /// header if required to extract styles
+    ///
+    /// The used content is styled afterwards with `style_content`
     fn style(
         &mut self,
-        descriptor: &DiagramDescriptor,
+        _descriptor: &DiagramDescriptor,
         _header: &ElementHeader,
     ) -> Result<(), ElementError> {
-        for e in self.content.iter_mut() {
-            e.style(descriptor)?;
-        }
         Ok(())
     }
 
@@ -163,6 +165,25 @@ impl<'a> Use<'a> {
         self.strings.push(s.to_string());
         Ok(())
     }
+
+    //mp borrow_id_ref
+    /// Borrow the id of the definition that this is a usage of
+    pub fn borrow_id_ref(&self) -> &str {
+        &self.id_ref
+    }
+
+    //mp borrow_content
+    /// Borrow the element that the use is bound to, once uniquified
+    pub fn borrow_content(&self) -> &[Element<'a>] {
+        &self.content
+    }
+
+    //mp borrow_strings
+    /// Borrow the strings provided with the element
+    pub fn borrow_strings(&self) -> &[String] {
+        &self.strings
+    }
+
     //fp tree_add_element
     pub fn tree_add_element<'b>(
         &'b mut self,
@@ -173,6 +194,45 @@ impl<'a> Use<'a> {
         }
         tree
     }
+    //mp add_anchors
+    /// Add the anchors of the used content, which is placed in the
+    /// same layout as the use
+    pub fn add_anchors(&self, transform: &Transform, anchors: &mut ConnectorAnchors) -> BBox {
+        let mut bbox = BBox::none();
+        for e in self.content.iter() {
+            bbox = bbox.union(e.add_anchors(transform, false, anchors));
+        }
+        bbox
+    }
+    //mp style_content
+    /// Style the used content, adding any errors to the list
+    pub fn style_content(&mut self, descriptor: &DiagramDescriptor, errors: &mut ElementErrorList) {
+        for e in self.content.iter_mut() {
+            e.style(descriptor, errors);
+        }
+    }
+    //mp resolve_connectors
+    pub fn resolve_connectors(
+        &mut self,
+        anchors: &ConnectorAnchors,
+        errors: &mut ElementErrorList,
+    ) {
+        for e in self.content.iter_mut() {
+            e.resolve_connectors(anchors, errors);
+        }
+    }
+    //mp resolve_labels
+    pub fn resolve_labels(
+        &mut self,
+        anchors: &ConnectorAnchors,
+        errors: &mut ElementErrorList,
+    ) -> BBox {
+        let mut bbox = BBox::none();
+        for e in self.content.iter_mut() {
+            bbox = bbox.union(e.resolve_labels(anchors, errors));
+        }
+        bbox
+    }
 }
 
 //ip GenerateSvg format Use
@@ -0,0 +1,336 @@
+/*a Copyright
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+
+@file    flow.rs
+@brief   Flow of the content of a layout along rows or columns
+ */
+
+//a Imports
+use vg_rs::grid::GridData;
+use vg_rs::layout::Layout;
+
+use crate::constants::attributes as at;
+use crate::diagram::{Element, ElementError, ElementHeader, LayoutPlacement};
+
+//a Constants
+/// The growth of links that should (in effect) keep their size when
+/// the rest of the links of a flow grow
+const STIFF_GROWTH: f64 = 1.0E-6;
+
+//a FlowJustify
+//tp FlowJustify
+/// How the cells of a row (or column) share any space along it
+/// beyond that which they need
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowJustify {
+    /// The cells are placed at the start, with the space after them
+    Start,
+    /// The cells are centred, with the space split either side
+    Center,
+    /// The cells are placed at the end, with the space before them
+    End,
+    /// The space is shared between the gaps between the cells
+    SpaceBetween,
+    /// The space is shared between the cells themselves, and the
+    /// elements expand to fill them
+    Stretch,
+}
+
+//ip FlowJustify
+impl FlowJustify {
+    //fp of_str
+    pub fn of_str(s: &str) -> Option<Self> {
+        match s {
+            "start" => Some(Self::Start),
+            "center" => Some(Self::Center),
+            "end" => Some(Self::End),
+            "space-between" => Some(Self::SpaceBetween),
+            "stretch" => Some(Self::Stretch),
+            _ => None,
+        }
+    }
+}
+
+//a FlowAlign
+//tp FlowAlign
+/// How each element is placed across a row (or column) within its
+/// cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowAlign {
+    Start,
+    Center,
+    End,
+    /// The element expands to fill its cell
+    Stretch,
+}
+
+//ip FlowAlign
+impl FlowAlign {
+    //fp of_str
+    pub fn of_str(s: &str) -> Option<Self> {
+        match s {
+            "start" => Some(Self::Start),
+            "center" => Some(Self::Center),
+            "end" => Some(Self::End),
+            "stretch" => Some(Self::Stretch),
+            _ => None,
+        }
+    }
+}
+
+//a Flow
+//tp Flow
+/// The flow of the content of a layout along rows (or columns): each
+/// element that is not gridded or placed itself is given the next
+/// cell of the row, wrapping to a new row after a number of cells
+/// if required
+///
+/// The cells of the elements along a row are between grid lines 1
+/// and 2, 3 and 4, and so on, with the cells between them being the
+/// gaps; the rows are similarly between lines 1 and 2, 3 and 4, and
+/// so on, in the other dimension. The cells are those of the grid of
+/// the layout, and so the cell data of the layout applies to them
+/// too.
+#[derive(Debug, Clone, Copy)]
+pub struct Flow {
+    /// True if the content flows along rows (in X), false for columns
+    row: bool,
+    /// The gap between adjacent cells, and between adjacent rows
+    gap: f64,
+    /// The number of cells in a row before the flow wraps to the
+    /// next row; if 0 then it does not wrap
+    wrap_count: usize,
+    justify: Option<FlowJustify>,
+    align: Option<FlowAlign>,
+}
+
+//ip Flow
+impl Flow {
+    //fp of_header
+    /// Get the flow of a layout from its styles, if it has a
+    /// 'direction' of 'row' or 'column'
+    pub fn of_header(header: &ElementHeader) -> Result<Option<Self>, ElementError> {
+        let row = match header.get_style_of_name_string(at::DIRECTION).as_deref() {
+            None => {
+                return Ok(None);
+            }
+            Some("row") => true,
+            Some("column") => false,
+            Some(d) => {
+                return Err(ElementError::of_string(
+                    header,
+                    &format!("unknown direction '{}', expected 'row' or 'column'", d),
+                ));
+            }
+        };
+        let gap = header.get_style_of_name_float(at::GAP, Some(0.)).unwrap();
+        let wrap_count = header
+            .get_style_of_name_int(at::WRAPCOUNT, Some(0))
+            .unwrap();
+        if gap < 0. || wrap_count < 0 {
+            return Err(ElementError::of_string(
+                header,
+                "gap and wrap-count of a layout must not be negative",
+            ));
+        }
+        let justify = match header.get_style_of_name_string(at::JUSTIFY) {
+            None => None,
+            Some(s) => Some(FlowJustify::of_str(&s).ok_or_else(|| {
+                ElementError::of_string(header, &format!("unknown justify '{}'", s))
+            })?),
+        };
+        let align = match header.get_style_of_name_string(at::ALIGN) {
+            None => None,
+            Some(s) => Some(FlowAlign::of_str(&s).ok_or_else(|| {
+                ElementError::of_string(header, &format!("unknown align '{}'", s))
+            })?),
+        };
+        Ok(Some(Self {
+            row,
+            gap,
+            wrap_count: wrap_count as usize,
+            justify,
+            align,
+        }))
+    }
+
+    //mp flow_content
+    /// Give the next cell to each element of the content of a layout
+    /// that is not gridded or placed, fitting it to its cell as
+    /// required, and set how the grid of the layout is justified
+    ///
+    /// The content must have been styled. The cell data for the gaps
+    /// between the cells and rows is returned, for X and then Y.
+    pub fn flow_content(
+        &self,
+        layout: &mut Layout,
+        content: &mut [Element],
+    ) -> (Vec<GridData<usize>>, Vec<GridData<usize>>) {
+        let mut num_cells = 0;
+        let mut num_rows = 0;
+        let mut n = 0;
+        for element in content.iter_mut() {
+            if !matches!(element.header.layout.placement, LayoutPlacement::None) {
+                continue;
+            }
+            let (row, cell) = match self.wrap_count {
+                0 => (0, n),
+                w => (n / w, n % w),
+            };
+            n += 1;
+            num_cells = num_cells.max(cell + 1);
+            num_rows = num_rows.max(row + 1);
+            let cell = ((2 * cell + 1).to_string(), (2 * cell + 2).to_string());
+            let row = ((2 * row + 1).to_string(), (2 * row + 2).to_string());
+            let (x, y) = if self.row { (cell, row) } else { (row, cell) };
+            element.header.layout.set_grid(x.0, y.0, x.1, y.1);
+            self.fit_element(&mut element.header);
+        }
+
+        // The grid only expands if every link along it has a growth,
+        // so the links that should keep their size get a tiny one
+        let (cell_growth, gap_growth) = match self.justify {
+            Some(FlowJustify::SpaceBetween) => (Some(STIFF_GROWTH), Some(1.)),
+            Some(FlowJustify::Stretch) => (Some(1.), Some(STIFF_GROWTH)),
+            _ => (None, None),
+        };
+        let mut cell_data = Vec::new();
+        let mut row_data = Vec::new();
+        for cell in 0..num_cells {
+            let start = layout.add_grid_id(self.row, &(2 * cell + 1).to_string());
+            let end = layout.add_grid_id(self.row, &(2 * cell + 2).to_string());
+            if let Some(growth) = cell_growth {
+                cell_data.push(GridData::new_growth(start, end, growth));
+            }
+        }
+        for cell in 1..num_cells {
+            let start = layout.add_grid_id(self.row, &(2 * cell).to_string());
+            let end = layout.add_grid_id(self.row, &(2 * cell + 1).to_string());
+            cell_data.push(GridData::new_width(start, end, self.gap));
+            if let Some(growth) = gap_growth {
+                cell_data.push(GridData::new_growth(start, end, growth));
+            }
+        }
+        for row in 1..num_rows {
+            let start = layout.add_grid_id(!self.row, &(2 * row).to_string());
+            let end = layout.add_grid_id(!self.row, &(2 * row + 1).to_string());
+            row_data.push(GridData::new_width(start, end, self.gap));
+        }
+
+        if let Some(justify) = self.justify {
+            let (expand, anchor) = match justify {
+                FlowJustify::Start => (0., -1.),
+                FlowJustify::Center => (0., 0.),
+                FlowJustify::End => (0., 1.),
+                FlowJustify::SpaceBetween | FlowJustify::Stretch => (1., 0.),
+            };
+            layout.set_grid_expand(self.row, expand);
+            layout.set_grid_anchor(self.row, anchor);
+        }
+
+        if self.row {
+            (cell_data, row_data)
+        } else {
+            (row_data, cell_data)
+        }
+    }
+
+    //mi fit_element
+    /// Fit an element to its cell, aligning it across its row and
+    /// stretching it along the row if required, unless it has its own
+    /// anchor or expansion
+    fn fit_element(&self, header: &mut ElementHeader) {
+        let mut floats = [0.; 4];
+        let mut is_set = |name| {
+            header
+                .get_style_value_of_name(name)
+                .and_then(|x| x.as_floats(&mut floats))
+                .is_some()
+        };
+        if is_set(at::ANCHOR) || is_set(at::EXPAND) {
+            return;
+        }
+        let (along, across) = if self.row { (0, 1) } else { (1, 0) };
+        match self.align {
+            Some(FlowAlign::Start) => header.layout.anchor[across] = -1.,
+            Some(FlowAlign::Center) => header.layout.anchor[across] = 0.,
+            Some(FlowAlign::End) => header.layout.anchor[across] = 1.,
+            Some(FlowAlign::Stretch) => header.layout.expand[across] = 1.,
+            None => (),
+        }
+        if self.justify == Some(FlowJustify::Stretch) {
+            header.layout.expand[along] = 1.;
+        }
+    }
+
+    //zz All done
+}
+
+//a Test
+#[cfg(test)]
+mod tests {
+    use crate::diagram::test_utils::layout_content;
+
+    #[test]
+    fn test_row() {
+        let boxes = layout_content(
+            "#diagram ##layout direction=row gap=2 align=start ###rect width=4 height=2 ###rect width=6 height=4 ###rect width=2 height=6",
+        )
+        .unwrap();
+        assert_eq!(
+            boxes,
+            vec![(-8., -4., -3., -1.), (-2., 4., -3., 1.), (6., 8., -3., 3.)]
+        );
+    }
+
+    #[test]
+    fn test_wrap() {
+        let boxes = layout_content(
+            "#diagram ##layout direction=column gap=1 wrap-count=2 ###rect width=2 height=2 ###rect width=2 height=2 ###rect width=2 height=2 ###rect grid=3,3 width=1 height=1",
+        )
+        .unwrap();
+        assert_eq!(
+            boxes,
+            vec![
+                (-2.5, -0.5, -2.5, -0.5),
+                (-2.5, -0.5, 0.5, 2.5),
+                (0.5, 2.5, -2.5, -0.5),
+                (1., 2., 1., 2.)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_justify() {
+        let justified = |justify| {
+            layout_content(&format!("#diagram ##layout grid=1,1 expand=1,0 direction=row gap=2 justify={} ###rect width=2 height=2 ###rect width=2 height=2 ##rect grid=1,2 width=20 height=2", justify)).unwrap()
+        };
+        assert_eq!(
+            justified("start"),
+            vec![(-10., -8., -1., 1.), (-6., -4., -1., 1.)]
+        );
+        assert_eq!(
+            justified("end"),
+            vec![(4., 6., -1., 1.), (8., 10., -1., 1.)]
+        );
+        assert_eq!(
+            justified("space-between"),
+            vec![(-10., -8., -1., 1.), (8., 10., -1., 1.)]
+        );
+        assert_eq!(
+            justified("stretch"),
+            vec![(-10., -1., -1., 1.), (1., 10., -1., 1.)]
+        );
+    }
+}
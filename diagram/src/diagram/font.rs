@@ -18,6 +18,9 @@ limitations under the License.
 
 //a Imports
 #![allow(dead_code)]
+use std::collections::{BTreeMap, HashMap};
+
+use super::font_file::{FontError, FontFile};
 
 //a FontMetrics trait
 //tp TextMetrics
@@ -44,6 +47,11 @@ pub trait FontMetrics {
 }
 
 //a Font
+/// Flag in a `FontStyle` for a bold font
+pub const FONT_BOLD: usize = 1;
+/// Flag in a `FontStyle` for an italic (or oblique) font
+pub const FONT_ITALIC: usize = 2;
+
 //tp FontStyle
 /// A font style as a size in points and flags for font styling options
 #[derive(Clone, Copy, Debug)]
@@ -55,11 +63,58 @@ pub struct FontStyle {
 //ip FontStyle
 impl FontStyle {
     //fp new
-    /// Create a new simple font style
-    pub fn new(size: f64, _weight: Option<&String>, _style: Option<&String>) -> Self {
-        let flags = 0;
+    /// Create a new simple font style, given the CSS-like font-weight
+    /// ('bold', 'bolder' or a weight of 600 or more are bold) and
+    /// font-style ('italic' or 'oblique' are italic)
+    pub fn new(size: f64, weight: Option<&String>, style: Option<&String>) -> Self {
+        let weight_flags = match weight.map(|s| s.as_str()) {
+            Some("bold") | Some("bolder") => FONT_BOLD,
+            Some(w) if w.parse::<f64>().is_ok_and(|w| w >= 600.) => FONT_BOLD,
+            _ => 0,
+        };
+        let style_flags = match style.map(|s| s.as_str()) {
+            Some("italic") | Some("oblique") => FONT_ITALIC,
+            _ => 0,
+        };
+        let flags = weight_flags | style_flags;
         Self { size, flags }
     }
+
+    //mp size
+    /// Get the size of the font in points
+    pub fn size(&self) -> f64 {
+        self.size
+    }
+
+    //mp with_flags
+    /// Get the style with additional flags (such as FONT_BOLD)
+    pub fn with_flags(&self, flags: usize) -> Self {
+        Self {
+            size: self.size,
+            flags: self.flags | flags,
+        }
+    }
+
+    //mp scaled
+    /// Get the style scaled in size
+    pub fn scaled(&self, scale: f64) -> Self {
+        Self {
+            size: self.size * scale,
+            flags: self.flags,
+        }
+    }
+
+    //mp is_bold
+    /// Return true if the style is bold
+    pub fn is_bold(&self) -> bool {
+        (self.flags & FONT_BOLD) != 0
+    }
+
+    //mp is_italic
+    /// Return true if the style is italic
+    pub fn is_italic(&self) -> bool {
+        (self.flags & FONT_ITALIC) != 0
+    }
 }
 
 //tp Metrics
@@ -224,6 +279,48 @@ impl<V: Value> Metrics<V> {
             exceptions,
         }
     }
+    //fp of_char_widths
+    /// Create metrics for the characters from `first_char` onwards,
+    /// given their widths, all with the same height, depth and italic
+    /// correction; there must be fewer than 256 distinct widths
+    pub fn of_char_widths(first_char: char, widths: &[V], height: V, depth: V, italic: V) -> Self {
+        let last_char = char::from_u32(first_char as u32 + widths.len() as u32 - 1).unwrap();
+        let mut distinct_widths = vec![V::zero()];
+        let mut char_metrics = Vec::new();
+        for w in widths {
+            let index = match distinct_widths.iter().position(|x| x == w) {
+                Some(index) => index,
+                None => {
+                    distinct_widths.push(*w);
+                    distinct_widths.len() - 1
+                }
+            };
+            char_metrics.push(CharIndices::of_indices(index, 0, 0, 0, 0));
+        }
+        Self {
+            first_char,
+            last_char,
+            widths: distinct_widths,
+            heights: vec![height],
+            depths: vec![depth],
+            italics: vec![italic],
+            char_metrics,
+            parameters: Vec::new(),
+            exceptions: Vec::new(),
+        }
+    }
+    //mp add_exception
+    /// Add metrics for a range of characters that override these
+    /// metrics, extending the range of characters covered if required
+    pub fn add_exception(&mut self, exception: Self) {
+        if exception.first_char < self.first_char {
+            self.first_char = exception.first_char;
+        }
+        if exception.last_char > self.last_char {
+            self.last_char = exception.last_char;
+        }
+        self.exceptions.push(exception);
+    }
     pub fn get_width(&self, index: usize) -> V {
         assert!(index < self.widths.len());
         self.widths[index]
@@ -264,7 +361,12 @@ impl<V: Value> Metrics<V> {
                     return Some(m);
                 }
             }
-            Some((self, ((c as u32) - (self.first_char as u32)) as usize))
+            let index = ((c as u32) - (self.first_char as u32)) as usize;
+            if index < self.char_metrics.len() {
+                Some((self, index))
+            } else {
+                None
+            }
         }
     }
     pub fn glyph_metrics(&self, c: char) -> GlyphMetrics<V> {
@@ -287,29 +389,180 @@ impl Value for f64 {
         0.0
     }
 }
-/// This structure provides simple metric storage for
+//tp FontVariant
+/// The metrics, in ems, of one variant of a font (regular, bold,
+/// italic or bold italic), with kerning for pairs of characters
 #[derive(Debug)]
-pub struct Font {
+struct FontVariant {
     metrics: Metrics<f64>,
+    kerning: HashMap<(char, char), f64>,
+}
+
+//ip FontVariant
+impl FontVariant {
+    //fp new_monospace
+    fn new_monospace(width: f64, italic: f64) -> Self {
+        Self {
+            metrics: Metrics::new_monospace(width, 1.1, 0.3, italic),
+            kerning: HashMap::new(),
+        }
+    }
+}
+
+//tp Font
+/// This structure provides simple metric storage for a font family,
+/// with metrics for each of its variants that are known
+#[derive(Debug)]
+pub struct Font {
+    /// Metrics of the variants, indexed by `FontStyle` flags; at least one is present
+    variants: [Option<FontVariant>; 4],
+    /// True if the variants are the built-in monospace metrics, rather
+    /// than from font files
+    is_builtin: bool,
 }
 
 impl Default for Font {
     fn default() -> Self {
         Self {
-            metrics: Metrics::new_monospace(0.5, 1.1, 0.3, 0.),
+            variants: [
+                Some(FontVariant::new_monospace(0.5, 0.)),
+                Some(FontVariant::new_monospace(0.55, 0.)),
+                Some(FontVariant::new_monospace(0.5, 0.1)),
+                Some(FontVariant::new_monospace(0.55, 0.1)),
+            ],
+            is_builtin: true,
         }
     }
 }
 
+impl Font {
+    //fp of_file
+    /// Create a font from the metrics in a TrueType or OpenType font file
+    pub fn of_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self, FontError> {
+        Ok(Self::of_font_file(&FontFile::of_file(path)?))
+    }
+
+    //fp of_font_file
+    /// Create a font from the metrics of a font file, with the single
+    /// variant (bold and/or italic) that the font file declares itself to be
+    ///
+    /// The characters of the font are split in to blocks of 128, each
+    /// of which is an exception to the metrics of the font as a whole,
+    /// which are those of its missing glyph. All characters have the
+    /// ascent and descent of the font, and an italic correction of the
+    /// ascent slanted by the italic angle.
+    pub fn of_font_file(font_file: &FontFile) -> Self {
+        const BLOCK: u32 = 128;
+        let height = font_file.em(font_file.ascender as f64);
+        let depth = -font_file.em(font_file.descender as f64);
+        let italic = (height * (-font_file.italic_angle).to_radians().tan()).max(0.);
+        let missing_width = font_file.em(font_file.advance(0) as f64);
+        let mut metrics = Metrics::new_monospace(missing_width, height, depth, italic);
+
+        let mut blocks: BTreeMap<u32, BTreeMap<u32, f64>> = BTreeMap::new();
+        let mut chars_of_glyph: HashMap<u16, Vec<char>> = HashMap::new();
+        for (c, glyph) in font_file.iter_chars() {
+            let width = font_file.em(font_file.advance(glyph) as f64);
+            blocks
+                .entry(c as u32 / BLOCK)
+                .or_default()
+                .insert(c as u32, width);
+            chars_of_glyph.entry(glyph).or_default().push(c);
+        }
+        for block in blocks.values() {
+            let first = *block.keys().next().unwrap();
+            let last = *block.keys().next_back().unwrap();
+            let widths: Vec<f64> = (first..=last)
+                .map(|c| block.get(&c).copied().unwrap_or(missing_width))
+                .collect();
+            let first_char = char::from_u32(first).unwrap();
+            metrics.add_exception(Metrics::of_char_widths(
+                first_char, &widths, height, depth, italic,
+            ));
+        }
+
+        let mut kerning = HashMap::new();
+        for ((left, right), k) in font_file.iter_kerning() {
+            if let (Some(lc), Some(rc)) = (chars_of_glyph.get(&left), chars_of_glyph.get(&right)) {
+                for l in lc {
+                    for r in rc {
+                        kerning.insert((*l, *r), font_file.em(k as f64));
+                    }
+                }
+            }
+        }
+
+        let mut flags = 0;
+        if font_file.is_bold {
+            flags |= FONT_BOLD;
+        }
+        if font_file.is_italic {
+            flags |= FONT_ITALIC;
+        }
+        let mut variants = [None, None, None, None];
+        variants[flags] = Some(FontVariant { metrics, kerning });
+        Self {
+            variants,
+            is_builtin: false,
+        }
+    }
+
+    //mp add_variants
+    /// Add the variants of another font to this one, replacing any
+    /// that this font already has
+    ///
+    /// If this font has the built-in metrics then all of its variants
+    /// are dropped, so that the styles the other font does not supply
+    /// fall back to its variants rather than to the built-in ones
+    pub fn add_variants(&mut self, other: Font) {
+        if self.is_builtin {
+            *self = other;
+            return;
+        }
+        for (v, o) in self.variants.iter_mut().zip(other.variants) {
+            if o.is_some() {
+                *v = o;
+            }
+        }
+    }
+
+    //mi variant
+    /// Get the variant of the font for a style; if the font does not
+    /// have that variant then drop italic, then bold, and finally use
+    /// any variant that the font has
+    fn variant(&self, style: &FontStyle) -> &FontVariant {
+        let flags = style.flags & (FONT_BOLD | FONT_ITALIC);
+        for f in [flags, flags & !FONT_ITALIC, flags & !FONT_BOLD, 0] {
+            if let Some(v) = &self.variants[f] {
+                return v;
+            }
+        }
+        self.variants.iter().flatten().next().unwrap()
+    }
+}
+
 impl FontMetrics for Font {
+    /// Get the metrics of some text; for italic text the italic
+    /// correction of the last character is added, so that the text
+    /// does not overhang its bounding box
     fn get_metrics(&self, text: &str, style: &FontStyle) -> TextMetrics {
+        let variant = self.variant(style);
         let mut gm = GlyphMetrics::zero();
+        let mut kern = 0.;
+        let mut last_c = None;
         for c in text.chars() {
             // if a space, add metrics.space?
-            gm = gm.add(&self.metrics.glyph_metrics(c));
+            gm = gm.add(&variant.metrics.glyph_metrics(c));
+            if let Some(lc) = last_c {
+                kern += variant.kerning.get(&(lc, c)).copied().unwrap_or(0.);
+            }
+            last_c = Some(c);
+        }
+        if style.is_italic() {
+            kern += gm.italic;
         }
         let size = style.size * 25.4 / 72.0;
-        let width = gm.width * size;
+        let width = (gm.width + kern) * size;
         let ascender = gm.height * size;
         let descender = gm.depth * size;
         TextMetrics {
@@ -319,3 +572,78 @@ impl FontMetrics for Font {
         }
     }
 }
+
+//a Test
+#[cfg(test)]
+mod test_font {
+    use super::*;
+    use crate::diagram::font_file::test_font_file::test_font_bytes;
+
+    //ft test_font_file_metrics
+    #[test]
+    fn test_font_file_metrics() {
+        let font = Font::of_font_file(&FontFile::of_bytes(&test_font_bytes(0)).unwrap());
+        // 72 point text has an em of 25.4mm
+        let style = FontStyle::new(72., None, None);
+        let m = font.get_metrics("AC", &style);
+        assert!((m.width - 1.4 * 25.4).abs() < 1E-6);
+        assert!((m.ascender - 0.8 * 25.4).abs() < 1E-6);
+        assert!((m.descender - 0.2 * 25.4).abs() < 1E-6);
+        // 'A' 'B' is kerned by -0.05 em
+        let m = font.get_metrics("AB", &style);
+        assert!((m.width - 1.25 * 25.4).abs() < 1E-6);
+        // Missing glyphs use the width of glyph 0
+        let m = font.get_metrics("Az", &style);
+        assert!((m.width - 1.1 * 25.4).abs() < 1E-6);
+    }
+
+    //ft test_font_variants
+    #[test]
+    fn test_font_variants() {
+        let bold = "bold".to_string();
+        let italic = "italic".to_string();
+        let regular_style = FontStyle::new(72., None, None);
+        let bold_style = FontStyle::new(72., Some(&bold), None);
+        let italic_style = FontStyle::new(72., None, Some(&italic));
+        let bold_italic_style = FontStyle::new(72., Some(&"700".to_string()), Some(&italic));
+        assert!(bold_style.is_bold() && !bold_style.is_italic());
+        assert!(!italic_style.is_bold() && italic_style.is_italic());
+        assert!(bold_italic_style.is_bold() && bold_italic_style.is_italic());
+
+        // The default font has wider bold, and italic correction
+        let font = Font::default();
+        let regular = font.get_metrics("AB", &regular_style).width;
+        assert!(font.get_metrics("AB", &bold_style).width > regular);
+        assert!(font.get_metrics("AB", &italic_style).width > regular);
+        assert!(font.get_metrics("AB", &bold_italic_style).width > regular);
+
+        // A font file that is only italic is used for all styles;
+        // italic adds the correction for the last character
+        let mut font = Font::of_font_file(&FontFile::of_bytes(&test_font_bytes(-45)).unwrap());
+        let m = font.get_metrics("AC", &regular_style);
+        assert!((m.width - 1.4 * 25.4).abs() < 1E-6);
+        let m = font.get_metrics("AC", &italic_style);
+        assert!((m.width - 2.2 * 25.4).abs() < 1E-6);
+        let m = font.get_metrics("AC", &bold_italic_style);
+        assert!((m.width - 2.2 * 25.4).abs() < 1E-6);
+
+        // Adding an upright variant is used for the regular style only
+        font.add_variants(Font::of_font_file(
+            &FontFile::of_bytes(&test_font_bytes(0)).unwrap(),
+        ));
+        assert!(font.variants[0].is_some());
+        assert!(font.variants[FONT_ITALIC].is_some());
+        let m = font.get_metrics("AC", &italic_style);
+        assert!((m.width - 2.2 * 25.4).abs() < 1E-6);
+
+        // Adding a font file to the built-in font replaces all of its
+        // variants, so bold text uses the metrics of the file
+        let mut font = Font::default();
+        font.add_variants(Font::of_font_file(
+            &FontFile::of_bytes(&test_font_bytes(0)).unwrap(),
+        ));
+        assert!(font.variants[FONT_BOLD].is_none());
+        let m = font.get_metrics("AC", &bold_style);
+        assert!((m.width - 1.4 * 25.4).abs() < 1E-6);
+    }
+}
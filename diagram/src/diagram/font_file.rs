@@ -0,0 +1,518 @@
+/*a Copyright
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+
+@file    font_file.rs
+@brief   Metrics from TrueType and OpenType font files
+ */
+
+//a Imports
+use std::collections::HashMap;
+
+//a FontError
+//tp FontError
+/// An error in reading a font file
+#[derive(Debug)]
+pub enum FontError {
+    /// The file could not be read
+    Io(String),
+    /// The file is not a font, or is truncated or corrupt
+    Malformed(String),
+    /// The font does not have a table that is required
+    MissingTable(&'static str),
+}
+
+//ip Display for FontError
+impl std::fmt::Display for FontError {
+    //mp fmt - format error for display
+    /// Display the error
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Io(s) => write!(f, "FontError reading file {}", s),
+            Self::Malformed(s) => write!(f, "FontError malformed font: {}", s),
+            Self::MissingTable(s) => write!(f, "FontError font has no '{}' table", s),
+        }
+    }
+}
+
+//a FontData
+//tp FontData
+/// A big-endian view of the bytes of a font file
+struct FontData<'a> {
+    data: &'a [u8],
+}
+
+//ip FontData
+impl<'a> FontData<'a> {
+    //mp slice
+    fn slice(&self, offset: usize, length: usize) -> Result<&'a [u8], FontError> {
+        if offset + length > self.data.len() {
+            Err(FontError::Malformed(format!(
+                "read of {} bytes at {} is beyond the end of the data",
+                length, offset
+            )))
+        } else {
+            Ok(&self.data[offset..offset + length])
+        }
+    }
+    //mp u16
+    fn u16(&self, offset: usize) -> Result<u16, FontError> {
+        let b = self.slice(offset, 2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+    //mp i16
+    fn i16(&self, offset: usize) -> Result<i16, FontError> {
+        Ok(self.u16(offset)? as i16)
+    }
+    //mp u32
+    fn u32(&self, offset: usize) -> Result<u32, FontError> {
+        let b = self.slice(offset, 4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+//a FontFile
+//tp FontFile
+/// The metrics of a TrueType or OpenType font, as read from the
+/// 'head', 'hhea', 'hmtx', 'cmap', 'post' and (optional) 'kern'
+/// tables of the font file
+///
+/// All the values are in font units; there are `units_per_em` of
+/// these to the em. Only the legacy 'kern' table is used for
+/// kerning; kerning in the 'GPOS' table is ignored.
+#[derive(Debug, Default)]
+pub struct FontFile {
+    /// Number of font units per em
+    pub units_per_em: u16,
+    /// Ascent above the baseline (positive)
+    pub ascender: i16,
+    /// Descent below the baseline (negative)
+    pub descender: i16,
+    /// Additional gap between lines
+    pub line_gap: i16,
+    /// Italic angle in degrees counter-clockwise from vertical (negative for a forward lean)
+    pub italic_angle: f64,
+    /// True if the font is bold
+    pub is_bold: bool,
+    /// True if the font is italic (or oblique)
+    pub is_italic: bool,
+    /// Advance width of each glyph
+    advances: Vec<u16>,
+    /// Mapping from character to glyph index
+    glyph_of_char: HashMap<char, u16>,
+    /// Kerning adjustments for pairs of glyphs
+    kerning: HashMap<(u16, u16), i16>,
+}
+
+//ip FontFile
+impl FontFile {
+    //fp of_file
+    /// Read a font from a .ttf or .otf file
+    pub fn of_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self, FontError> {
+        let data = std::fs::read(path.as_ref())
+            .map_err(|e| FontError::Io(format!("'{}': {}", path.as_ref().display(), e)))?;
+        Self::of_bytes(&data)
+    }
+
+    //fp of_bytes
+    /// Read a font from the bytes of a .ttf or .otf file; for a font
+    /// collection the first font is used
+    pub fn of_bytes(data: &[u8]) -> Result<Self, FontError> {
+        let data = FontData { data };
+        let mut offset = 0;
+        if data.slice(0, 4)? == b"ttcf" {
+            offset = data.u32(12)? as usize;
+        }
+        let tables = Self::read_table_directory(&data, offset)?;
+        let table =
+            |tag: &'static str| tables.get(tag).copied().ok_or(FontError::MissingTable(tag));
+
+        let mut font = Self::default();
+        let head = table("head")?;
+        font.units_per_em = data.u16(head + 18)?;
+        let mac_style = data.u16(head + 44)?;
+        font.is_bold = (mac_style & 1) != 0;
+        font.is_italic = (mac_style & 2) != 0;
+        if font.units_per_em == 0 {
+            return Err(FontError::Malformed("units per em of zero".into()));
+        }
+
+        let hhea = table("hhea")?;
+        font.ascender = data.i16(hhea + 4)?;
+        font.descender = data.i16(hhea + 6)?;
+        font.line_gap = data.i16(hhea + 8)?;
+        let num_h_metrics = data.u16(hhea + 34)? as usize;
+
+        let num_glyphs = data.u16(table("maxp")? + 4)? as usize;
+        let hmtx = table("hmtx")?;
+        let mut advance = 0;
+        for i in 0..num_glyphs.max(num_h_metrics) {
+            if i < num_h_metrics {
+                advance = data.u16(hmtx + i * 4)?;
+            }
+            font.advances.push(advance);
+        }
+
+        if let Ok(post) = table("post") {
+            font.italic_angle = (data.u32(post + 4)? as i32 as f64) / 65536.0;
+            font.is_italic |= font.italic_angle != 0.;
+        }
+
+        font.read_cmap(&data, table("cmap")?)?;
+        if let Ok(kern) = table("kern") {
+            font.read_kern(&data, kern)?;
+        }
+        Ok(font)
+    }
+
+    //fi read_table_directory
+    fn read_table_directory(
+        data: &FontData,
+        offset: usize,
+    ) -> Result<HashMap<&'static str, usize>, FontError> {
+        const TABLES: [&str; 7] = ["head", "hhea", "maxp", "hmtx", "cmap", "post", "kern"];
+        let version = data.u32(offset)?;
+        if version != 0x00010000 && version != 0x4f54544f && version != 0x74727565 {
+            return Err(FontError::Malformed(format!(
+                "unknown font version {:08x}",
+                version
+            )));
+        }
+        let num_tables = data.u16(offset + 4)? as usize;
+        let mut tables = HashMap::new();
+        for i in 0..num_tables {
+            let record = offset + 12 + i * 16;
+            let tag = data.slice(record, 4)?;
+            if let Some(t) = TABLES.iter().find(|t| t.as_bytes() == tag) {
+                tables.insert(*t, data.u32(record + 8)? as usize);
+            }
+        }
+        Ok(tables)
+    }
+
+    //mi read_cmap
+    /// Read the best Unicode character map of the font; a format 12
+    /// (full Unicode) map is preferred, then a format 4 (BMP) map
+    fn read_cmap(&mut self, data: &FontData, cmap: usize) -> Result<(), FontError> {
+        let num_tables = data.u16(cmap + 2)? as usize;
+        let mut best = None;
+        for i in 0..num_tables {
+            let record = cmap + 4 + i * 8;
+            let platform = data.u16(record)?;
+            let encoding = data.u16(record + 2)?;
+            let subtable = cmap + data.u32(record + 4)? as usize;
+            let is_unicode = platform == 0 || (platform == 3 && (encoding == 1 || encoding == 10));
+            if !is_unicode {
+                continue;
+            }
+            let format = data.u16(subtable)?;
+            let score = match format {
+                12 => 2,
+                4 => 1,
+                _ => 0,
+            };
+            if score > 0 && best.is_none_or(|(s, _)| score > s) {
+                best = Some((score, subtable));
+            }
+        }
+        match best {
+            Some((2, subtable)) => self.read_cmap_format_12(data, subtable),
+            Some((_, subtable)) => self.read_cmap_format_4(data, subtable),
+            None => Err(FontError::Malformed(
+                "no Unicode character map of format 4 or 12".into(),
+            )),
+        }
+    }
+
+    //mi read_cmap_format_4
+    fn read_cmap_format_4(&mut self, data: &FontData, subtable: usize) -> Result<(), FontError> {
+        let seg_count_x2 = data.u16(subtable + 6)? as usize;
+        let end_codes = subtable + 14;
+        let start_codes = end_codes + seg_count_x2 + 2;
+        let id_deltas = start_codes + seg_count_x2;
+        let id_range_offsets = id_deltas + seg_count_x2;
+        for seg in (0..seg_count_x2).step_by(2) {
+            let end = data.u16(end_codes + seg)?;
+            let start = data.u16(start_codes + seg)?;
+            let delta = data.u16(id_deltas + seg)?;
+            let range_offset = data.u16(id_range_offsets + seg)? as usize;
+            for c in start..=end {
+                if c == 0xffff {
+                    break;
+                }
+                let glyph = if range_offset == 0 {
+                    c.wrapping_add(delta)
+                } else {
+                    let addr = id_range_offsets + seg + range_offset + 2 * ((c - start) as usize);
+                    match data.u16(addr)? {
+                        0 => 0,
+                        g => g.wrapping_add(delta),
+                    }
+                };
+                self.add_char(c as u32, glyph as u32);
+            }
+        }
+        Ok(())
+    }
+
+    //mi read_cmap_format_12
+    /// Read a format 12 character map; each group of characters maps
+    /// to consecutive glyphs, and must be a valid range of Unicode
+    /// characters (the group stops at the last glyph of the font)
+    fn read_cmap_format_12(&mut self, data: &FontData, subtable: usize) -> Result<(), FontError> {
+        let num_groups = data.u32(subtable + 12)? as usize;
+        for i in 0..num_groups {
+            let group = subtable + 16 + i * 12;
+            let start = data.u32(group)?;
+            let end = data.u32(group + 4)?;
+            let glyph = data.u32(group + 8)?;
+            if end < start || end > 0x10ffff {
+                return Err(FontError::Malformed(format!(
+                    "bad character range {:x} to {:x} in character map",
+                    start, end
+                )));
+            }
+            for c in start..=end {
+                match glyph.checked_add(c - start) {
+                    Some(g) if (g as usize) < self.advances.len() => self.add_char(c, g),
+                    _ => break,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    //mi add_char
+    fn add_char(&mut self, c: u32, glyph: u32) {
+        if glyph == 0 || glyph as usize >= self.advances.len() {
+            return;
+        }
+        if let Some(c) = char::from_u32(c) {
+            self.glyph_of_char.insert(c, glyph as u16);
+        }
+    }
+
+    //mi read_kern
+    /// Read the horizontal format 0 subtables of a (Microsoft) 'kern' table
+    fn read_kern(&mut self, data: &FontData, kern: usize) -> Result<(), FontError> {
+        if data.u16(kern)? != 0 {
+            return Ok(());
+        }
+        let num_tables = data.u16(kern + 2)? as usize;
+        let mut subtable = kern + 4;
+        for _ in 0..num_tables {
+            let length = data.u16(subtable + 2)? as usize;
+            let coverage = data.u16(subtable + 4)?;
+            let is_horizontal = (coverage & 1) != 0;
+            let is_format_0 = (coverage >> 8) == 0;
+            if is_horizontal && is_format_0 {
+                let num_pairs = data.u16(subtable + 6)? as usize;
+                for i in 0..num_pairs {
+                    let pair = subtable + 14 + i * 6;
+                    let left = data.u16(pair)?;
+                    let right = data.u16(pair + 2)?;
+                    let value = data.i16(pair + 4)?;
+                    self.kerning.insert((left, right), value);
+                }
+            }
+            subtable += length;
+        }
+        Ok(())
+    }
+
+    //mp em
+    /// Convert a value in font units to ems
+    pub fn em(&self, v: f64) -> f64 {
+        v / (self.units_per_em as f64)
+    }
+
+    //mp glyph
+    /// Get the glyph for a character, if the font has one
+    pub fn glyph(&self, c: char) -> Option<u16> {
+        self.glyph_of_char.get(&c).copied()
+    }
+
+    //mp advance
+    /// Get the advance width of a glyph in font units
+    pub fn advance(&self, glyph: u16) -> u16 {
+        self.advances.get(glyph as usize).copied().unwrap_or(0)
+    }
+
+    //mp iter_chars
+    /// Iterate over the characters that the font has glyphs for, with the glyphs
+    pub fn iter_chars(&self) -> impl Iterator<Item = (char, u16)> + '_ {
+        self.glyph_of_char.iter().map(|(c, g)| (*c, *g))
+    }
+
+    //mp iter_kerning
+    /// Iterate over the kerning pairs of glyphs, with their adjustment in font units
+    pub fn iter_kerning(&self) -> impl Iterator<Item = ((u16, u16), i16)> + '_ {
+        self.kerning.iter().map(|(p, k)| (*p, *k))
+    }
+
+    //zz All done
+}
+
+//a Test
+#[cfg(test)]
+pub(crate) mod test_font_file {
+    use super::*;
+
+    //fi table
+    fn push_u16(v: &mut Vec<u8>, x: u16) {
+        v.extend_from_slice(&x.to_be_bytes());
+    }
+    fn push_u32(v: &mut Vec<u8>, x: u32) {
+        v.extend_from_slice(&x.to_be_bytes());
+    }
+
+    //fp test_font_bytes
+    /// Create a font of 1000 units per em with glyphs for 'A' (600
+    /// units wide), 'B' (700) and 'C' (800), with 'A' 'B' kerned by
+    /// -50; the ascender is 800 and descender -200, and the italic
+    /// angle is `italic_angle` (and the font is italic if this is not zero)
+    pub(crate) fn test_font_bytes(italic_angle: i32) -> Vec<u8> {
+        let mut head = vec![0; 54];
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+        if italic_angle != 0 {
+            head[44..46].copy_from_slice(&2u16.to_be_bytes());
+        }
+        let mut hhea = vec![0; 36];
+        hhea[4..6].copy_from_slice(&800i16.to_be_bytes());
+        hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+        hhea[34..36].copy_from_slice(&4u16.to_be_bytes());
+        let mut maxp = vec![0; 6];
+        maxp[4..6].copy_from_slice(&4u16.to_be_bytes());
+        let mut hmtx = Vec::new();
+        for w in [500u16, 600, 700, 800] {
+            push_u16(&mut hmtx, w);
+            push_u16(&mut hmtx, 0);
+        }
+        let mut post = vec![0; 32];
+        post[4..8].copy_from_slice(&(italic_angle * 65536).to_be_bytes());
+        let mut cmap = Vec::new();
+        for x in [0u16, 1, 3, 1] {
+            push_u16(&mut cmap, x);
+        }
+        push_u32(&mut cmap, 12);
+        // Format 4 with segments 'A'-'C' and 0xffff
+        for x in [4u16, 32, 0, 4, 4, 1, 0] {
+            push_u16(&mut cmap, x);
+        }
+        for x in [
+            0x43u16,
+            0xffff,
+            0,
+            0x41,
+            0xffff,
+            1u16.wrapping_sub(0x41),
+            1,
+            0,
+            0,
+        ] {
+            push_u16(&mut cmap, x);
+        }
+        let mut kern = Vec::new();
+        for x in [0u16, 1, 0, 20, 1, 1, 0, 0, 0, 1, 2] {
+            push_u16(&mut kern, x);
+        }
+        push_u16(&mut kern, (-50i16) as u16);
+
+        let tables: Vec<(&[u8], Vec<u8>)> = vec![
+            (b"cmap", cmap),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"hmtx", hmtx),
+            (b"kern", kern),
+            (b"maxp", maxp),
+            (b"post", post),
+        ];
+        let mut font = Vec::new();
+        push_u32(&mut font, 0x00010000);
+        push_u16(&mut font, tables.len() as u16);
+        push_u16(&mut font, 0);
+        push_u16(&mut font, 0);
+        push_u16(&mut font, 0);
+        let mut offset = 12 + 16 * tables.len();
+        for (tag, t) in &tables {
+            font.extend_from_slice(tag);
+            push_u32(&mut font, 0);
+            push_u32(&mut font, offset as u32);
+            push_u32(&mut font, t.len() as u32);
+            offset += t.len();
+        }
+        for (_, t) in &tables {
+            font.extend_from_slice(t);
+        }
+        font
+    }
+
+    //ft test_read
+    #[test]
+    fn test_read() {
+        let font = FontFile::of_bytes(&test_font_bytes(-12)).unwrap();
+        assert_eq!(font.units_per_em, 1000);
+        assert_eq!(font.ascender, 800);
+        assert_eq!(font.descender, -200);
+        assert_eq!(font.italic_angle, -12.);
+        assert!(font.is_italic && !font.is_bold);
+        assert_eq!(font.glyph('A'), Some(1));
+        assert_eq!(font.glyph('C'), Some(3));
+        assert_eq!(font.glyph('D'), None);
+        assert_eq!(font.advance(2), 700);
+        assert_eq!(font.kerning.get(&(1, 2)), Some(&-50));
+        assert!((font.em(250.) - 0.25).abs() < 1E-9);
+    }
+
+    //ft test_malformed
+    #[test]
+    fn test_malformed() {
+        assert!(FontFile::of_bytes(b"not a font").is_err());
+        let bytes = test_font_bytes(0);
+        assert!(FontFile::of_bytes(&bytes[0..100]).is_err());
+    }
+
+    //ft test_cmap_format_12
+    #[test]
+    fn test_cmap_format_12() {
+        let read = |groups: &[(u32, u32, u32)]| {
+            let mut cmap = Vec::new();
+            for x in [12u16, 0] {
+                push_u16(&mut cmap, x);
+            }
+            for x in [16 + 12 * groups.len() as u32, 0, groups.len() as u32] {
+                push_u32(&mut cmap, x);
+            }
+            for (start, end, glyph) in groups {
+                for x in [*start, *end, *glyph] {
+                    push_u32(&mut cmap, x);
+                }
+            }
+            let mut font = FontFile {
+                advances: vec![500; 4],
+                ..Default::default()
+            };
+            font.read_cmap_format_12(&FontData { data: &cmap }, 0)
+                .map(|_| font)
+        };
+        let font = read(&[(0x41, 0x43, 1), (0x1f600, 0x10ffff, 3)]).unwrap();
+        assert_eq!(font.glyph('A'), Some(1));
+        assert_eq!(font.glyph('C'), Some(3));
+        assert_eq!(font.glyph('\u{1f600}'), Some(3));
+        assert_eq!(font.glyph('\u{1f601}'), None);
+        let font = read(&[(0x41, 0x42, 0xffff_ffff)]).unwrap();
+        assert_eq!(font.glyph('A'), None);
+        assert!(read(&[(0x43, 0x41, 1)]).is_err());
+        assert!(read(&[(0x41, 0xffff_ffff, 1)]).is_err());
+    }
+}
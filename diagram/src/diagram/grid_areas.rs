@@ -0,0 +1,301 @@
+/*a Copyright
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+
+@file    grid_areas.rs
+@brief   Named areas and grid lines of a layout
+ */
+
+//a Imports
+use crate::constants::attributes as at;
+use crate::diagram::{ElementError, ElementHeader, LayoutPlacement};
+
+//a GridArea
+//tp GridArea
+/// A named rectangular area of the grid of a layout, with its start
+/// and end grid lines in X and Y
+#[derive(Debug, Clone)]
+struct GridArea {
+    name: String,
+    x: (usize, usize),
+    y: (usize, usize),
+}
+
+//a GridAreas
+//tp GridAreas
+/// The named areas of the grid of a layout, given by its 'areas'
+/// style, and the names of its grid lines
+///
+/// The 'areas' are rows of whitespace-separated cell names, separated
+/// by commas or each in double quotes (as in CSS, such as '"hdr hdr"
+/// "nav body"'), with '.' for a cell that is not part of any area; each
+/// row must have the same number of cells. Column *n* of the rows
+/// (from 0) is between grid lines *n+1* and *n+2* in X, and row *n*
+/// similarly in Y. The cells of each name must form a rectangle.
+///
+/// The grid lines of a layout are numbered, or named by its 'minx'
+/// and 'miny' cell data; the start and end lines of an area *name*
+/// are also named *name*-start and *name*-end.
+#[derive(Debug, Clone, Default)]
+pub struct GridAreas {
+    /// The areas, in the order they first appear in the rows
+    areas: Vec<GridArea>,
+}
+
+//ip GridAreas
+impl GridAreas {
+    //fp of_header
+    /// Get the areas of a layout from its 'areas' style
+    pub fn of_header(header: &ElementHeader) -> Result<Self, ElementError> {
+        let rows: Vec<Vec<&str>> = match header
+            .get_style_value_of_name(at::AREAS)
+            .and_then(|x| x.as_vec_str())
+        {
+            None => {
+                return Ok(Self::default());
+            }
+            Some(rows) => rows
+                .iter()
+                .flat_map(|r| {
+                    if r.contains('"') {
+                        // Rows given as quoted strings; the text
+                        // between each pair of quotes is a row
+                        r.split('"').skip(1).step_by(2).collect()
+                    } else {
+                        vec![*r]
+                    }
+                })
+                .map(|r| r.split_whitespace().collect())
+                .collect(),
+        };
+        if rows.iter().any(|r| r.len() != rows[0].len()) {
+            return Err(ElementError::of_string(
+                header,
+                "each row of 'areas' must have the same number of cells",
+            ));
+        }
+        let mut areas: Vec<GridArea> = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, name) in row.iter().enumerate() {
+                if *name == "." {
+                    continue;
+                }
+                match areas.iter_mut().find(|a| a.name == *name) {
+                    Some(area) => {
+                        area.x = (area.x.0.min(x), area.x.1.max(x));
+                        area.y.1 = y;
+                    }
+                    None => {
+                        areas.push(GridArea {
+                            name: name.to_string(),
+                            x: (x, x),
+                            y: (y, y),
+                        });
+                    }
+                }
+            }
+        }
+        for area in areas.iter_mut() {
+            for row in &rows[area.y.0..=area.y.1] {
+                for name in &row[area.x.0..=area.x.1] {
+                    if *name == "." {
+                        return Err(ElementError::of_string(
+                            header,
+                            &format!("area '{}' is not rectangular", area.name),
+                        ));
+                    } else if *name != area.name {
+                        return Err(ElementError::of_string(
+                            header,
+                            &format!(
+                                "area '{}' is not rectangular, as it overlaps area '{}'",
+                                area.name, name
+                            ),
+                        ));
+                    }
+                }
+            }
+            // Convert the cells spanned to the grid lines around them
+            area.x = (area.x.0 + 1, area.x.1 + 2);
+            area.y = (area.y.0 + 1, area.y.1 + 2);
+        }
+        Ok(Self { areas })
+    }
+
+    //mi find_area
+    fn find_area(&self, name: &str) -> Option<&GridArea> {
+        self.areas.iter().find(|a| a.name == name)
+    }
+
+    //mi grid_line
+    /// Get the grid line id for a name or number in X or Y, given
+    /// the cell ids of the layout
+    fn grid_line(&self, x: bool, cell_ids: &[String], name: &str) -> Option<String> {
+        if name.parse::<isize>().is_ok() || cell_ids.iter().any(|c| c == name) {
+            return Some(name.to_string());
+        }
+        let (area, start) = {
+            if let Some(area) = name.strip_suffix("-start") {
+                (area, true)
+            } else if let Some(area) = name.strip_suffix("-end") {
+                (area, false)
+            } else {
+                return None;
+            }
+        };
+        let area = self.find_area(area)?;
+        let lines = if x { area.x } else { area.y };
+        let line = if start { lines.0 } else { lines.1 };
+        Some(line.to_string())
+    }
+
+    //mp grid_element
+    /// Grid an element of the layout by its 'area' style, if it has
+    /// one; it may not also have a 'grid' or 'place'
+    ///
+    /// The cell ids are those of the 'minx' and 'miny' of the layout
+    pub fn grid_element(
+        &self,
+        header: &mut ElementHeader,
+        x_cell_ids: &[String],
+        y_cell_ids: &[String],
+    ) -> Result<(), ElementError> {
+        if let Some((sx, sy, ex, ey)) = self.grid_of_area(header, x_cell_ids, y_cell_ids)? {
+            if !matches!(header.layout.placement, LayoutPlacement::None) {
+                return Err(ElementError::of_string(
+                    header,
+                    "an element with an 'area' may not also have a 'grid' or 'place'",
+                ));
+            }
+            header.layout.set_grid(sx, sy, ex, ey);
+        }
+        Ok(())
+    }
+
+    //mi grid_of_area
+    /// Get the grid lines (start X, start Y, end X, end Y) of an
+    /// element from its 'area' style, if it has one; this is the name
+    /// of an area, or four grid lines by name or number
+    fn grid_of_area(
+        &self,
+        header: &ElementHeader,
+        x_cell_ids: &[String],
+        y_cell_ids: &[String],
+    ) -> Result<Option<(String, String, String, String)>, ElementError> {
+        let v = match header
+            .get_style_value_of_name(at::AREA)
+            .and_then(|x| x.as_vec_str())
+        {
+            None => {
+                return Ok(None);
+            }
+            Some(v) => v,
+        };
+        match v.len() {
+            1 => match self.find_area(v[0].trim()) {
+                Some(area) => Ok(Some((
+                    area.x.0.to_string(),
+                    area.y.0.to_string(),
+                    area.x.1.to_string(),
+                    area.y.1.to_string(),
+                ))),
+                None => Err(ElementError::of_string(
+                    header,
+                    &format!("unknown area '{}'", v[0].trim()),
+                )),
+            },
+            4 => {
+                let line = |x, cell_ids, name: &str| {
+                    self.grid_line(x, cell_ids, name.trim()).ok_or_else(|| {
+                        ElementError::of_string(
+                            header,
+                            &format!("unknown grid line '{}'", name.trim()),
+                        )
+                    })
+                };
+                Ok(Some((
+                    line(true, x_cell_ids, v[0])?,
+                    line(false, y_cell_ids, v[1])?,
+                    line(true, x_cell_ids, v[2])?,
+                    line(false, y_cell_ids, v[3])?,
+                )))
+            }
+            _ => Err(ElementError::of_string(
+                header,
+                "'area' must be the name of an area, or four grid lines",
+            )),
+        }
+    }
+
+    //zz All done
+}
+
+//a Test
+#[cfg(test)]
+mod tests {
+    use crate::diagram::test_utils::layout_content;
+
+    #[test]
+    fn test_areas() {
+        let boxes = layout_content(
+            "#diagram ##layout areas='hdr hdr, nav body' ###rect area=body width=6 height=4 ###rect area=hdr width=10 height=2 ###rect area=nav width=4 height=4",
+        )
+        .unwrap();
+        assert_eq!(
+            boxes,
+            vec![(-1., 5., -1., 3.), (-5., 5., -3., -1.), (-5., -1., -1., 3.)]
+        );
+    }
+
+    #[test]
+    fn test_quoted_rows() {
+        let boxes = layout_content(
+            "#diagram ##layout areas='\"hdr hdr\" \"nav body\"' ###rect area=body width=6 height=4 ###rect area=hdr width=10 height=2 ###rect area=nav width=4 height=4",
+        )
+        .unwrap();
+        assert_eq!(
+            boxes,
+            vec![(-1., 5., -1., 3.), (-5., 5., -3., -1.), (-5., -1., -1., 3.)]
+        );
+    }
+
+    #[test]
+    fn test_lines() {
+        let boxes = layout_content(
+            "#diagram ##layout minx=left,10,mid,20,right areas='a b' ###rect area=mid,1,right,2 width=2 height=2 ###rect area=left,a-start,mid,b-end width=2 height=2",
+        )
+        .unwrap();
+        assert_eq!(boxes, vec![(4., 6., -1., 1.), (-11., -9., -1., 1.)]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |text| layout_content(text).unwrap_err()[0].clone();
+        assert!(error("#diagram ##layout areas='a b, a a' ###rect area=a")
+            .contains("area 'a' is not rectangular, as it overlaps area 'b'"));
+        assert!(
+            error("#diagram ##layout areas='a b, b b, a b' ###rect area=a")
+                .contains("area 'a' is not rectangular, as it overlaps area 'b'")
+        );
+        assert!(error("#diagram ##layout areas='a ., a a' ###rect area=a")
+            .contains("area 'a' is not rectangular"));
+        assert!(error("#diagram ##layout areas='a b, a' ###rect area=a")
+            .contains("must have the same number of cells"));
+        assert!(error("#diagram ##layout areas='a b' ###rect area=c").contains("unknown area 'c'"));
+        assert!(error("#diagram ##layout ###rect area=x,1,2,2").contains("unknown grid line 'x'"));
+        assert!(
+            error("#diagram ##layout areas='a b' ###rect area=a grid=1,1")
+                .contains("may not also have a 'grid'")
+        );
+        assert!(error("#diagram ##group ###rect area=a").contains("not directly part of a Layout"));
+    }
+}
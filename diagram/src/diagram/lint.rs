@@ -0,0 +1,405 @@
+/*a Copyright
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+
+@file    lint.rs
+@brief   Warnings for the contents of a diagram and its stylesheet
+ */
+
+//a Imports
+use std::collections::HashSet;
+
+use super::{Element, ElementError, ElementErrorList, Group, LayoutPlacement};
+use crate::constants::attributes as at;
+use crate::{DiagramContents, StyleSheet};
+use stylesheet::StylableNodeRule;
+
+//a Functions
+//fi describe_rule
+/// Describe a rule by its selector, as it would be written in HML
+fn describe_rule(rule_fn: &StylableNodeRule) -> String {
+    let mut selector = Vec::new();
+    if let Some(id) = rule_fn.id_matches() {
+        selector.push(format!("id={}", id));
+    }
+    if let Some(node_type) = rule_fn.node_type() {
+        selector.push(format!("type={}", node_type));
+    }
+    if !rule_fn.classes().is_empty() {
+        selector.push(format!("class='{}'", rule_fn.classes().join(" ")));
+    }
+    if !rule_fn.has_styles().is_empty() {
+        selector.push(format!("has='{}'", rule_fn.has_styles().join(" ")));
+    }
+    if selector.is_empty() {
+        "rule with no conditions".to_string()
+    } else {
+        format!("rule {}", selector.join(" "))
+    }
+}
+
+//a DiagramLint
+//tp DiagramLint
+/// A check of the contents of a diagram and its stylesheet for
+/// things that are not errors but are probably mistakes, producing
+/// warnings for:
+///
+/// * attributes of an element that are not styles of its type (and
+///   which it does not pass on to any children)
+///
+/// * names in a style (or the inline style of a rule) that are not
+///   styles of any element it is applied to
+///
+/// * definitions that are not used, and markers that are not
+///   referenced by the 'markers' of any element
+///
+/// * rules that do not match any element, and styles with ids that
+///   are not applied to any element
+///
+/// * grid references of an element that are outside the grid cells
+///   given by the 'minx' or 'miny' of its layout
+///
+/// * layouts whose 'equalx', 'equaly' or 'aspect' could not all be
+///   met
+///
+/// Definitions and markers read from libraries are not expected to
+/// all be used, and so are not warned about.
+///
+/// The check is made after the diagram has been uniquified, had its
+/// stylesheet applied, been styled and been laid out; an element of a definition
+/// that is used many times is only warned about once.
+pub struct DiagramLint<'a, 'diag> {
+    contents: &'a DiagramContents<'diag>,
+    stylesheet: &'a StyleSheet<'diag>,
+    warnings: ElementErrorList,
+    /// The warnings given for elements, with the positions of their
+    /// markup, so that each is given once
+    reported: HashSet<(String, String)>,
+    /// The ids of the definitions that are used
+    used_ids: HashSet<String>,
+    /// The ids of the markers that are referenced
+    marker_ids: HashSet<String>,
+    /// For each rule, true if its action has been applied to an
+    /// element
+    rule_applied: Vec<bool>,
+    /// For each action, the names of its styles that are used by an
+    /// element it has been applied to
+    action_styles_used: Vec<HashSet<String>>,
+}
+
+//ip DiagramLint
+impl<'a, 'diag> DiagramLint<'a, 'diag> {
+    //fp new
+    /// Create a new check of diagram contents and a stylesheet
+    pub fn new(contents: &'a DiagramContents<'diag>, stylesheet: &'a StyleSheet<'diag>) -> Self {
+        Self {
+            contents,
+            stylesheet,
+            warnings: ElementErrorList::new(),
+            reported: HashSet::new(),
+            used_ids: HashSet::new(),
+            marker_ids: HashSet::new(),
+            rule_applied: vec![false; stylesheet.num_rules()],
+            action_styles_used: vec![HashSet::new(); stylesheet.num_actions()],
+        }
+    }
+
+    //mp warnings
+    /// Check the diagram, and return the warnings
+    pub fn warnings(mut self) -> ElementErrorList {
+        let contents = self.contents;
+        if let Some(root) = &contents.root_layout {
+            self.check_element(root, None);
+        }
+        for marker in &contents.markers {
+            self.check_element(marker, None);
+        }
+        for element in &contents.definitions {
+            if !self.used_ids.contains(element.borrow_id()) {
+                self.warn_unused(element, "definition is not used");
+            }
+        }
+        for element in &contents.markers {
+            if !self.marker_ids.contains(element.borrow_id()) {
+                self.warn_unused(element, "marker is not used");
+            }
+        }
+        for rule in 0..self.stylesheet.num_rules() {
+            if self.stylesheet.is_toplevel_rule(rule) {
+                self.check_rule(rule);
+            }
+        }
+        for action in 0..self.stylesheet.num_actions() {
+            self.check_action(action);
+        }
+        self.warnings
+    }
+
+    //mi warn
+    /// Add a warning about an element, unless it has already been
+    /// given for the markup of the element
+    fn warn(&mut self, element: &Element, warning: String) {
+        let key = match &element.header.span {
+            Some(span) => span.position().to_string(),
+            None => element.borrow_id().to_string(),
+        };
+        if self.reported.insert((key, warning.clone())) {
+            self.warnings
+                .add(ElementError::warning(&element.header, &warning));
+        }
+    }
+
+    //mi warn_unused
+    /// Warn that a definition or marker is not used, unless it has
+    /// no id (and so cannot be used) or is from a library
+    fn warn_unused(&mut self, element: &Element, warning: &str) {
+        let id = element.borrow_id();
+        if !id.is_empty() && !self.contents.library_ids.iter().any(|l| l == id) {
+            self.warnings
+                .add(ElementError::warning(&element.header, warning));
+        }
+    }
+
+    //mi check_element
+    /// Check an element and its content; the layout group is that
+    /// which the element is placed or gridded within, if any
+    fn check_element(&mut self, element: &Element, layout_group: Option<&Group>) {
+        let stylable = &element.header.stylable;
+        let is_container = element.content.is_container();
+        for rule in stylable.applied_rules() {
+            self.rule_applied[*rule] = true;
+            if let (_, Some(action)) = self.stylesheet.borrow_rule(*rule) {
+                for (name, _) in self.stylesheet.borrow_action(action).borrow_source() {
+                    if is_container || stylable.is_node_style(name) {
+                        self.action_styles_used[action].insert(name.clone());
+                    }
+                }
+            }
+        }
+        if !is_container {
+            for (name, _) in &element.header.attributes {
+                if name != "id" && name != "class" && !stylable.is_node_style(name) {
+                    let warning = format!(
+                        "attribute '{}' is not used by a '{}' element",
+                        name,
+                        stylable.node_type()
+                    );
+                    self.warn(element, warning);
+                }
+            }
+        }
+        if let Some(markers) = element
+            .header
+            .get_style_value_of_name(at::MARKERS)
+            .and_then(|x| x.as_vec_str())
+        {
+            for m in markers {
+                if m != "none" {
+                    self.marker_ids.insert(m.to_string());
+                }
+            }
+        }
+        if let Some(group) = layout_group {
+            if let LayoutPlacement::Grid(sx, sy, ex, ey) = &element.header.layout.placement {
+                self.check_grid(element, group, true, sx, ex);
+                self.check_grid(element, group, false, sy, ey);
+            }
+        }
+        // The content of a use is placed as the use is, and so its
+        // grid references have been checked already
+        let content_layout_group = match element.content.borrow_use() {
+            Some(u) => {
+                self.used_ids.insert(u.borrow_id_ref().to_string());
+                None
+            }
+            None => element.content.borrow_group().filter(|g| g.is_layout()),
+        };
+        if let Some(group) = element.content.borrow_group() {
+            if !group.constraints_met() {
+                self.warn(
+                    element,
+                    "the 'equalx', 'equaly' and 'aspect' of the layout could not all be met"
+                        .to_string(),
+                );
+            }
+        }
+        for e in element.content.borrow_content() {
+            self.check_element(e, content_layout_group);
+        }
+    }
+
+    //mi check_grid
+    /// Check that the grid lines an element spans in one dimension
+    /// are within those given by the cell data of its layout, if it
+    /// has any
+    fn check_grid(&mut self, element: &Element, group: &Group, x: bool, start: &str, end: &str) {
+        let lines: Option<Vec<isize>> = group
+            .borrow_cell_ids(x)
+            .iter()
+            .map(|s| s.parse().ok())
+            .collect();
+        let (min, max) = match lines {
+            Some(lines) if !lines.is_empty() => {
+                (*lines.iter().min().unwrap(), *lines.iter().max().unwrap())
+            }
+            _ => {
+                return;
+            }
+        };
+        let out_of_range = [start, end]
+            .iter()
+            .filter_map(|s| s.parse::<isize>().ok())
+            .any(|l| l < min || l > max);
+        if out_of_range {
+            let (axis, attr) = if x { ("X", at::MINX) } else { ("Y", at::MINY) };
+            let warning = format!(
+                "grid {} lines {} to {} are outside the lines {} to {} given by '{}' of its layout",
+                axis, start, end, min, max, attr
+            );
+            self.warn(element, warning);
+        }
+    }
+
+    //mi rule_used
+    /// Return true if a rule, or any of its child rules, has been
+    /// applied to an element
+    fn rule_used(&self, rule: usize) -> bool {
+        self.rule_applied[rule]
+            || self
+                .stylesheet
+                .iter_rule_children(rule)
+                .any(|child| self.rule_used(child))
+    }
+
+    //mi check_rule
+    /// Warn if a rule does not match any element; if it does, then
+    /// check its child rules
+    fn check_rule(&mut self, rule: usize) {
+        if self.rule_used(rule) {
+            let children: Vec<usize> = self.stylesheet.iter_rule_children(rule).collect();
+            for child in children {
+                self.check_rule(child);
+            }
+        } else {
+            let (rule_fn, _) = self.stylesheet.borrow_rule(rule);
+            self.warnings.add(ElementError::diagram_warning(&format!(
+                "{} does not match any element",
+                describe_rule(rule_fn)
+            )));
+        }
+    }
+
+    //mi check_action
+    /// Warn if a style with an id is not applied to any element, or
+    /// if a style that is applied has names that are not used by any
+    /// element it is applied to
+    fn check_action(&mut self, action: usize) {
+        let stylesheet = self.stylesheet;
+        let rule = (0..stylesheet.num_rules())
+            .filter(|rule| stylesheet.borrow_rule(*rule).1 == Some(action))
+            .find(|rule| self.rule_applied[*rule]);
+        let id = stylesheet.id_of_action(action);
+        let rule = match (rule, id) {
+            (Some(rule), _) => rule,
+            (None, Some(id)) => {
+                self.warnings.add(ElementError::diagram_warning(&format!(
+                    "style '{}' is not applied to any element",
+                    id
+                )));
+                return;
+            }
+            // An inline style of a rule that does not match is
+            // warned about with the rule
+            (None, None) => {
+                return;
+            }
+        };
+        let described = match id {
+            Some(id) => format!("style '{}'", id),
+            None => describe_rule(stylesheet.borrow_rule(rule).0),
+        };
+        for (name, _) in stylesheet.borrow_action(action).borrow_source() {
+            if !self.action_styles_used[action].contains(name) {
+                self.warnings.add(ElementError::diagram_warning(&format!(
+                    "'{}' of {} is not used by any element it is applied to",
+                    name, described
+                )));
+            }
+        }
+    }
+
+    //zz All done
+}
+
+//a Test
+#[cfg(test)]
+mod tests {
+    use vg_rs::BBox;
+
+    use crate::{Diagram, DiagramDescriptor, DiagramML};
+
+    //fi lint
+    /// Read HML into a new diagram, prepare and lay it out, and get
+    /// its warnings
+    fn lint(text: &str) -> Vec<String> {
+        let style_set = DiagramDescriptor::create_style_set();
+        let diagram_descriptor = DiagramDescriptor::new(&style_set);
+        let mut diagram = Diagram::new(&diagram_descriptor);
+        let mut dml = DiagramML::new(&mut diagram);
+        dml.read_file(text.as_bytes(), false).unwrap();
+        assert!(diagram.uniquify().is_ok());
+        assert!(diagram.apply_stylesheet().is_ok());
+        assert!(diagram.style().is_ok());
+        assert!(diagram.layout(&BBox::none()).is_ok());
+        diagram.lint().iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_lint() {
+        let warnings = lint(
+            r#"#diagram
+##style id=unused fill-color=red
+##style id=big width=20 font-size=10
+##rule type=rect style=big
+##rule type=text fill-color=blue
+##defs
+###rect id=spare width=3
+###rect id=box width=3
+###marker id=arrow
+####polygon vertices=3
+###marker id=dot
+####circle width=1
+##layout minx=1,10,2,10,3
+###rect id=r1 grid=1,1 font-size=12 width=2
+###rect id=r2 grid=3,1 width=2
+###use ref=box grid=2,1
+##path id=p coords="0 0 10 10" markers="arrow"
+##layout id=l aspect='1,1,2,2,*2,1,1,2,2'
+###rect id=r3 grid=1,1 width=2
+"#,
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                "Warning: element id 'r1': attribute 'font-size' is not used by a 'rect' element",
+                "Warning: element id 'r2': grid X lines 3 to 4 are outside the lines 1 to 3 given by 'minx' of its layout",
+                "Warning: element id 'l': the 'equalx', 'equaly' and 'aspect' of the layout could not all be met",
+                "Warning: element id 'spare': definition is not used",
+                "Warning: element id 'dot': marker is not used",
+                "Warning: rule type=text does not match any element",
+                "Warning: style 'unused' is not applied to any element",
+                "Warning: 'font-size' of style 'big' is not used by any element it is applied to",
+            ]
+        );
+    }
+}
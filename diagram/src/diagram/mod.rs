@@ -17,6 +17,7 @@ limitations under the License.
  */
 
 //a Imports
+mod builder;
 mod color;
 mod descriptor;
 mod diagram;
@@ -27,26 +28,37 @@ mod element_header;
 mod element_layout;
 mod element_scope;
 mod elements;
+mod flow;
 mod font;
+mod font_file;
+mod grid_areas;
+mod lint;
 mod svg;
+#[cfg(test)]
+mod test_utils;
 mod text;
 mod traits;
 
 pub mod types;
 pub use traits::DiagramElementContent;
 
+pub use builder::{BuildError, DiagramBuilder, ElementBuilder};
 pub use color::Color;
 
 pub use self::descriptor::DiagramDescriptor;
 pub use self::diagram::{Diagram, DiagramContents};
 pub use self::types::{
-    IndentOptions, StylableNode, StyleRule, StyleSheet, StyleTypeValue, ValueError,
+    IndentOptions, StylableNode, StyleRule, StyleSheet, StyleTypeValue, StyleVariables,
 }; // , StyleAction};
 pub use element::Element;
 pub use element_content::ElementContent;
-pub use element_error::ElementError;
+pub use element_error::{ElementError, ElementErrorList, ElementSpan};
 pub use element_header::ElementHeader;
 pub use element_layout::{ElementLayout, LayoutPlacement};
 pub use element_scope::ElementScope;
-pub use elements::{Group, Path, Shape, Text, Use};
+pub use elements::{Connector, ConnectorAnchors, Group, Label, Path, Shape, Text, Use};
+pub use flow::Flow;
+pub use font_file::FontError;
+pub use grid_areas::GridAreas;
+pub use lint::DiagramLint;
 pub use svg::{GenerateSvg, GenerateSvgElement, Svg, SvgElement, SvgError};
@@ -36,6 +36,8 @@ impl<'a> GenerateSvgElement for ElementContent<'a> {
     fn generate_svg(&self, svg: &mut Svg, header: &ElementHeader) -> Result<(), SvgError> {
         match self {
             ElementContent::Path(ref s) => s.generate_svg(svg, header),
+            ElementContent::Connector(ref s) => s.generate_svg(svg, header),
+            ElementContent::Label(ref s) => s.generate_svg(svg, header),
             ElementContent::Shape(ref s) => s.generate_svg(svg, header),
             ElementContent::Text(ref t) => t.generate_svg(svg, header),
             ElementContent::Group(ref g) => g.generate_svg(svg, header),
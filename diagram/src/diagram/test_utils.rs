@@ -0,0 +1,70 @@
+/*a Copyright
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+
+@file    test_utils.rs
+@brief   Functions shared by the tests of the diagram module
+ */
+
+//a Imports
+use vg_rs::BBox;
+
+use crate::{Diagram, DiagramDescriptor, DiagramML};
+
+//a Types
+//tp Ranges
+/// The X and Y ranges of a number of rectangles, as (x0, x1, y0, y1)
+pub type Ranges = Vec<(f64, f64, f64, f64)>;
+
+//a Functions
+//fp layout_content
+/// Read HML with a single layout into a new diagram and lay it out,
+/// and get the X and Y ranges of the laid-out content rectangles of
+/// the content of the layout (rounded to remove numerical noise), or
+/// the errors in styling it
+pub fn layout_content(text: &str) -> Result<Ranges, Vec<String>> {
+    let style_set = DiagramDescriptor::create_style_set();
+    let diagram_descriptor = DiagramDescriptor::new(&style_set);
+    let mut diagram = Diagram::new(&diagram_descriptor);
+    let mut dml = DiagramML::new(&mut diagram);
+    dml.read_file(text.as_bytes(), false).unwrap();
+    assert!(diagram.uniquify().is_ok());
+    assert!(diagram.apply_stylesheet().is_ok());
+    if let Err(mut errors) = diagram.style() {
+        return Err(errors.take().iter().map(|e| e.to_string()).collect());
+    }
+    assert!(diagram.layout(&BBox::none()).is_ok());
+    let (contents, _) = diagram.borrow_contents_stylesheet();
+    let root = contents.root_layout.as_ref().unwrap();
+    let layout = &root.content.borrow_group().unwrap().content[0];
+    Ok(layout
+        .content
+        .borrow_group()
+        .unwrap()
+        .content
+        .iter()
+        .map(|e| {
+            let layout_box = &e.header.layout_box;
+            let r = layout_box
+                .get_content_rectangle()
+                .transform(layout_box.content_transform().unwrap());
+            let round = |x: f64| (x * 1000.).round() / 1000.;
+            (
+                round(r.x.min()),
+                round(r.x.max()),
+                round(r.y.min()),
+                round(r.y.max()),
+            )
+        })
+        .collect())
+}
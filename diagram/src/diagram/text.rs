@@ -18,21 +18,311 @@ limitations under the License.
 
 //a Imports
 use super::font::*;
+use super::Color;
 use std::cell::RefCell;
 use std::rc::Rc;
+use stylesheet::TypeValue;
 
-//a Text span
-#[derive(Debug)]
+//a Constants
+/// Size of superscript and subscript text relative to the text around it
+const SCRIPT_SCALE: f64 = 0.7;
+
+/// Rise of the baseline of superscript text (and fall for subscript)
+/// in ems of the text around it
+const SCRIPT_RISE: f64 = 0.35;
+
+//a Wrap
+//tp Wrap
+/// The method used to break lines of text that are too wide
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Wrap {
+    /// Break as late as possible on each line
+    #[default]
+    Greedy,
+    /// Break to minimize the sum of the squares of the space left at
+    /// the end of each line but the last
+    Optimal,
+}
+
+//ip Wrap
+impl Wrap {
+    //fp of_str
+    pub fn of_str(s: &str) -> Option<Self> {
+        match s {
+            "greedy" => Some(Self::Greedy),
+            "optimal" => Some(Self::Optimal),
+            _ => None,
+        }
+    }
+}
+
+//a TextAlign
+//tp TextAlign
+/// The horizontal alignment of the lines of a text area within its width
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Lines that are not the last of a paragraph are stretched at
+    /// their spaces to the full width; others are left aligned
+    Justify,
+}
+
+//ip TextAlign
+impl TextAlign {
+    //fp of_str
+    pub fn of_str(s: &str) -> Option<Self> {
+        match s {
+            "left" => Some(Self::Left),
+            "center" => Some(Self::Center),
+            "right" => Some(Self::Right),
+            "justify" => Some(Self::Justify),
+            _ => None,
+        }
+    }
+}
+
+//tp TextAnchor
+/// The part of a span of text that its position is for, as per SVG 'text-anchor'
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAnchor {
+    Start,
+    Middle,
+    End,
+}
+
+//ip TextAnchor
+impl TextAnchor {
+    //mp as_str
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Middle => "middle",
+            Self::End => "end",
+        }
+    }
+}
+
+//a Markup
+//tp Markup
+/// The inline markup of a span of text, which modifies the style of
+/// the text element it is in
+///
+/// The markup is `*bold*`, `_italic_`, `` `code` `` (monospace, with
+/// no markup within it), `^{superscript}`, `_{subscript}` and
+/// `{color:text}`; a backslash escapes the next character.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Markup {
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+    pub color: Option<(f64, f64, f64)>,
+    /// 1 for superscript, -1 for subscript, else 0
+    pub shift: isize,
+}
+
+//ip Markup
+impl Markup {
+    //mp is_none
+    /// Return true if the markup makes no change to the text
+    pub fn is_none(&self) -> bool {
+        *self == Self::default()
+    }
+
+    //mp scale
+    /// Get the size of the text relative to the text around it
+    pub fn scale(&self) -> f64 {
+        if self.shift == 0 {
+            1.
+        } else {
+            SCRIPT_SCALE
+        }
+    }
+
+    //mp rise
+    /// Get the rise of the baseline for the markup given the em (in
+    /// mm) of the text around it
+    pub fn rise(&self, em: f64) -> f64 {
+        (self.shift as f64) * SCRIPT_RISE * em
+    }
+
+    //fp parse
+    /// Parse a line of text with markup in to runs of text with their markup
+    pub fn parse(text: &str) -> Result<Vec<(Markup, String)>, String> {
+        let mut runs = Vec::new();
+        let mut markup = Markup::default();
+        let mut run = String::new();
+        // The markup to return to at each unclosed '{'
+        let mut closers = Vec::new();
+        let mut chars = text.chars().peekable();
+        let flush = |runs: &mut Vec<(Markup, String)>, markup: &Markup, run: &mut String| {
+            if !run.is_empty() {
+                runs.push((markup.clone(), std::mem::take(run)));
+            }
+        };
+        while let Some(c) = chars.next() {
+            if markup.code && c != '`' {
+                run.push(c);
+                continue;
+            }
+            match c {
+                '\\' => {
+                    if let Some(c) = chars.next() {
+                        run.push(c);
+                    }
+                }
+                '*' => {
+                    flush(&mut runs, &markup, &mut run);
+                    markup.bold = !markup.bold;
+                }
+                '`' => {
+                    flush(&mut runs, &markup, &mut run);
+                    markup.code = !markup.code;
+                }
+                '^' | '_' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    flush(&mut runs, &markup, &mut run);
+                    closers.push(markup.clone());
+                    markup.shift = if c == '^' { 1 } else { -1 };
+                }
+                '_' => {
+                    flush(&mut runs, &markup, &mut run);
+                    markup.italic = !markup.italic;
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(':') => break,
+                            Some(c) if c != '}' => name.push(c),
+                            _ => {
+                                return Err(format!(
+                                    "expected '{{color:text}}' in text markup '{}'",
+                                    text
+                                ));
+                            }
+                        }
+                    }
+                    let mut color = Color::default();
+                    let mut rgb = [0.; 4];
+                    let rgb = color
+                        .parse_string(name.trim(), false)
+                        .ok()
+                        .and_then(|_| color.get_floats(&mut rgb))
+                        .ok_or_else(|| format!("unknown color '{}' in text markup", name))?;
+                    flush(&mut runs, &markup, &mut run);
+                    closers.push(markup.clone());
+                    markup.color = Some((rgb[0], rgb[1], rgb[2]));
+                }
+                '}' => match closers.pop() {
+                    Some(m) => {
+                        flush(&mut runs, &markup, &mut run);
+                        markup = m;
+                    }
+                    None => run.push(c),
+                },
+                _ => run.push(c),
+            }
+        }
+        if !closers.is_empty() {
+            return Err(format!("unclosed '{{' in text markup '{}'", text));
+        }
+        flush(&mut runs, &markup, &mut run);
+        Ok(runs)
+    }
+}
+
+//a Bullet
+//tp Bullet
+/// The bullet or number of an item in a list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Bullet {
     Char(char),
+    /// Numbered '1.', '2.', ...
     Numeral(usize),
+    /// Numbered 'a.', 'b.', ...
     Alphabet(usize),
+    /// Numbered 'i.', 'ii.', ...
+    Roman(usize),
+}
+
+//ip Bullet
+impl Bullet {
+    //fp of_level
+    /// Get the bullet for an item of an unnumbered list at a nesting level
+    pub fn of_level(level: usize) -> Self {
+        Self::Char(['\u{2022}', '\u{25e6}', '\u{25aa}'][level % 3])
+    }
+
+    //fp number
+    /// Get the number for an item of a numbered list at a nesting level
+    pub fn number(level: usize, n: usize) -> Self {
+        match level % 3 {
+            0 => Self::Numeral(n),
+            1 => Self::Alphabet(n),
+            _ => Self::Roman(n),
+        }
+    }
+
+    //mp label
+    /// Get the text of the bullet or number
+    pub fn label(&self) -> String {
+        match self {
+            Self::Char(c) => c.to_string(),
+            Self::Numeral(n) => format!("{}.", n),
+            Self::Alphabet(n) => {
+                let mut s = String::new();
+                let mut n = *n;
+                while n > 0 {
+                    n -= 1;
+                    s.insert(0, (b'a' + (n % 26) as u8) as char);
+                    n /= 26;
+                }
+                s.push('.');
+                s
+            }
+            Self::Roman(n) => {
+                const NUMERALS: [(usize, &str); 13] = [
+                    (1000, "m"),
+                    (900, "cm"),
+                    (500, "d"),
+                    (400, "cd"),
+                    (100, "c"),
+                    (90, "xc"),
+                    (50, "l"),
+                    (40, "xl"),
+                    (10, "x"),
+                    (9, "ix"),
+                    (5, "v"),
+                    (4, "iv"),
+                    (1, "i"),
+                ];
+                let mut s = String::new();
+                let mut n = *n;
+                for (v, r) in NUMERALS {
+                    while n >= v {
+                        s.push_str(r);
+                        n -= v;
+                    }
+                }
+                s.push('.');
+                s
+            }
+        }
+    }
 }
+
+//a Text span
 #[derive(Debug)]
 pub struct TextSpan<F: FontMetrics> {
     font: Rc<RefCell<F>>,
     style: FontStyle,
     text: String, // single line
+    markup: Markup,
+    /// Rise of the baseline of the span in mm
+    rise: f64,
 }
 
 impl<F: FontMetrics> TextSpan<F> {
@@ -41,18 +331,67 @@ impl<F: FontMetrics> TextSpan<F> {
             font,
             style,
             text: text.to_string(),
+            markup: Markup::default(),
+            rise: 0.,
+        }
+    }
+    /// Create a span of text with markup, given the style of the text around it
+    pub fn of_markup(text: &str, font: Rc<RefCell<F>>, style: FontStyle, markup: Markup) -> Self {
+        let mut flags = 0;
+        if markup.bold {
+            flags |= FONT_BOLD;
+        }
+        if markup.italic {
+            flags |= FONT_ITALIC;
+        }
+        let rise = markup.rise(style.size() * 25.4 / 72.0);
+        let style = style.with_flags(flags).scaled(markup.scale());
+        Self {
+            font,
+            style,
+            text: text.to_string(),
+            markup,
+            rise,
+        }
+    }
+    /// Create a span with the font and style of this one but different text
+    pub fn with_text(&self, text: &str) -> Self {
+        Self {
+            font: self.font.clone(),
+            style: self.style,
+            text: text.to_string(),
+            markup: self.markup.clone(),
+            rise: self.rise,
         }
     }
     pub fn get_metrics(&self) -> TextMetrics {
-        self.font.borrow().get_metrics(&self.text, &self.style)
+        let mut tm = self.font.borrow().get_metrics(&self.text, &self.style);
+        tm.ascender = (tm.ascender + self.rise).max(0.);
+        tm.descender = (tm.descender - self.rise).max(0.);
+        tm
     }
 }
 
 //a Text line
+/// A word of a line of text, used in wrapping; this is the spans
+/// that make up the word (with the index of the span of the line that
+/// each is from), its width, and the width of the space that precedes it
+struct Word<F: FontMetrics> {
+    pieces: Vec<(usize, TextSpan<F>)>,
+    space: f64,
+    width: f64,
+}
+
 #[derive(Debug)]
 pub struct TextLine<F: FontMetrics> {
     indent: usize,
     bullet: Option<Bullet>,
+    /// The span for the bullet of the line, or of the line it
+    /// continues (when it has no bullet itself, but has the hanging
+    /// indent of the bullet)
+    label: Option<TextSpan<F>>,
+    /// True unless the line is wrapped and continues on the next line
+    ends_paragraph: bool,
     spans: Vec<TextSpan<F>>,
 }
 impl<F: FontMetrics> TextLine<F> {
@@ -60,22 +399,181 @@ impl<F: FontMetrics> TextLine<F> {
         Self {
             indent,
             bullet,
+            label: None,
+            ends_paragraph: true,
             spans: Vec::new(),
         }
     }
     pub fn add_span(&mut self, span: TextSpan<F>) {
         self.spans.push(span);
     }
+    /// Set the font of the bullet of the line, if it has one
+    pub fn set_bullet_font(&mut self, font: Rc<RefCell<F>>, style: FontStyle) {
+        if let Some(bullet) = &self.bullet {
+            self.label = Some(TextSpan::new(&bullet.label(), font, style));
+        }
+    }
+    /// Get the width of the indent of each nesting level and the
+    /// hanging indent of a bullet, from the metrics of the font of
+    /// the bullet or text; the hanging indent is wide enough for a
+    /// two-digit number
+    fn indent_widths(&self) -> (f64, f64) {
+        let Some(span) = self.label.as_ref().or(self.spans.first()) else {
+            return (0., 0.);
+        };
+        let indent = span.with_text("    ").get_metrics().width;
+        let hang = match &self.label {
+            None => 0.,
+            Some(label) => {
+                label
+                    .get_metrics()
+                    .width
+                    .max(label.with_text("00.").get_metrics().width)
+                    + label.with_text(" ").get_metrics().width
+            }
+        };
+        (indent, hang)
+    }
+    /// Get the offset of the text of the line, for its indent and bullet
     pub fn line_metrics(&self) -> f64 {
-        (self.indent as f64) * 5.
+        let (indent, hang) = self.indent_widths();
+        (self.indent as f64) * indent + hang
+    }
+    /// Split the text of the line in to words at whitespace; a word
+    /// may be made of pieces from more than one span
+    fn words(&self) -> Vec<Word<F>> {
+        let mut words: Vec<Word<F>> = Vec::new();
+        let mut in_word = false;
+        for (i, s) in self.spans.iter().enumerate() {
+            let space = s.with_text(" ").get_metrics().width;
+            let mut text = s.text.as_str();
+            while !text.is_empty() {
+                let trimmed = text.trim_start();
+                if trimmed.len() != text.len() {
+                    in_word = false;
+                    text = trimmed;
+                    continue;
+                }
+                let end = text.find(char::is_whitespace).unwrap_or(text.len());
+                let piece = s.with_text(&text[0..end]);
+                let width = piece.get_metrics().width;
+                match words.last_mut() {
+                    Some(w) if in_word => {
+                        w.pieces.push((i, piece));
+                        w.width += width;
+                    }
+                    _ => {
+                        words.push(Word {
+                            pieces: vec![(i, piece)],
+                            space,
+                            width,
+                        });
+                    }
+                }
+                in_word = true;
+                text = &text[end..];
+            }
+        }
+        words
+    }
+    /// Create a line from some words; this is the first line of the
+    /// wrapped line if `first`, else a continuation of it
+    fn of_words(&self, words: &[Word<F>], first: bool) -> Self {
+        let mut line = Self::new(self.indent, None);
+        if first {
+            line.bullet = self.bullet;
+        }
+        line.label = self.label.as_ref().map(|l| l.with_text(&l.text));
+        let mut last_span = None;
+        for (n, w) in words.iter().enumerate() {
+            for (p, (i, piece)) in w.pieces.iter().enumerate() {
+                let sep = if n > 0 && p == 0 { " " } else { "" };
+                if last_span == Some(*i) {
+                    let span = line.spans.last_mut().unwrap();
+                    span.text.push_str(sep);
+                    span.text.push_str(&piece.text);
+                } else {
+                    line.add_span(piece.with_text(&format!("{}{}", sep, piece.text)));
+                }
+                last_span = Some(*i);
+            }
+        }
+        line
+    }
+    /// Wrap the line so that its lines are no wider than `width`
+    /// where possible (a single word may be wider)
+    pub fn wrap(&self, width: f64, wrap: Wrap) -> Vec<Self> {
+        let words = self.words();
+        if words.is_empty() {
+            return vec![self.of_words(&words, true)];
+        }
+        let width = width - self.line_metrics();
+        let breaks = match wrap {
+            Wrap::Greedy => Self::greedy_breaks(&words, width),
+            Wrap::Optimal => Self::optimal_breaks(&words, width),
+        };
+        let mut lines = Vec::new();
+        let mut start = 0;
+        for end in breaks {
+            let mut line = self.of_words(&words[start..end], start == 0);
+            line.ends_paragraph = end == words.len();
+            lines.push(line);
+            start = end;
+        }
+        lines
+    }
+    /// Find the ends of lines, breaking each line as late as possible
+    fn greedy_breaks(words: &[Word<F>], width: f64) -> Vec<usize> {
+        let mut breaks = Vec::new();
+        let mut line_width = words[0].width;
+        for (i, w) in words.iter().enumerate().skip(1) {
+            if line_width + w.space + w.width > width {
+                breaks.push(i);
+                line_width = w.width;
+            } else {
+                line_width += w.space + w.width;
+            }
+        }
+        breaks.push(words.len());
+        breaks
+    }
+    /// Find the ends of lines that minimize the sum of the squares of
+    /// the space left at the end of each line but the last
+    fn optimal_breaks(words: &[Word<F>], width: f64) -> Vec<usize> {
+        let n = words.len();
+        // cost[i] is the minimum cost of setting words[i..], with the first line ending at end[i]
+        let mut cost = vec![0.; n + 1];
+        let mut end = vec![n; n + 1];
+        for i in (0..n).rev() {
+            cost[i] = f64::MAX;
+            let mut line_width = words[i].width;
+            for j in (i + 1)..=n {
+                if j > i + 1 {
+                    line_width += words[j - 1].space + words[j - 1].width;
+                    if line_width > width {
+                        break;
+                    }
+                }
+                let slack = (width - line_width).max(0.);
+                let line_cost = if j == n { 0. } else { slack * slack };
+                if line_cost + cost[j] < cost[i] {
+                    cost[i] = line_cost + cost[j];
+                    end[i] = j;
+                }
+            }
+        }
+        let mut breaks = Vec::new();
+        let mut i = 0;
+        while i < n {
+            i = end[i];
+            breaks.push(i);
+        }
+        breaks
     }
     pub fn get_metrics(&self) -> TextMetrics {
         let mut max_asc = 0.;
         let mut max_desc = 0.;
         let mut width = self.line_metrics();
-        if self.bullet.is_some() {
-            width += 7.;
-        }
         for s in &self.spans {
             let tm = s.get_metrics();
             width += tm.width;
@@ -96,54 +594,169 @@ impl<F: FontMetrics> TextLine<F> {
 
 //a Text area
 //ip TextArea
-#[derive(Debug, Default)]
+/// The text of a text element, as lines of spans; the lines are
+/// those added, unless they have been wrapped
+#[derive(Debug)]
 pub struct TextArea<F: FontMetrics> {
-    lines: Vec<TextLine<F>>,
+    /// Lines as added
+    paragraphs: Vec<TextLine<F>>,
+    /// Lines after wrapping, if wrapped
+    wrapped: Option<Vec<TextLine<F>>>,
+    /// Alignment of the lines within the width of the area
+    align: TextAlign,
+    /// Spacing of lines as a multiple of their height
+    line_spacing: f64,
+    /// The number of the last item of the numbered list at each
+    /// nesting level, or 0 if there is no list at that level
+    list_numbers: Vec<usize>,
+}
+
+//ip Default for TextArea
+impl<F: FontMetrics> Default for TextArea<F> {
+    fn default() -> Self {
+        Self {
+            paragraphs: Vec::new(),
+            wrapped: None,
+            align: TextAlign::default(),
+            line_spacing: 1.,
+            list_numbers: Vec::new(),
+        }
+    }
 }
 
 //ip TextArea
 impl<F: FontMetrics> TextArea<F> {
     //mp add_line
     pub fn add_line(&mut self, line: TextLine<F>) {
-        self.lines.push(line);
+        self.paragraphs.push(line);
+        self.wrapped = None;
+    }
+
+    //mp set_align
+    /// Set the alignment of the lines of the text
+    pub fn set_align(&mut self, align: TextAlign) {
+        self.align = align;
+    }
+
+    //mp set_line_spacing
+    /// Set the spacing of lines as a multiple of their height (ascender plus descender)
+    pub fn set_line_spacing(&mut self, line_spacing: f64) {
+        self.line_spacing = line_spacing;
+    }
+
+    //mi lines
+    /// Get the lines of the text area, wrapped if they have been
+    fn lines(&self) -> &[TextLine<F>] {
+        self.wrapped.as_ref().unwrap_or(&self.paragraphs)
+    }
+
+    //mp wrap
+    /// Wrap the lines of text added to the area to a width, or unwrap
+    /// them if the width is None
+    pub fn wrap(&mut self, width: Option<f64>, wrap: Wrap) {
+        self.wrapped = width.map(|width| {
+            self.paragraphs
+                .iter()
+                .flat_map(|l| l.wrap(width, wrap))
+                .collect()
+        });
+    }
+
+    //mi line_of_text
+    /// Create a line for some text, given its indent and any list
+    /// item prefix at its start, and return it with the rest of the
+    /// text
+    ///
+    /// Each two spaces at the start of the text are a level of
+    /// indent. Lists items start with '* ', '+ ' or '- ' for a bullet,
+    /// and '# ' or a number and '. ' (such as '1. ') for a numbered
+    /// list; numbers count up from 1 (or the number given for the
+    /// first item) for each list at each nesting level, and a list ends
+    /// at a line at the same or shallower level that is not an item of
+    /// it.
+    fn line_of_text<'t>(&mut self, text: &'t str) -> (TextLine<F>, &'t str) {
+        let mut sl = text;
+        let mut indent = 0;
+        while sl.starts_with("  ") {
+            indent += 1;
+            sl = &sl[2..];
+        }
+        let digits = sl.len() - sl.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let (number, prefix) = {
+            if sl.starts_with("* ") || sl.starts_with("+ ") || sl.starts_with("- ") {
+                (None, 2)
+            } else if sl.starts_with("# ") {
+                (Some(None), 2)
+            } else if digits > 0 && sl[digits..].starts_with(". ") {
+                (Some(sl[0..digits].parse::<usize>().ok()), digits + 2)
+            } else {
+                (None, 0)
+            }
+        };
+        self.list_numbers.resize(indent + 1, 0);
+        let bullet = match number {
+            _ if prefix == 0 => {
+                self.list_numbers[indent] = 0;
+                None
+            }
+            None => {
+                self.list_numbers[indent] = 0;
+                Some(Bullet::of_level(indent))
+            }
+            Some(n) => {
+                let last = self.list_numbers[indent];
+                let n = match n {
+                    Some(n) if last == 0 => n,
+                    _ => last + 1,
+                };
+                self.list_numbers[indent] = n;
+                Some(Bullet::number(indent, n))
+            }
+        };
+        (TextLine::new(indent, bullet), &sl[prefix..])
     }
 
     //mp add_text
     pub fn add_text(&mut self, text: &str, font: Rc<RefCell<F>>, style: FontStyle) {
         for l in text.lines() {
-            let mut bullet = None;
-            let mut sl = l;
-            let mut indent = 0;
-            while sl.starts_with("  ") {
-                indent += 1;
-                sl = &sl[2..];
-            }
-            if sl.starts_with("* ") {
-                bullet = Some(Bullet::Char('*'));
-                sl = &sl[2..];
-            } else if sl.starts_with("+ ") {
-                bullet = Some(Bullet::Char('+'));
-                sl = &sl[2..];
-            } else if sl.starts_with("# ") {
-                bullet = Some(Bullet::Numeral(0));
-                sl = &sl[2..];
-            }
-            let mut line = TextLine::new(indent, bullet);
+            let (mut line, sl) = self.line_of_text(l);
+            line.set_bullet_font(font.clone(), style);
             line.add_span(TextSpan::new(sl, font.clone(), style));
             self.add_line(line);
         }
     }
 
+    //mp add_markup
+    /// Add text with inline markup (see [Markup]); code is in `code_font`
+    pub fn add_markup(
+        &mut self,
+        text: &str,
+        font: Rc<RefCell<F>>,
+        code_font: Rc<RefCell<F>>,
+        style: FontStyle,
+    ) -> Result<(), String> {
+        for l in text.lines() {
+            let (mut line, sl) = self.line_of_text(l);
+            line.set_bullet_font(font.clone(), style);
+            for (markup, run) in Markup::parse(sl)? {
+                let font = if markup.code { &code_font } else { &font };
+                line.add_span(TextSpan::of_markup(&run, font.clone(), style, markup));
+            }
+            self.add_line(line);
+        }
+        Ok(())
+    }
+
     //mp get_bbox
     pub fn get_bbox(&self) -> (f64, f64) {
         let mut width = 0.;
         let mut height = 0.;
-        for l in &self.lines {
+        for l in self.lines() {
             let tm = l.get_metrics();
             if width < tm.width {
                 width = tm.width;
             }
-            height += tm.ascender + tm.descender;
+            height += (tm.ascender + tm.descender) * self.line_spacing;
         }
         (width, height)
     }
@@ -158,14 +771,18 @@ impl<F: FontMetrics> TextArea<F> {
 
 //a TextSpan iterator
 //tp TextSpanIter
+/// An iterator over the spans of text of a text area, with their
+/// positions given the alignment and line spacing of the area
 pub struct TextSpanIter<'a, F: FontMetrics> {
     area: &'a TextArea<F>,
+    /// Width of the text area
+    width: f64,
+    /// Y of the top of the next line
     top_y: f64,
-    x: f64,
-    ascender: f64,
-    descender: f64,
+    /// Next line to generate the span elements for
     line: usize,
-    span: usize,
+    /// Span elements of the current line, in reverse order
+    elements: Vec<TextSpanElement<'a>>,
 }
 
 //ip TextSpanIter
@@ -173,55 +790,329 @@ impl<'a, F: FontMetrics> TextSpanIter<'a, F> {
     pub fn new(area: &'a TextArea<F>) -> Self {
         Self {
             area,
+            width: area.get_bbox().0,
             top_y: 0.,
-            x: 0.,
             line: 0,
-            span: 0,
-            ascender: 0.,
-            descender: 0.,
+            elements: Vec::new(),
         }
     }
+
+    //mi add_line_elements
+    /// Add the span elements of a line and move on to the next
+    fn add_line_elements(&mut self, line: &'a TextLine<F>) {
+        let tm = line.get_metrics();
+        let height = tm.ascender + tm.descender;
+        let y = self.top_y + height * (self.area.line_spacing - 1.) * 0.5 + tm.ascender;
+        self.top_y += height * self.area.line_spacing;
+        let lm = line.line_metrics();
+        let slack = self.width - tm.width;
+        let justify = self.area.align == TextAlign::Justify && !line.ends_paragraph;
+        let (mut x, anchor) = match self.area.align {
+            TextAlign::Center if line.spans.len() == 1 && line.bullet.is_none() => {
+                (lm + (slack + tm.width - lm) * 0.5, TextAnchor::Middle)
+            }
+            TextAlign::Right if line.spans.len() == 1 && line.bullet.is_none() => {
+                (self.width, TextAnchor::End)
+            }
+            TextAlign::Center => (lm + slack * 0.5, TextAnchor::Start),
+            TextAlign::Right => (lm + slack, TextAnchor::Start),
+            _ => (lm, TextAnchor::Start),
+        };
+        if let (Some(label), Some(_)) = (&line.label, &line.bullet) {
+            // The bullet is at the start of the hanging indent
+            let (_, hang) = line.indent_widths();
+            self.elements.push(TextSpanElement {
+                line: self.line,
+                x: x - hang,
+                y,
+                text: &label.text,
+                anchor: TextAnchor::Start,
+                markup: &label.markup,
+            });
+        }
+        if justify {
+            // Words of the line as (span, word, width of whitespace before it)
+            let mut words = Vec::new();
+            for span in &line.spans {
+                let mut text = span.text.as_str();
+                while !text.is_empty() {
+                    let start = text.len() - text.trim_start().len();
+                    let space = span.with_text(&text[0..start]).get_metrics().width;
+                    text = &text[start..];
+                    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+                    if end > 0 {
+                        words.push((span, &text[0..end], space));
+                    }
+                    text = &text[end..];
+                }
+            }
+            let gaps = words.iter().skip(1).filter(|(_, _, s)| *s > 0.).count();
+            let extra = if gaps > 0 { slack / (gaps as f64) } else { 0. };
+            for (n, (span, text, space)) in words.into_iter().enumerate() {
+                if n > 0 && space > 0. {
+                    x += space + extra;
+                }
+                self.elements.push(TextSpanElement {
+                    line: self.line,
+                    x,
+                    y: y - span.rise,
+                    text,
+                    anchor,
+                    markup: &span.markup,
+                });
+                x += span.with_text(text).get_metrics().width;
+            }
+        } else {
+            for span in &line.spans {
+                self.elements.push(TextSpanElement {
+                    line: self.line,
+                    x,
+                    y: y - span.rise,
+                    text: &span.text,
+                    anchor,
+                    markup: &span.markup,
+                });
+                x += span.get_metrics().width;
+            }
+        }
+        self.elements.reverse();
+        self.line += 1;
+    }
 }
 
+//tp TextSpanElement
+/// A span of text positioned in the text area; `x` is the start,
+/// middle or end of the text as given by `anchor`
 #[derive(Debug)]
 pub struct TextSpanElement<'a> {
+    /// Index of the line the span is in
+    pub line: usize,
     pub x: f64,
     pub y: f64,
     pub text: &'a str,
+    pub anchor: TextAnchor,
+    pub markup: &'a Markup,
 }
 
 //ip Iterator for TextSpanIter
 impl<'a, F: FontMetrics> Iterator for TextSpanIter<'a, F> {
     type Item = TextSpanElement<'a>;
     fn next(&mut self) -> Option<Self::Item> {
-        if self.line >= self.area.lines.len() {
-            None
-        } else if self.span >= self.area.lines[self.line].spans.len() {
-            self.span = 0;
-            self.line += 1;
-            self.top_y += self.ascender + self.descender;
-            self.next()
-        } else {
-            if self.span == 0 {
-                let lm = self.area.lines[self.line].line_metrics();
-                let tm = self.area.lines[self.line].get_metrics();
-                self.ascender = tm.ascender;
-                self.descender = tm.descender;
-                self.x = lm;
-            }
-            let i = self.span;
-            self.span += 1;
-            let span = &self.area.lines[self.line].spans[i];
-            let tm = span.get_metrics();
-            let y = self.top_y + self.ascender; // not sure where the text should go - tm.ascender;
-            let x = self.x;
-            self.x += tm.width;
-            let text_span_element = TextSpanElement {
-                x,
-                y,
-                text: &span.text,
-            };
-            Some(text_span_element)
+        let lines = self.area.lines();
+        while self.elements.is_empty() {
+            if self.line >= lines.len() {
+                return None;
+            }
+            self.add_line_elements(&lines[self.line]);
         }
+        self.elements.pop()
+    }
+}
+
+//a Test
+#[cfg(test)]
+mod test_text {
+    use super::*;
+
+    //fi lines_of
+    /// Wrap some text in the default (monospace) font at a size where
+    /// each character is 1mm wide, and return the text of the lines
+    fn lines_of(text: &str, width: f64, wrap: Wrap) -> Vec<String> {
+        let font = Rc::new(RefCell::new(Font::default()));
+        let style = FontStyle::new(72. / 25.4 * 2., None, None);
+        let mut area = TextArea::default();
+        area.add_text(text, font, style);
+        area.wrap(Some(width), wrap);
+        let mut lines: Vec<String> = Vec::new();
+        let mut last_y = None;
+        for t in area.iter_spans() {
+            if last_y == Some(t.y) {
+                lines.last_mut().unwrap().push_str(t.text);
+            } else {
+                lines.push(t.text.to_string());
+            }
+            last_y = Some(t.y);
+        }
+        lines
+    }
+
+    //ft test_greedy
+    #[test]
+    fn test_greedy() {
+        assert_eq!(
+            lines_of("aaa bb cc ddddd", 6., Wrap::Greedy),
+            vec!["aaa bb", "cc", "ddddd"]
+        );
+        assert_eq!(
+            lines_of("aaa  bb\nverylongword x", 6., Wrap::Greedy),
+            vec!["aaa bb", "verylongword", "x"]
+        );
+    }
+
+    //ft test_optimal
+    #[test]
+    fn test_optimal() {
+        assert_eq!(
+            lines_of("aaa bb cc ddddd", 6., Wrap::Optimal),
+            vec!["aaa", "bb cc", "ddddd"]
+        );
+        assert_eq!(lines_of("aaa bb", 20., Wrap::Optimal), vec!["aaa bb"]);
+    }
+
+    //ft test_bbox
+    #[test]
+    fn test_bbox() {
+        let font = Rc::new(RefCell::new(Font::default()));
+        let style = FontStyle::new(72. / 25.4 * 2., None, None);
+        let mut area = TextArea::default();
+        area.add_text("aaa bb cc", font, style);
+        let (w, h) = area.get_bbox();
+        assert!((w - 9.).abs() < 1E-6);
+        area.wrap(Some(6.), Wrap::Greedy);
+        let (w2, h2) = area.get_bbox();
+        assert!((w2 - 6.).abs() < 1E-6);
+        assert!((h2 - 2. * h).abs() < 1E-6);
+        area.wrap(None, Wrap::Greedy);
+        assert!((area.get_bbox().0 - 9.).abs() < 1E-6);
+    }
+
+    //fi spans_of
+    /// Lay out some text wrapped to 6mm, aligned, with characters of 1mm
+    fn spans_of(
+        text: &str,
+        align: TextAlign,
+        line_spacing: f64,
+    ) -> Vec<(f64, f64, String, TextAnchor)> {
+        let font = Rc::new(RefCell::new(Font::default()));
+        let style = FontStyle::new(72. / 25.4 * 2., None, None);
+        let mut area = TextArea::default();
+        area.add_text(text, font, style);
+        area.wrap(Some(6.), Wrap::Greedy);
+        area.set_align(align);
+        area.set_line_spacing(line_spacing);
+        area.iter_spans()
+            .map(|t| (t.x, t.y, t.text.to_string(), t.anchor))
+            .collect()
+    }
+
+    //ft test_align
+    #[test]
+    fn test_align() {
+        // Lines of 'aa bbb', 'c d' and 'eeeee'
+        let text = "aa bbb c d eeeee";
+        let spans = spans_of(text, TextAlign::Left, 1.);
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans[1].0, 0.);
+        assert_eq!(spans[1].3, TextAnchor::Start);
+
+        let spans = spans_of(text, TextAlign::Center, 1.);
+        assert!((spans[1].0 - 3.).abs() < 1E-6);
+        assert_eq!(spans[1].3, TextAnchor::Middle);
+
+        let spans = spans_of(text, TextAlign::Right, 1.);
+        assert!((spans[2].0 - 6.).abs() < 1E-6);
+        assert_eq!(spans[2].3, TextAnchor::End);
+
+        // Justified lines are split in to words, except the last line
+        let spans = spans_of(text, TextAlign::Justify, 1.);
+        let words: Vec<&str> = spans.iter().map(|s| s.2.as_str()).collect();
+        assert_eq!(words, vec!["aa", "bbb", "c", "d", "eeeee"]);
+        assert!((spans[1].0 - 3.).abs() < 1E-6);
+        assert!((spans[3].0 - 5.).abs() < 1E-6);
+        assert_eq!(spans[4].0, 0.);
+    }
+
+    //ft test_line_spacing
+    #[test]
+    fn test_line_spacing() {
+        let single = spans_of("aa bbb c d", TextAlign::Left, 1.);
+        let double = spans_of("aa bbb c d", TextAlign::Left, 2.);
+        let line_height = single[1].1 - single[0].1;
+        assert!((double[1].1 - double[0].1 - 2. * line_height).abs() < 1E-6);
+        assert!((double[0].1 - single[0].1 - 0.5 * line_height).abs() < 1E-6);
+    }
+
+    //ft test_lists
+    #[test]
+    fn test_lists() {
+        assert_eq!(
+            lines_of(
+                "1. a\n# b\n  # c\n  # d\n    # e\n# f\ng\n# h",
+                40.,
+                Wrap::Greedy
+            ),
+            vec!["1.a", "2.b", "a.c", "b.d", "i.e", "3.f", "g", "1.h"]
+        );
+        assert_eq!(
+            lines_of("4. a\n7. b\n- c\n  + d\n    * e", 40., Wrap::Greedy),
+            vec!["4.a", "5.b", "\u{2022}c", "\u{25e6}d", "\u{25aa}e"]
+        );
+        assert_eq!(Bullet::Alphabet(28).label(), "ab.");
+        assert_eq!(Bullet::Roman(1994).label(), "mcmxciv.");
+    }
+
+    //ft test_hanging_indent
+    #[test]
+    fn test_hanging_indent() {
+        // The hanging indent is the width of '00. ', and each level of
+        // nesting is indented by four spaces, all at 1mm per character
+        let spans = spans_of("1. aa b\n  - c", TextAlign::Left, 1.);
+        let xs: Vec<(f64, &str)> = spans.iter().map(|(x, _, t, _)| (*x, t.as_str())).collect();
+        assert_eq!(
+            xs,
+            vec![
+                (0., "1."),
+                (4., "aa"),
+                (4., "b"),
+                (4., "\u{25e6}"),
+                (8., "c")
+            ]
+        );
+    }
+
+    //ft test_markup
+    #[test]
+    fn test_markup() {
+        let runs = Markup::parse("reg *R0* is _x_ x_{2} or `a*b` ^{+1} {red:hot} \\*").unwrap();
+        let texts: Vec<&str> = runs.iter().map(|(_, s)| s.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec!["reg ", "R0", " is ", "x", " x", "2", " or ", "a*b", " ", "+1", " ", "hot", " *"]
+        );
+        assert!(runs[1].0.bold && !runs[2].0.bold);
+        assert!(runs[3].0.italic && !runs[4].0.italic);
+        assert_eq!(runs[5].0.shift, -1);
+        assert!(runs[7].0.code && !runs[7].0.bold);
+        assert_eq!(runs[9].0.shift, 1);
+        assert_eq!(runs[11].0.color, Some((1., 0., 0.)));
+        assert!(runs[12].0.is_none());
+
+        assert!(Markup::parse("^{unclosed").is_err());
+        assert!(Markup::parse("{nocolor:x}").is_err());
+        assert!(Markup::parse("{x").is_err());
+    }
+
+    //ft test_markup_spans
+    #[test]
+    fn test_markup_spans() {
+        let font = Rc::new(RefCell::new(Font::default()));
+        let style = FontStyle::new(72. / 25.4 * 2., None, None);
+        let mut area = TextArea::default();
+        area.add_markup("ab x^{2} *cd*", font.clone(), font, style)
+            .unwrap();
+        let spans: Vec<TextSpanElement> = area.iter_spans().collect();
+        let texts: Vec<&str> = spans.iter().map(|t| t.text).collect();
+        assert_eq!(texts, vec!["ab x", "2", " ", "cd"]);
+        // Superscript is smaller and raised
+        assert!((spans[1].x - 4.).abs() < 1E-6);
+        assert!((spans[2].x - 4.7).abs() < 1E-6);
+        assert!((spans[0].y - spans[1].y - 0.7).abs() < 1E-6);
+        // Bold in the default font is wider
+        assert!((area.get_bbox().0 - (5.7 + 2.2)).abs() < 1E-6);
+        // Wrapping keeps the markup of the spans
+        area.wrap(Some(5.), Wrap::Greedy);
+        let spans: Vec<TextSpanElement> = area.iter_spans().collect();
+        assert_eq!(spans.len(), 3);
+        assert!(spans[2].markup.bold);
     }
 }
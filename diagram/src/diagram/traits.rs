@@ -25,8 +25,7 @@ use crate::DiagramDescriptor;
 use vg_rs::layout::Layout;
 use vg_rs::BBox;
 
-pub use super::elements::{Group, Path, Shape, Text, Use};
-use super::{ElementError, ElementHeader, ElementScope};
+use super::{ElementError, ElementErrorList, ElementHeader, ElementScope};
 
 //a DiagramElementContent trait
 //tp DiagramElementContent
@@ -53,11 +52,15 @@ pub trait DiagramElementContent<'a, 'b>: Sized + std::fmt::Debug {
     /// The id_ref should identify an element in `scope`.
     /// The header may have to be cloned - it has layout information etc, and indeed any of its
     /// name/values override those of
+    ///
+    /// Errors in any content of the element are added to the list,
+    /// so that the rest of the content is still uniquified
     fn uniquify(
         &mut self,
         _header: &ElementHeader<'a>,
         _scope: &ElementScope<'a, 'b>,
         uid: usize,
+        _errors: &mut ElementErrorList,
     ) -> Result<(bool, usize), ElementError> {
         Ok((false, uid))
     }
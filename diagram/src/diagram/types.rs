@@ -32,6 +32,7 @@ pub type StyleSet = stylesheet::TypeSet;
 pub type StyleSheet<'a> = stylesheet::Stylesheet<'a>;
 pub type StyleRule = stylesheet::StylableNodeRule;
 pub type ValueError = stylesheet::ValueError;
+pub type StyleVariables = stylesheet::Variables;
 pub type RrcFont = Rc<RefCell<font::Font>>;
 
 //tp IndentedOptions
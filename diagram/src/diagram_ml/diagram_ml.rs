@@ -17,11 +17,120 @@ limitations under the License.
  */
 
 //a Imports
-use super::MLReader;
-use crate::Diagram;
+use super::{HmlMarkup, MLLibraries, MLMarkup, MLReader, MLWriter, MarkupFormat};
+use super::{XmlMarkup, XmlReader};
+use crate::{Diagram, DiagramContents, DiagramDescriptor, StyleSheet};
 use hml_rs::names::Namespace;
 use hml_rs::reader::Reader as HmlReader;
 use hml_rs::string::Reader as StringReader;
+use std::path::Path;
+
+//a Functions
+//fp read_markup
+/// Read markup (HML or XML) into diagram contents and its
+/// stylesheet, returning any errors formatted with their context in
+/// the markup
+///
+/// The ids of the definitions read (and the references of *use*
+/// elements) are given the prefix, if it is not empty.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn read_markup<'diag, F: std::io::Read>(
+    descriptor: &'diag DiagramDescriptor,
+    contents: &mut DiagramContents<'diag>,
+    stylesheet: &mut StyleSheet<'diag>,
+    libraries: &mut MLLibraries,
+    id_prefix: &str,
+    mut f: F,
+    format: MarkupFormat,
+    is_library: bool,
+) -> Result<(), Vec<String>> {
+    let mut text = String::new();
+    if let Err(e) = f.read_to_string(&mut text) {
+        return Err(vec![format!("{}", e)]);
+    }
+    match format {
+        MarkupFormat::Hml => {
+            let mut reader = StringReader::new(&text);
+            read_events(
+                descriptor,
+                contents,
+                stylesheet,
+                libraries,
+                id_prefix,
+                &mut reader,
+                Box::new(HmlMarkup::new()),
+                format,
+                is_library,
+            )
+        }
+        MarkupFormat::Xml => {
+            let mut reader = XmlReader::new(&text);
+            read_events(
+                descriptor,
+                contents,
+                stylesheet,
+                libraries,
+                id_prefix,
+                &mut reader,
+                Box::new(XmlMarkup::new(&text)),
+                format,
+                is_library,
+            )
+        }
+    }
+}
+
+//fi read_events
+/// Read the markup events of a reader into diagram contents and its
+/// stylesheet, returning any errors formatted with their context in
+/// the reader
+#[allow(clippy::too_many_arguments)]
+fn read_events<'diag, 'a, R: HmlReader>(
+    descriptor: &'diag DiagramDescriptor,
+    contents: &mut DiagramContents<'diag>,
+    stylesheet: &mut StyleSheet<'diag>,
+    libraries: &mut MLLibraries,
+    id_prefix: &str,
+    reader: &mut R,
+    markup: Box<dyn MLMarkup<R> + 'a>,
+    format: MarkupFormat,
+    is_library: bool,
+) -> Result<(), Vec<String>> {
+    let mut namespace = Namespace::new(true);
+    let mut ml_reader = MLReader::new(
+        contents,
+        stylesheet,
+        libraries,
+        id_prefix,
+        &mut namespace,
+        reader,
+        markup,
+        format,
+    );
+    let result = ml_reader.read_file(descriptor, is_library);
+    drop(ml_reader);
+    match result {
+        Err(mut err_list) => {
+            let mut r = Vec::new();
+            for e in err_list.take() {
+                let mut s = String::new();
+                use std::fmt::Write;
+                if let Some(span) = e.borrow_span() {
+                    reader
+                        .fmt_context(&mut s, span.start(), span.end())
+                        .unwrap();
+                    e.write_without_span(&mut s).unwrap();
+                    write!(&mut s, " at {}", span.start()).unwrap();
+                } else {
+                    write!(&mut s, "{}", e).unwrap();
+                }
+                r.push(s);
+            }
+            Err(r)
+        }
+        _ => Ok(()),
+    }
+}
 
 //a DiagramML
 //tp DiagramML
@@ -44,6 +153,8 @@ use hml_rs::string::Reader as StringReader;
 /// ```
 pub struct DiagramML<'a, 'diag> {
     diagram: &'a mut Diagram<'diag>,
+    libraries: MLLibraries,
+    format: Option<MarkupFormat>,
 }
 
 //ip DiagramML
@@ -53,57 +164,126 @@ impl<'a, 'diag> DiagramML<'a, 'diag> {
     ///
     /// The diagram is borrowed mutably, and is obviously then held
     /// until the reader has completed reading the file.
-    ///
-    /// It is possible that the reader will support including other
-    /// files within a file being read; this will require the reader
-    /// to invoke a new reader with the new file.
     pub fn new(d: &'a mut Diagram<'diag>) -> Self {
-        Self { diagram: d }
+        Self {
+            diagram: d,
+            libraries: MLLibraries::default(),
+            format: None,
+        }
+    }
+
+    //mp set_format
+    /// Set the markup format of the files read, rather than HML
+    /// or that given by the extension of their path
+    pub fn set_format(&mut self, format: MarkupFormat) {
+        self.format = Some(format);
+    }
+
+    //mp add_search_path
+    /// Add a directory to search for the library files named by
+    /// 'include' and 'import' elements
+    pub fn add_search_path<P: AsRef<Path>>(&mut self, path: P) {
+        self.libraries.add_search_path(path);
     }
 
     //mp read_file
-    /// Read a file as HML (currently), using its contents to build
-    /// the `Diagram` that this reader is constructing.
+    /// Read a file as HML (or in the format set), using its contents
+    /// to build the `Diagram` that this reader is constructing.
+    ///
+    /// Libraries included by the file are found in the current
+    /// directory or the search path.
     pub fn read_file<F: std::io::Read>(
         &mut self,
-        mut f: F,
+        f: F,
+        is_library: bool,
+    ) -> Result<(), Vec<String>> {
+        let format = self.format.unwrap_or(MarkupFormat::Hml);
+        self.read_file_format(f, format, is_library)
+    }
+
+    //mi read_file_format
+    fn read_file_format<F: std::io::Read>(
+        &mut self,
+        f: F,
+        format: MarkupFormat,
         is_library: bool,
     ) -> Result<(), Vec<String>> {
-        let mut namespace = Namespace::new(true);
-        let mut contents = String::new();
-        let mut reader = {
-            match StringReader::of_file(&mut f, &mut contents) {
-                Err(e) => {
-                    let mut r = Vec::new();
-                    r.push(format!("{}", e));
-                    return Err(r);
-                }
-                Ok(x) => x,
-            }
-        };
         let (descriptor, contents, stylesheet) = self.diagram.borrow_contents_descriptor();
-        let mut ml_reader = MLReader::new(contents, stylesheet, &mut namespace, &mut reader);
-        match ml_reader.read_file(descriptor, is_library) {
-            Err(mut err_list) => {
-                let mut r = Vec::new();
-                for e in err_list.take() {
+        read_markup(
+            descriptor,
+            contents,
+            stylesheet,
+            &mut self.libraries,
+            "",
+            f,
+            format,
+            is_library,
+        )
+    }
+
+    //mp read_path
+    /// Read a file by its path, as for `read_file`; libraries
+    /// included by the file are found relative to its directory
+    /// first, and importing it again has no effect
+    ///
+    /// Unless a format has been set, a file with a '.xml' extension
+    /// is read as XML, and any other as HML.
+    pub fn read_path<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        is_library: bool,
+    ) -> Result<(), Vec<String>> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .map_err(|e| vec![format!("Failed to open '{}': {}", path.display(), e)])?;
+        let format = self
+            .format
+            .or_else(|| MarkupFormat::of_path(path))
+            .unwrap_or(MarkupFormat::Hml);
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.libraries.push_file(&path);
+        let result = self.read_file_format(file, format, is_library);
+        self.libraries.pop_file();
+        result
+    }
+
+    //mp read_stylesheet
+    /// Read a stylesheet in the CSS-like '.dss' text format, adding
+    /// its rules to the stylesheet of the `Diagram` that this reader
+    /// is constructing
+    pub fn read_stylesheet<F: std::io::Read>(&mut self, mut f: F) -> Result<(), Vec<String>> {
+        let mut text = String::new();
+        if let Err(e) = f.read_to_string(&mut text) {
+            return Err(vec![format!("{}", e)]);
+        }
+        let (_, _, stylesheet) = self.diagram.borrow_contents_descriptor();
+        stylesheet.read_dss(&text).map_err(|errors| {
+            errors
+                .iter()
+                .map(|e| {
                     let mut s = String::new();
                     use std::fmt::Write;
-                    if let Some(span) = e.borrow_span() {
-                        reader
-                            .fmt_context(&mut s, span.start(), span.end())
-                            .unwrap();
-                        e.write_without_span(&mut s).unwrap();
-                        write!(&mut s, " at {}", span.start()).unwrap();
-                    } else {
-                        write!(&mut s, "{}", e).unwrap();
-                    }
-                    r.push(s);
-                }
-                Err(r)
-            }
-            _ => Ok(()),
-        }
+                    e.fmt_context(&mut s, &text).unwrap();
+                    write!(&mut s, "{}", e).unwrap();
+                    s
+                })
+                .collect()
+        })
+    }
+
+    //mp write_file
+    /// Write the contents and stylesheet of the `Diagram` as
+    /// canonical HML, that reads back as the same diagram
+    ///
+    /// Included libraries are written as part of the file, and the
+    /// values of variables and functions are evaluated.
+    pub fn write_file<F: std::io::Write>(&self, mut f: F) -> std::io::Result<()> {
+        let (contents, stylesheet) = self.diagram.borrow_contents_stylesheet();
+        let mut text = String::new();
+        MLWriter::new(contents, stylesheet)
+            .write(&mut text)
+            .map_err(std::io::Error::other)?;
+        f.write_all(text.as_bytes())
     }
 
     //zz All done
@@ -112,7 +292,7 @@ impl<'a, 'diag> DiagramML<'a, 'diag> {
 //a Test
 #[cfg(test)]
 mod tests {
-    use crate::{Diagram, DiagramDescriptor, DiagramML};
+    use crate::{Diagram, DiagramDescriptor, DiagramML, MarkupFormat};
     #[test]
     fn test_why() {
         let style_set = DiagramDescriptor::create_style_set();
@@ -123,4 +303,226 @@ mod tests {
         let (_, contents, _) = diagram.borrow_contents_descriptor();
         assert_eq!(0, contents.definitions.len());
     }
+    #[test]
+    fn test_xml() {
+        let style_set = DiagramDescriptor::create_style_set();
+        let diagram_descriptor = DiagramDescriptor::new(&style_set);
+        let mut diagram = Diagram::new(&diagram_descriptor);
+        let mut dml = DiagramML::new(&mut diagram);
+        dml.set_format(MarkupFormat::Xml);
+        dml.read_file(
+            r#"<?xml version="1.0"?>
+<diagram>
+  <!-- a comment -->
+  <defs><rect id="a" fill-color="red"/></defs>
+  <layout><rect/><text>Hello &amp; goodbye</text></layout>
+</diagram>"#
+                .as_bytes(),
+            false,
+        )
+        .unwrap();
+        let errors = dml
+            .read_file("<diagram>\n  <rect></group>\n</diagram>".as_bytes(), false)
+            .unwrap_err();
+        assert!(
+            errors[0].contains("    2:   <rect></group>"),
+            "{}",
+            errors[0]
+        );
+        assert!(errors[0].ends_with("at line 2 column 16"), "{}", errors[0]);
+        let (_, contents, _) = diagram.borrow_contents_descriptor();
+        assert_eq!(1, contents.definitions.len());
+        assert!(contents.definitions[0].has_id("a"));
+        assert_eq!(Some(MarkupFormat::Xml), MarkupFormat::of_path("a/b.xml"));
+        assert_eq!(None, MarkupFormat::of_path("a/b.dml"));
+    }
+    #[test]
+    fn test_element_errors() {
+        let style_set = DiagramDescriptor::create_style_set();
+        let diagram_descriptor = DiagramDescriptor::new(&style_set);
+        let mut diagram = Diagram::new(&diagram_descriptor);
+        let mut dml = DiagramML::new(&mut diagram);
+        dml.read_file(
+            "#diagram\n##use id=u1 ref=missing\n##rect\n##use id=u2 ref=absent\n".as_bytes(),
+            false,
+        )
+        .unwrap();
+        // Both unknown references are reported, each with the
+        // markup of its element
+        let errors = diagram.uniquify().unwrap_err();
+        let errors: Vec<String> = errors
+            .iter()
+            .map(|e| {
+                let mut s = String::new();
+                e.write_with_context(&mut s).unwrap();
+                s
+            })
+            .collect();
+        assert_eq!(errors.len(), 2);
+        assert!(
+            errors[0].contains("   2|  ##use id=u1 ref=missing"),
+            "{}",
+            errors[0]
+        );
+        assert!(
+            errors[0].ends_with("Unknown id reference 'missing' at line 2 character 1"),
+            "{}",
+            errors[0]
+        );
+        assert!(
+            errors[1].contains("   4|  ##use id=u2 ref=absent"),
+            "{}",
+            errors[1]
+        );
+        assert!(
+            errors[1].ends_with("at line 4 character 1"),
+            "{}",
+            errors[1]
+        );
+    }
+    #[test]
+    fn test_rule_styles() {
+        let style_set = DiagramDescriptor::create_style_set();
+        let diagram_descriptor = DiagramDescriptor::new(&style_set);
+        let mut diagram = Diagram::new(&diagram_descriptor);
+        let mut dml = DiagramML::new(&mut diagram);
+        // Rules may use styles defined after them, even in a library
+        // read later
+        dml.read_file(
+            "#diagram\n##rule id=a style=early\n##rule id=b style=late\n##rule id=c style=missing\n##style id=early stroke-width=1\n##rect id=a\n##rect id=b\n##rect id=c\n".as_bytes(),
+            false,
+        )
+        .unwrap();
+        dml.read_file("#library ##style id=late stroke-width=2".as_bytes(), true)
+            .unwrap();
+        assert!(diagram.uniquify().is_ok());
+        let errors = diagram.apply_stylesheet().unwrap_err();
+        let errors: Vec<String> = errors
+            .iter()
+            .map(|e| {
+                let mut s = String::new();
+                e.write_with_context(&mut s).unwrap();
+                s
+            })
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].contains("   4|  ##rule id=c style=missing"),
+            "{}",
+            errors[0]
+        );
+        assert!(
+            errors[0].ends_with("Unknown style id 'missing' at line 4 character 1"),
+            "{}",
+            errors[0]
+        );
+        let (contents, _) = diagram.borrow_contents_stylesheet();
+        let widths: Vec<_> = contents
+            .root_layout
+            .as_ref()
+            .and_then(|r| r.content.borrow_group())
+            .unwrap()
+            .content
+            .iter()
+            .map(|e| e.header.get_style_of_name_float("stroke-width", None))
+            .collect();
+        assert_eq!(widths, vec![Some(1.), Some(2.), None]);
+    }
+    #[test]
+    fn test_read_stylesheet() {
+        let style_set = DiagramDescriptor::create_style_set();
+        let diagram_descriptor = DiagramDescriptor::new(&style_set);
+        let mut diagram = Diagram::new(&diagram_descriptor);
+        let mut dml = DiagramML::new(&mut diagram);
+        dml.read_stylesheet("layout.pipeline > text.reg { fill-color: lightblue }".as_bytes())
+            .unwrap();
+        let errors = dml
+            .read_stylesheet("rect {\n  fill-colour: red;\n}".as_bytes())
+            .unwrap_err();
+        assert_eq!(1, errors.len());
+        assert!(errors[0].ends_with("at line 2 column 3"), "{}", errors[0]);
+    }
+    #[test]
+    fn test_variables() {
+        let style_set = DiagramDescriptor::create_style_set();
+        let diagram_descriptor = DiagramDescriptor::new(&style_set);
+        let mut diagram = Diagram::new(&diagram_descriptor);
+        let mut dml = DiagramML::new(&mut diagram);
+        dml.read_file(
+            "#library ##var unit=4 accent=#3366cc ##var pale='lighten($accent, 20%)'".as_bytes(),
+            true,
+        )
+        .unwrap();
+        dml.read_file(
+            "#diagram ##rect width='$unit * 3' fill-color=$pale".as_bytes(),
+            false,
+        )
+        .unwrap();
+        assert!(dml
+            .read_file("#diagram ##rect width=$size".as_bytes(), false)
+            .is_err());
+        assert!(dml
+            .read_file("#diagram ##rect id=$size".as_bytes(), false)
+            .is_err());
+        let (_, _, stylesheet) = diagram.borrow_contents_descriptor();
+        assert_eq!(stylesheet.variables().get("pale"), Some("#85a3e1"));
+    }
+    #[test]
+    fn test_library_order() {
+        let style_set = DiagramDescriptor::create_style_set();
+        let diagram_descriptor = DiagramDescriptor::new(&style_set);
+        let mut diagram = Diagram::new(&diagram_descriptor);
+        let mut dml = DiagramML::new(&mut diagram);
+        dml.read_file("#library ##defs ###rect id=z".as_bytes(), true)
+            .unwrap();
+        dml.read_file(
+            "#diagram ##defs ###circle id=x ###circle id=y".as_bytes(),
+            false,
+        )
+        .unwrap();
+        // The diagram's definitions are kept before the library's, as
+        // if it had been read first
+        let (_, contents, _) = diagram.borrow_contents_descriptor();
+        let ids: Vec<_> = contents.definitions.iter().map(|e| e.borrow_id()).collect();
+        assert_eq!(ids, vec!["x", "y", "z"]);
+    }
+    #[test]
+    fn test_include() {
+        let dir = std::env::temp_dir().join(format!("diagram_include_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        let write = |name: &str, text: &str| std::fs::write(dir.join(name), text).unwrap();
+        write("lib/colors.dml", "#library ##var fill=lightblue");
+        write(
+            "lib/shapes.dml",
+            "#library ##import file=colors.dml ##defs ###rect id=box fill-color=$fill ###group id=pair ####use ref=box ####use ref=box",
+        );
+        write(
+            "main.dml",
+            "#diagram ##import file=shapes.dml prefix=s ##import file=colors.dml ##use ref=s.pair",
+        );
+        write("cycle.dml", "#library ##include file=cycle2.dml");
+        write("cycle2.dml", "#library ##include file=cycle.dml");
+
+        let style_set = DiagramDescriptor::create_style_set();
+        let diagram_descriptor = DiagramDescriptor::new(&style_set);
+        let mut diagram = Diagram::new(&diagram_descriptor);
+        let mut dml = DiagramML::new(&mut diagram);
+        assert!(dml.read_path(dir.join("main.dml"), false).is_err());
+        dml.add_search_path(dir.join("lib"));
+        dml.read_path(dir.join("main.dml"), false).unwrap();
+        let errors = dml.read_path(dir.join("cycle.dml"), true).unwrap_err();
+        assert!(
+            errors.iter().any(|e| e.contains("include cycle")),
+            "{:?}",
+            errors
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let (_, contents, stylesheet) = diagram.borrow_contents_descriptor();
+        assert_eq!(stylesheet.variables().get("fill"), Some("lightblue"));
+        assert_eq!(2, contents.definitions.len());
+        assert!(contents.definitions[0].has_id("s.box"));
+        assert!(contents.definitions[1].has_id("s.pair"));
+        assert!(diagram.uniquify().is_ok());
+    }
 }
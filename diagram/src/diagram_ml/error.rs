@@ -43,6 +43,8 @@ where
     BadElement(HmlSpan<P>, String),
     BadMLEvent(HmlSpan<P>, String),
     BadValue(HmlSpan<P>, String),
+    Library(HmlSpan<P>, String),
+    Included(String),
     ParseError(HmlReaderError<P, E>),
     IOError(std::io::Error),
 }
@@ -107,6 +109,17 @@ where
         Self::BadValue(*span, format!("{}: '{}'", reason, value))
     }
 
+    //fi library
+    pub(crate) fn library(span: &HmlSpan<P>, reason: String) -> Self {
+        Self::Library(*span, reason)
+    }
+
+    //fi included
+    /// An error in a library file, already formatted with its context
+    pub(crate) fn included(error: String) -> Self {
+        Self::Included(error)
+    }
+
     //fi bad_attribute_name
     // fn bad_attribute_name(fp:&HmlFilePosition, name:&str) -> Self {
     // Self::BadAttributeName(fp.clone(), name.to_string())
@@ -166,6 +179,8 @@ where
             Self::BadElement(_span, s) => write!(f, "Element error '{}'", s),
             Self::BadMLEvent(_span, s) => write!(f, "Bad XML event {}", s),
             Self::BadValue(_span, s) => write!(f, "Bad value '{}'", s),
+            Self::Library(_span, s) => write!(f, "Library {}", s),
+            Self::Included(s) => write!(f, "{}", s),
             Self::ParseError(e) => e.write_without_span(f),
             Self::IOError(e) => write!(f, "IO error '{}'", e),
         }
@@ -180,6 +195,7 @@ where
             Self::BadElement(span, _) => Some(span),
             Self::BadMLEvent(span, _) => Some(span),
             Self::BadValue(span, _) => Some(span),
+            Self::Library(span, _) => Some(span),
             Self::ParseError(e) => e.borrow_span(),
             _ => None,
         }
@@ -0,0 +1,113 @@
+/*a Copyright
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+
+@file    libraries.rs
+@brief   Search paths and state for libraries included in markup
+ */
+
+//a Imports
+use std::path::{Path, PathBuf};
+
+//a MLLibraries
+//tp MLLibraries
+/// The directories to search for library files named by an
+/// 'include' or 'import' element, and the files that are being (or
+/// have been) read
+///
+/// A library file name is resolved relative to the directory of the
+/// file that names it (or the current directory, if that was not read
+/// from a file), and then relative to each of the search directories
+/// in order.
+#[derive(Debug, Default, Clone)]
+pub struct MLLibraries {
+    /// Directories to search for library files
+    search_paths: Vec<PathBuf>,
+    /// Stack of the (canonical) paths of the files being read
+    reading: Vec<PathBuf>,
+    /// The (canonical) paths of files that have been read
+    read: Vec<PathBuf>,
+}
+
+//ip MLLibraries
+impl MLLibraries {
+    //mp add_search_path
+    /// Add a directory to the end of the search path
+    pub fn add_search_path<P: AsRef<Path>>(&mut self, path: P) {
+        self.search_paths.push(path.as_ref().to_path_buf());
+    }
+
+    //mp resolve
+    /// Resolve a library file name to the canonical path of an
+    /// existing file
+    pub fn resolve(&self, name: &str) -> Option<PathBuf> {
+        let name = Path::new(name);
+        let current = self
+            .reading
+            .last()
+            .and_then(|p| p.parent())
+            .map(|p| p.join(name))
+            .unwrap_or_else(|| name.to_path_buf());
+        std::iter::once(current)
+            .chain(self.search_paths.iter().map(|d| d.join(name)))
+            .filter(|p| p.is_file())
+            .find_map(|p| p.canonicalize().ok())
+    }
+
+    //mp is_reading
+    /// Return true if a file is currently being read, and so
+    /// including it again would be a cycle
+    pub fn is_reading(&self, path: &Path) -> bool {
+        self.reading.iter().any(|p| p == path)
+    }
+
+    //mp has_read
+    /// Return true if a file has been read (or is being read)
+    pub fn has_read(&self, path: &Path) -> bool {
+        self.read.iter().any(|p| p == path)
+    }
+
+    //mp cycle
+    /// Describe the cycle of files that including a path would cause
+    pub fn cycle(&self, path: &Path) -> String {
+        let start = self.reading.iter().position(|p| p == path).unwrap_or(0);
+        let mut cycle = String::new();
+        for p in self.reading[start..]
+            .iter()
+            .chain(std::iter::once(&path.to_path_buf()))
+        {
+            if !cycle.is_empty() {
+                cycle.push_str(" -> ");
+            }
+            cycle.push_str(&p.display().to_string());
+        }
+        cycle
+    }
+
+    //mp push_file
+    /// Record that a file (given by its canonical path) is being read
+    pub fn push_file(&mut self, path: &Path) {
+        self.reading.push(path.to_path_buf());
+        if !self.has_read(path) {
+            self.read.push(path.to_path_buf());
+        }
+    }
+
+    //mp pop_file
+    /// Record that the most recently pushed file has been read
+    pub fn pop_file(&mut self) {
+        self.reading.pop();
+    }
+
+    //zz All done
+}
@@ -0,0 +1,117 @@
+/*a Copyright
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+
+@file    markup.rs
+@brief   Markup formats and the sources of their events
+ */
+
+//a Imports
+use hml_rs::markup::Event as HmlEvent;
+use hml_rs::names::NamespaceStack;
+use hml_rs::reader::Reader as HmlReader;
+use hml_rs::reader::Result as HmlResult;
+use hml_rs::reader::Span as HmlSpan;
+use std::path::Path;
+
+//a MarkupFormat
+//tp MarkupFormat
+/// The markup languages that a diagram may be read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkupFormat {
+    /// Human markup language, with '#' introducing elements
+    Hml,
+    /// XML
+    Xml,
+}
+
+//ip MarkupFormat
+impl MarkupFormat {
+    //fp of_path
+    /// Get the format of a file from its extension, if it is a
+    /// known one ('.xml' or '.hml')
+    pub fn of_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("xml") => Some(Self::Xml),
+            Some("hml") => Some(Self::Hml),
+            _ => None,
+        }
+    }
+}
+
+//ip FromStr for MarkupFormat
+impl std::str::FromStr for MarkupFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "hml" => Ok(Self::Hml),
+            "xml" => Ok(Self::Xml),
+            _ => Err(format!(
+                "Unknown markup format '{}', expected 'hml' or 'xml'",
+                s
+            )),
+        }
+    }
+}
+
+//a MLMarkup
+//tt MLMarkup
+/// A source of markup events for an `MLReader`, parsing the
+/// contents of a markup reader
+pub trait MLMarkup<R: HmlReader> {
+    /// Get the next markup event, using the namespace stack to
+    /// resolve the names of elements and attributes
+    fn next_event(
+        &mut self,
+        namespace_stack: &mut NamespaceStack,
+        reader: &mut R,
+    ) -> HmlResult<HmlEvent<HmlSpan<R::Position>>, R::Position, R::Error>;
+}
+
+//tp HmlMarkup
+/// The events of an HML document, from its lexer and parser
+pub struct HmlMarkup<R: HmlReader> {
+    lexer: hml_rs::hml_reader::Lexer<R>,
+    parser: hml_rs::hml_reader::Parser<R>,
+}
+
+//ip HmlMarkup
+impl<R: HmlReader> HmlMarkup<R> {
+    //fp new
+    pub fn new() -> Self {
+        Self {
+            lexer: hml_rs::hml_reader::Lexer::new(),
+            parser: hml_rs::hml_reader::Parser::new(),
+        }
+    }
+}
+
+//ip Default for HmlMarkup
+impl<R: HmlReader> Default for HmlMarkup<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//ip MLMarkup for HmlMarkup
+impl<R: HmlReader> MLMarkup<R> for HmlMarkup<R> {
+    fn next_event(
+        &mut self,
+        namespace_stack: &mut NamespaceStack,
+        reader: &mut R,
+    ) -> HmlResult<HmlEvent<HmlSpan<R::Position>>, R::Position, R::Error> {
+        let lexer = &mut self.lexer;
+        self.parser
+            .next_event(namespace_stack, || lexer.next_token(reader))
+    }
+}
@@ -16,7 +16,7 @@ limitations under the License.
  */
 
 //a Imports
-use crate::diagram::{Element, Group, Path, Shape, Text, Use};
+use crate::diagram::{Connector, Element, Group, Label, Path, Shape, Text, Use};
 // use crate::constants::attributes as at;
 use super::KnownName;
 use super::{MLError, MLReader, MLResult};
@@ -60,11 +60,29 @@ where
         span: &HmlSpan<P>,
         tag: HmlTag,
     ) -> MLResult<Element<'a>, P, E> {
-        let attrs = tag.attributes.take();
-        let mut attr_values = attrs.iter().map(|a| reader.map_attr(a));
+        // The references of a use within a library are to the
+        // (prefixed) definitions of the library
+        let attrs: Vec<(String, String)> = tag
+            .attributes
+            .take()
+            .iter()
+            .map(|a| {
+                let (name, value) = reader.map_attr(a);
+                match reader.known_id(&a.name) {
+                    Some(KnownName::Ref) => (name, reader.prefixed_id(value)),
+                    _ => (name, value.to_string()),
+                }
+            })
+            .collect();
+        let mut attr_values = attrs.iter().map(|(n, v)| (n.clone(), v.as_str()));
         let mut use_ref = MLError::value_result(
             span,
-            Element::new(descriptor, el::Typ::Use, &mut attr_values),
+            Element::new(
+                descriptor,
+                reader.stylesheet.variables(),
+                el::Typ::Use,
+                &mut attr_values,
+            ),
         )?;
         loop {
             let e = reader.next_event()?;
@@ -118,8 +136,15 @@ where
 
         let attrs = tag.attributes.take();
         let mut attr_values = attrs.iter().map(|a| reader.map_attr(a));
-        let mut group =
-            MLError::value_result(span, Element::new(descriptor, el_type, &mut attr_values))?;
+        let mut group = MLError::value_result(
+            span,
+            Element::new(
+                descriptor,
+                reader.stylesheet.variables(),
+                el_type,
+                &mut attr_values,
+            ),
+        )?;
         loop {
             let e = reader.next_event()?;
             use HmlEventType::*;
@@ -166,7 +191,12 @@ where
         let mut attr_values = attrs.iter().map(|a| reader.map_attr(a));
         let path = MLError::value_result(
             span,
-            Element::new(descriptor, el::Typ::Path, &mut attr_values),
+            Element::new(
+                descriptor,
+                reader.stylesheet.variables(),
+                el::Typ::Path,
+                &mut attr_values,
+            ),
         )?;
         loop {
             let e = reader.next_event()?;
@@ -190,6 +220,53 @@ where
     }
 }
 
+//ii MLEvent for Connector
+impl<'a, P, E, R> MLReadElement<'a, P, E, R> for Connector
+where
+    P: HmlPosition,
+    E: HmlError<Position = P>,
+    R: HmlReader<Position = P, Error = E>,
+{
+    //fp ml_read
+    fn ml_read(
+        reader: &mut MLReader<P, E, R>,
+        descriptor: &'a DiagramDescriptor,
+        span: &HmlSpan<P>,
+        tag: HmlTag,
+    ) -> MLResult<Element<'a>, P, E> {
+        let attrs = tag.attributes.take();
+        let mut attr_values = attrs.iter().map(|a| reader.map_attr(a));
+        let connector = MLError::value_result(
+            span,
+            Element::new(
+                descriptor,
+                reader.stylesheet.variables(),
+                el::Typ::Connector,
+                &mut attr_values,
+            ),
+        )?;
+        loop {
+            let e = reader.next_event()?;
+            use HmlEventType::*;
+            match e.get_type() {
+                Comment => (), // continue
+                EndElement => {
+                    return Ok(connector);
+                }
+                StartElement => {
+                    // content of connector is not allowed
+                    let span = *e.borrow_span();
+                    let tag = e.as_start_element().unwrap();
+                    reader.consume_bad_element(&span, &tag)?;
+                }
+                _ => {
+                    return Err(MLError::bad_ml_event(&e));
+                }
+            }
+        }
+    }
+}
+
 //ii MLEvent for Shape
 impl<'a, P, E, R> MLReadElement<'a, P, E, R> for Shape
 where
@@ -214,8 +291,15 @@ where
 
         let attrs = tag.attributes.take();
         let mut attr_values = attrs.iter().map(|a| reader.map_attr(a));
-        let shape =
-            MLError::value_result(span, Element::new(descriptor, el_type, &mut attr_values))?;
+        let shape = MLError::value_result(
+            span,
+            Element::new(
+                descriptor,
+                reader.stylesheet.variables(),
+                el_type,
+                &mut attr_values,
+            ),
+        )?;
         loop {
             let e = reader.next_event()?;
             use HmlEventType::*;
@@ -256,7 +340,12 @@ where
         let mut attr_values = attrs.iter().map(|a| reader.map_attr(a));
         let mut text = MLError::value_result(
             span,
-            Element::new(descriptor, el::Typ::Text, &mut attr_values),
+            Element::new(
+                descriptor,
+                reader.stylesheet.variables(),
+                el::Typ::Text,
+                &mut attr_values,
+            ),
         )?;
         loop {
             let e = reader.next_event()?;
@@ -286,6 +375,59 @@ where
     }
 }
 
+//ii MLEvent for Label
+impl<'a, P, E, R> MLReadElement<'a, P, E, R> for Label
+where
+    P: HmlPosition,
+    E: HmlError<Position = P>,
+    R: HmlReader<Position = P, Error = E>,
+{
+    //fp ml_read
+    fn ml_read(
+        reader: &mut MLReader<P, E, R>,
+        descriptor: &'a DiagramDescriptor,
+        span: &HmlSpan<P>,
+        tag: HmlTag,
+    ) -> MLResult<Element<'a>, P, E> {
+        let attrs = tag.attributes.take();
+        let mut attr_values = attrs.iter().map(|a| reader.map_attr(a));
+        let mut label = MLError::value_result(
+            span,
+            Element::new(
+                descriptor,
+                reader.stylesheet.variables(),
+                el::Typ::Label,
+                &mut attr_values,
+            ),
+        )?;
+        loop {
+            let e = reader.next_event()?;
+            use HmlEventType::*;
+            match e.get_type() {
+                Comment => (), // continue
+                EndElement => {
+                    return Ok(label);
+                }
+                Content => {
+                    MLError::element_result(
+                        e.borrow_span(),
+                        label.add_string(e.as_content().unwrap().1),
+                    )?;
+                }
+                StartElement => {
+                    // content of label is not allowed
+                    let span = *e.borrow_span();
+                    let tag = e.as_start_element().unwrap();
+                    reader.consume_bad_element(&span, &tag)?;
+                }
+                _ => {
+                    return Err(MLError::bad_ml_event(&e));
+                }
+            }
+        }
+    }
+}
+
 //ii MLReadElement for Element
 impl<'a, P, E, R> MLReadElement<'a, P, E, R> for Element<'a>
 where
@@ -299,12 +441,14 @@ where
         span: &HmlSpan<P>,
         tag: HmlTag,
     ) -> MLResult<Element<'a>, P, E> {
-        match reader.known_id(&tag.name) {
+        let mut element = match reader.known_id(&tag.name) {
             Some(KnownName::Use) => Use::ml_read(reader, descriptor, span, tag),
             Some(KnownName::Group) => Group::ml_read(reader, descriptor, span, tag),
             Some(KnownName::Marker) => Group::ml_read(reader, descriptor, span, tag),
             Some(KnownName::Layout) => Group::ml_read(reader, descriptor, span, tag),
             Some(KnownName::Path) => Path::ml_read(reader, descriptor, span, tag),
+            Some(KnownName::Connector) => Connector::ml_read(reader, descriptor, span, tag),
+            Some(KnownName::Label) => Label::ml_read(reader, descriptor, span, tag),
             Some(KnownName::Text) => Text::ml_read(reader, descriptor, span, tag),
             Some(KnownName::Rect) => Shape::ml_read(reader, descriptor, span, tag),
             Some(KnownName::Circle) => Shape::ml_read(reader, descriptor, span, tag),
@@ -318,12 +462,16 @@ where
                     KnownName::Marker,
                     KnownName::Layout,
                     KnownName::Path,
+                    KnownName::Connector,
+                    KnownName::Label,
                     KnownName::Text,
                     KnownName::Rect,
                     KnownName::Circle,
                     KnownName::Polygon,
                 ],
             )),
-        }
+        }?;
+        element.header.span = Some(reader.element_span(span));
+        Ok(element)
     }
 }
@@ -17,10 +17,10 @@ limitations under the License.
  */
 
 //a Imports
-use super::{KnownName, NameIds};
-use super::{MLError, MLErrorList, MLReadElement, MLResult};
+use super::{read_markup, KnownName, NameIds};
+use super::{MLError, MLErrorList, MLLibraries, MLMarkup, MLReadElement, MLResult, MarkupFormat};
 use crate::constants::elements as el;
-use crate::diagram::Element;
+use crate::diagram::{Element, ElementSpan};
 use crate::{DiagramContents, DiagramDescriptor};
 use crate::{StyleRule, StyleSheet};
 use hml_rs::markup::Event as HmlEvent;
@@ -45,11 +45,14 @@ where
 {
     pub contents: &'reader mut DiagramContents<'diag>,
     pub stylesheet: &'reader mut StyleSheet<'diag>,
+    libraries: &'reader mut MLLibraries,
+    id_prefix: String,
     reader: &'reader mut R,
     name_ids: NameIds,
     namespace_stack: NamespaceStack<'reader>,
-    lexer: hml_rs::hml_reader::Lexer<R>,
-    parser: hml_rs::hml_reader::Parser<R>,
+    markup: Box<dyn MLMarkup<R> + 'reader>,
+    format: MarkupFormat,
+    is_library: bool,
     pub errors: MLErrorList<P, R::Error>,
 }
 
@@ -61,25 +64,34 @@ where
     R: HmlReader<Position = P, Error = E>,
 {
     //fp new
+    /// Create a new reader of the markup events of a file in the
+    /// given format; libraries that it includes are read in the
+    /// format given by their file extension, or else in this format
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         contents: &'reader mut DiagramContents<'diag>,
         stylesheet: &'reader mut StyleSheet<'diag>,
+        libraries: &'reader mut MLLibraries,
+        id_prefix: &str,
         namespace: &'reader mut Namespace,
         reader: &'reader mut R,
+        markup: Box<dyn MLMarkup<R> + 'reader>,
+        format: MarkupFormat,
     ) -> Self {
         let mut namespace_stack = NamespaceStack::new(namespace);
         let name_ids = NameIds::create(&mut namespace_stack);
-        let lexer = hml_rs::hml_reader::Lexer::new();
-        let parser = hml_rs::hml_reader::Parser::new();
         Self {
             // descriptor,
             contents,
             stylesheet,
+            libraries,
+            id_prefix: id_prefix.to_string(),
             reader,
             name_ids,
             namespace_stack,
-            lexer,
-            parser,
+            markup,
+            format,
+            is_library: false,
             errors: MLErrorList::new(),
         }
     }
@@ -97,15 +109,35 @@ where
         )
     }
 
+    //mp prefixed_id
+    /// Get an id with the prefix of the library being read (if any)
+    pub fn prefixed_id(&self, id: &str) -> String {
+        if self.id_prefix.is_empty() {
+            id.to_string()
+        } else {
+            format!("{}.{}", self.id_prefix, id)
+        }
+    }
+
+    //mp element_span
+    /// Get the span of an element read from the markup, with the
+    /// excerpt of the markup for the context of errors found in the
+    /// element after it has been read
+    pub fn element_span(&self, span: &HmlSpan<P>) -> ElementSpan {
+        let mut context = String::new();
+        // The excerpt is only for reporting errors, so failing to
+        // write it is not an error
+        let _ = self
+            .reader
+            .fmt_context(&mut context, span.start(), span.end());
+        ElementSpan::new(context, span.start().to_string())
+    }
+
     //mp next_event
     pub fn next_event(&mut self) -> MLResult<HmlEvent<HmlSpan<P>>, P, E> {
-        let (parser, namespace_stack, lexer, reader) = (
-            &mut self.parser,
-            &mut self.namespace_stack,
-            &mut self.lexer,
-            &mut self.reader,
-        );
-        let e = parser.next_event(namespace_stack, || lexer.next_token(reader))?;
+        let e = self
+            .markup
+            .next_event(&mut self.namespace_stack, self.reader)?;
         Ok(e)
     }
 
@@ -153,6 +185,17 @@ where
         self.consume_element()
     }
 
+    //mi add_library_id
+    /// Record the id of a definition or marker if it is being read
+    /// from a library
+    fn add_library_id(&mut self, element: &Element) {
+        if self.is_library && !element.borrow_id().is_empty() {
+            self.contents
+                .library_ids
+                .push(element.borrow_id().to_string());
+        }
+    }
+
     //mp read_definitions
     fn read_definitions(&mut self, descriptor: &'diag DiagramDescriptor) -> MLResult<(), P, E> {
         loop {
@@ -170,7 +213,8 @@ where
                         Some(KnownName::Marker) => {
                             match Element::ml_read(self, descriptor, &span, tag) {
                                 Ok(element) => {
-                                    self.contents.markers.push(element);
+                                    self.add_library_id(&element);
+                                    self.contents.add_marker(element, self.is_library);
                                 }
                                 e => {
                                     self.errors.update(e);
@@ -178,8 +222,13 @@ where
                             }
                         }
                         _ => match Element::ml_read(self, descriptor, &span, tag) {
-                            Ok(element) => {
-                                self.contents.definitions.push(element);
+                            Ok(mut element) => {
+                                if !self.id_prefix.is_empty() && !element.borrow_id().is_empty() {
+                                    let id = self.prefixed_id(element.borrow_id());
+                                    element.set_id(&id);
+                                }
+                                self.add_library_id(&element);
+                                self.contents.add_definition(element, self.is_library);
                             }
                             e => {
                                 self.errors.update(e);
@@ -203,30 +252,66 @@ where
         tag: HmlTag,
     ) -> MLResult<(), P, E> {
         let mut rule = StyleRule::new();
-        let mut action = None;
+        let mut style = None;
         let mut attrs = Vec::new();
         for attr in tag.attributes.borrow() {
             match self.known_id(&attr.name) {
                 Some(KnownName::Style) => {
-                    if let Some(a) = self.stylesheet.get_action_index(&attr.value) {
-                        action = Some(*a);
-                    } else {
-                        return Err(MLError::bad_value(
-                            span,
-                            "unknown style id in rule",
-                            &attr.value,
-                        ));
-                    }
+                    style = Some(attr.value.as_str());
                 }
                 Some(KnownName::Id) => {
                     rule = rule.has_id(&attr.value);
                 }
+                Some(KnownName::Type) => {
+                    rule = rule.has_type(&attr.value);
+                }
                 Some(KnownName::Class) => {
-                    rule = rule.has_class(&attr.value);
+                    for c in attr.value.split_whitespace() {
+                        rule = rule.has_class(c);
+                    }
                 }
-                Some(KnownName::Depth) => {
-                    // rule = rule.has_class(attr.value);
+                Some(KnownName::Has) => {
+                    for s in attr.value.split_whitespace() {
+                        rule = rule.has_style(s);
+                    }
                 }
+                Some(KnownName::Relation) => match attr.value.as_str() {
+                    "child" => {
+                        rule = rule.child(true);
+                    }
+                    "descendant" => {
+                        rule = rule.child(false);
+                    }
+                    _ => {
+                        return Err(MLError::bad_value(
+                            span,
+                            "rule relation must be 'child' or 'descendant'",
+                            &attr.value,
+                        ));
+                    }
+                },
+                Some(KnownName::Depth) => match attr.value.parse::<usize>() {
+                    // The diagram element is at a depth of 2 in the
+                    // tree of stylable nodes
+                    Ok(d) => {
+                        rule = rule.max_depth(d + 2);
+                    }
+                    Err(_) => {
+                        return Err(MLError::bad_value(span, "bad depth in rule", &attr.value));
+                    }
+                },
+                Some(KnownName::Priority) => match attr.value.parse::<isize>() {
+                    Ok(p) => {
+                        rule = rule.priority(p);
+                    }
+                    Err(_) => {
+                        return Err(MLError::bad_value(
+                            span,
+                            "bad priority in rule",
+                            &attr.value,
+                        ));
+                    }
+                },
                 Some(_) => {
                     attrs.push((
                         attr.name.to_string(&self.namespace_stack),
@@ -242,16 +327,32 @@ where
                 }
             }
         }
-        if !attrs.is_empty() {
-            assert!(action.is_none());
-            let mut attr_values = attrs.into_iter().map(|(n, v)| (n, v));
-            action = Some(MLError::value_result(
-                span,
-                self.stylesheet
-                    .add_action_from_name_values(&mut attr_values),
-            )?);
-        }
-        let rule_index = self.stylesheet.add_rule(parent, rule, action);
+        // The style of a rule is resolved when the stylesheet is
+        // linked, so it may be defined after the rule
+        let rule_index = if let Some(style) = style {
+            if !attrs.is_empty() {
+                return Err(MLError::bad_value(
+                    span,
+                    "rule with a style id may not also have styles",
+                    style,
+                ));
+            }
+            let rule_index = self.stylesheet.add_rule_of_style(parent, rule, style);
+            let rule_span = self.element_span(span);
+            self.contents.rule_spans.insert(rule_index, rule_span);
+            rule_index
+        } else {
+            let mut action = None;
+            if !attrs.is_empty() {
+                let mut attr_values = attrs.into_iter();
+                action = Some(MLError::value_result(
+                    span,
+                    self.stylesheet
+                        .add_action_from_name_values(&mut attr_values),
+                )?);
+            }
+            self.stylesheet.add_rule(parent, rule, action)
+        };
         loop {
             // should support an 'apply' subrule
             let e = self.next_event()?;
@@ -319,6 +420,140 @@ where
         }
     }
 
+    //mp read_variables
+    /// Read a 'var' element, each of whose attributes sets a variable
+    /// that may be used in the values of styles that follow it
+    fn read_variables(&mut self, span: &HmlSpan<P>, tag: HmlTag) -> MLResult<(), P, E> {
+        let attrs = tag.attributes.take();
+        for a in attrs.iter() {
+            let name = a.name.to_string(&self.namespace_stack);
+            MLError::value_result(span, self.stylesheet.set_variable(&name, &a.value))?;
+        }
+        loop {
+            let e = self.next_event()?;
+            use HmlEventType::*;
+            match e.get_type() {
+                Comment => (), // continue
+                EndElement => {
+                    return Ok(());
+                }
+                StartElement => {
+                    // content of var is not allowed
+                    let span = *e.borrow_span();
+                    let tag = e.as_start_element().unwrap();
+                    self.consume_bad_element(&span, &tag)?;
+                }
+                _ => {
+                    return Err(MLError::bad_ml_event(&e));
+                }
+            }
+        }
+    }
+
+    //mp read_include
+    /// Read an 'include' or 'import' element, and the library file
+    /// that it names
+    ///
+    /// A file that is already being read cannot be included, as that
+    /// would be a cycle; and an import of a file that has already
+    /// been read has no effect. The ids of the definitions in the
+    /// library are given the prefix of the element, after that of
+    /// the library being read.
+    fn read_include(
+        &mut self,
+        descriptor: &'diag DiagramDescriptor,
+        span: &HmlSpan<P>,
+        tag: HmlTag,
+        import: bool,
+    ) -> MLResult<(), P, E> {
+        let mut file = None;
+        let mut id_prefix = self.id_prefix.clone();
+        for attr in tag.attributes.borrow() {
+            match self.known_id(&attr.name) {
+                Some(KnownName::File) => {
+                    file = Some(attr.value.clone());
+                }
+                Some(KnownName::Prefix) => {
+                    id_prefix = self.prefixed_id(&attr.value);
+                }
+                _ => {
+                    self.errors.add(MLError::bad_attribute_name(
+                        &self.namespace_stack,
+                        span,
+                        attr,
+                    ));
+                }
+            }
+        }
+        loop {
+            let e = self.next_event()?;
+            use HmlEventType::*;
+            match e.get_type() {
+                Comment => (), // continue
+                EndElement => {
+                    break;
+                }
+                StartElement => {
+                    // content of include is not allowed
+                    let span = *e.borrow_span();
+                    let tag = e.as_start_element().unwrap();
+                    self.consume_bad_element(&span, &tag)?;
+                }
+                _ => {
+                    return Err(MLError::bad_ml_event(&e));
+                }
+            }
+        }
+        let file = match file {
+            Some(file) => file,
+            None => {
+                return Err(MLError::library(span, "requires a 'file' attribute".into()));
+            }
+        };
+        let path = match self.libraries.resolve(&file) {
+            Some(path) => path,
+            None => {
+                return Err(MLError::library(
+                    span,
+                    format!("'{}' not found in the search path", file),
+                ));
+            }
+        };
+        if self.libraries.is_reading(&path) {
+            return Err(MLError::library(
+                span,
+                format!("include cycle {}", self.libraries.cycle(&path)),
+            ));
+        }
+        if import && self.libraries.has_read(&path) {
+            return Ok(());
+        }
+        let f = std::fs::File::open(&path)
+            .map_err(|e| MLError::library(span, format!("'{}': {}", path.display(), e)))?;
+        self.libraries.push_file(&path);
+        let result = read_markup(
+            descriptor,
+            self.contents,
+            self.stylesheet,
+            self.libraries,
+            &id_prefix,
+            f,
+            MarkupFormat::of_path(&path).unwrap_or(self.format),
+            true,
+        );
+        self.libraries.pop_file();
+        if let Err(errors) = result {
+            self.errors.add(MLError::library(
+                span,
+                format!("'{}' has errors", path.display()),
+            ));
+            for e in errors {
+                self.errors.add(MLError::included(e));
+            }
+        }
+        Ok(())
+    }
+
     //mp read_library
     fn read_library(&mut self, descriptor: &'diag DiagramDescriptor) -> MLResult<(), P, E> {
         loop {
@@ -342,6 +577,18 @@ where
                             let e = self.read_rule(descriptor, None, &span, tag);
                             self.errors.update(e);
                         }
+                        Some(KnownName::Var) => {
+                            let e = self.read_variables(&span, tag);
+                            self.errors.update(e);
+                        }
+                        Some(KnownName::Include) => {
+                            let e = self.read_include(descriptor, &span, tag, false);
+                            self.errors.update(e);
+                        }
+                        Some(KnownName::Import) => {
+                            let e = self.read_include(descriptor, &span, tag, true);
+                            self.errors.update(e);
+                        }
                         Some(KnownName::Defs) => {
                             let e = self.read_definitions(descriptor);
                             self.errors.update(e);
@@ -352,7 +599,14 @@ where
                                 &span,
                                 &tag,
                                 &self.name_ids,
-                                &[KnownName::Style, KnownName::Rule, KnownName::Defs],
+                                &[
+                                    KnownName::Style,
+                                    KnownName::Rule,
+                                    KnownName::Var,
+                                    KnownName::Include,
+                                    KnownName::Import,
+                                    KnownName::Defs,
+                                ],
                             ));
                         }
                     }
@@ -392,6 +646,18 @@ where
                             let e = self.read_rule(descriptor, None, &span, tag);
                             self.errors.update(e);
                         }
+                        Some(KnownName::Var) => {
+                            let e = self.read_variables(&span, tag);
+                            self.errors.update(e);
+                        }
+                        Some(KnownName::Include) => {
+                            let e = self.read_include(descriptor, &span, tag, false);
+                            self.errors.update(e);
+                        }
+                        Some(KnownName::Import) => {
+                            let e = self.read_include(descriptor, &span, tag, true);
+                            self.errors.update(e);
+                        }
                         Some(KnownName::Defs) => {
                             let e = self.read_definitions(descriptor);
                             self.errors.update(e);
@@ -486,10 +752,16 @@ where
                             let mut attr_values = attrs.iter().map(|a| {
                                 (a.name.to_string(&self.namespace_stack), a.value.as_str())
                             });
-                            let layout = MLError::value_result(
+                            let mut layout = MLError::value_result(
                                 &span,
-                                Element::new(descriptor, el::Typ::Diagram, &mut attr_values),
+                                Element::new(
+                                    descriptor,
+                                    self.stylesheet.variables(),
+                                    el::Typ::Diagram,
+                                    &mut attr_values,
+                                ),
                             )?;
+                            layout.header.span = Some(self.element_span(&span));
                             self.read_diagram(descriptor, layout)?;
                             diagram_read = true;
                         }
@@ -517,6 +789,7 @@ where
         descriptor: &'diag DiagramDescriptor,
         is_library: bool,
     ) -> Result<(), MLErrorList<P, E>> {
+        self.is_library = is_library;
         match self.next_event() {
             Ok(e) => {
                 if e.is_start_document() {
@@ -0,0 +1,326 @@
+/*a Copyright
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+
+@file    ml_writer.rs
+@brief   Writer of diagram contents and stylesheet as canonical HML
+ */
+
+//a Imports
+use crate::diagram::Element;
+use crate::{DiagramContents, StyleSheet};
+use std::fmt::{Result, Write};
+
+//a Functions
+//fi quoted
+/// Get a value as an HML string; it is left bare if that is
+/// unambiguous, else it is quoted with a quote character it does not
+/// contain, else with enough hashes that it cannot be terminated early
+fn quoted(value: &str, may_be_bare: bool) -> String {
+    let bare = may_be_bare
+        && !value.is_empty()
+        && !value.starts_with(['"', '\'', '#'])
+        && !value.chars().any(|c| c.is_whitespace());
+    if bare {
+        value.to_string()
+    } else if !value.contains(['\n', '\r']) && !value.contains('"') {
+        format!("\"{}\"", value)
+    } else if !value.contains(['\n', '\r']) && !value.contains('\'') {
+        format!("'{}'", value)
+    } else {
+        let mut hashes = "#".to_string();
+        while value.contains(&format!("\"{}", hashes)) {
+            hashes.push('#');
+        }
+        format!("{}\"{}\"{}", hashes, value, hashes)
+    }
+}
+
+//fi canonical_attributes
+/// Get the attributes of an element in canonical order - 'id', then
+/// 'class', then the rest sorted by name - with a later value for
+/// a name replacing an earlier one (except for classes, which
+/// accumulate)
+fn canonical_attributes(attributes: &[(String, String)]) -> Vec<(&str, String)> {
+    let mut classes: Vec<&str> = Vec::new();
+    let mut others: Vec<(&str, String)> = Vec::new();
+    for (name, value) in attributes {
+        if name == "class" {
+            classes.extend(value.split_whitespace());
+        } else if let Some(nv) = others.iter_mut().find(|(n, _)| n == name) {
+            nv.1 = value.clone();
+        } else {
+            others.push((name, value.clone()));
+        }
+    }
+    others.sort_by(|a, b| {
+        let key = |n: &str| (n != "id", n.to_string());
+        key(a.0).cmp(&key(b.0))
+    });
+    if !classes.is_empty() {
+        let at = others
+            .iter()
+            .position(|(n, _)| *n != "id")
+            .unwrap_or(others.len());
+        others.insert(at, ("class", classes.join(" ")));
+    }
+    others
+}
+
+//a MLWriter
+//tp MLWriter
+/// A writer of the contents of a diagram and its stylesheet as
+/// canonical HML
+///
+/// The variables are written first (sorted by name), then the styles
+/// with ids and the rules (in the order they were added), then the
+/// definitions and markers, and then the contents of the diagram
+/// itself; the attributes of each element are written with 'id' and
+/// 'class' first and then the others sorted by name, with the
+/// values of variables and functions evaluated.
+///
+/// Reading the HML written produces contents and a stylesheet that
+/// write out identically.
+///
+/// If there is no diagram element (only libraries have been read)
+/// then a library is written.
+pub struct MLWriter<'a, 'diag> {
+    contents: &'a DiagramContents<'diag>,
+    stylesheet: &'a StyleSheet<'diag>,
+}
+
+//ip MLWriter
+impl<'a, 'diag> MLWriter<'a, 'diag> {
+    //fp new
+    /// Create a new writer of diagram contents and a stylesheet
+    pub fn new(contents: &'a DiagramContents<'diag>, stylesheet: &'a StyleSheet<'diag>) -> Self {
+        Self {
+            contents,
+            stylesheet,
+        }
+    }
+
+    //mp write
+    /// Write the contents and stylesheet as an HML document
+    pub fn write(&self, f: &mut dyn Write) -> Result {
+        match &self.contents.root_layout {
+            Some(root) => {
+                write!(f, "#diagram")?;
+                self.write_attributes(f, &root.header.attributes)?;
+                writeln!(f)?;
+            }
+            None => {
+                writeln!(f, "#library")?;
+            }
+        }
+        self.write_variables(f)?;
+        self.write_styles(f)?;
+        for rule in 0..self.stylesheet.num_rules() {
+            if self.stylesheet.is_toplevel_rule(rule) {
+                self.write_rule(f, rule, 2)?;
+            }
+        }
+        if !self.contents.definitions.is_empty() || !self.contents.markers.is_empty() {
+            writeln!(f, "##defs")?;
+            for element in &self.contents.definitions {
+                self.write_element(f, element, 3)?;
+            }
+            for element in &self.contents.markers {
+                self.write_element(f, element, 3)?;
+            }
+        }
+        if let Some(root) = &self.contents.root_layout {
+            if let Some(group) = root.content.borrow_group() {
+                for element in &group.content {
+                    self.write_element(f, element, 2)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    //mi write_attributes
+    fn write_attributes(&self, f: &mut dyn Write, attributes: &[(String, String)]) -> Result {
+        for (name, value) in canonical_attributes(attributes) {
+            write!(f, " {}={}", name, quoted(&value, true))?;
+        }
+        Ok(())
+    }
+
+    //mi write_variables
+    fn write_variables(&self, f: &mut dyn Write) -> Result {
+        let mut variables: Vec<_> = self.stylesheet.variables().iter().collect();
+        variables.sort();
+        for (name, value) in variables {
+            writeln!(f, "##var {}={}", name, quoted(value, true))?;
+        }
+        Ok(())
+    }
+
+    //mi write_styles
+    /// Write the actions of the stylesheet that have ids, in the
+    /// order they were added; other actions are written inline in
+    /// the rules that use them
+    fn write_styles(&self, f: &mut dyn Write) -> Result {
+        for action in 0..self.stylesheet.num_actions() {
+            if let Some(id) = self.stylesheet.id_of_action(action) {
+                write!(f, "##style id={}", quoted(id, true))?;
+                let source = self.stylesheet.borrow_action(action).borrow_source();
+                self.write_attributes(f, source)?;
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+
+    //mi write_rule
+    /// Write a rule and its child rules, as elements at a depth
+    fn write_rule(&self, f: &mut dyn Write, rule: usize, depth: usize) -> Result {
+        let (rule_fn, action) = self.stylesheet.borrow_rule(rule);
+        write!(f, "{}rule", "#".repeat(depth))?;
+        if let Some(id) = rule_fn.id_matches() {
+            write!(f, " id={}", quoted(id, true))?;
+        }
+        if let Some(node_type) = rule_fn.node_type() {
+            write!(f, " type={}", quoted(node_type, true))?;
+        }
+        if !rule_fn.classes().is_empty() {
+            write!(f, " class={}", quoted(&rule_fn.classes().join(" "), true))?;
+        }
+        if !rule_fn.has_styles().is_empty() {
+            write!(f, " has={}", quoted(&rule_fn.has_styles().join(" "), true))?;
+        }
+        if rule_fn.is_child() {
+            write!(f, " relation=child")?;
+        }
+        // The diagram element is at a depth of 2 in the tree of
+        // stylable nodes
+        if rule_fn.get_max_depth() >= 2 {
+            write!(f, " depth={}", rule_fn.get_max_depth() - 2)?;
+        }
+        if rule_fn.get_priority() != 0 {
+            write!(f, " priority={}", rule_fn.get_priority())?;
+        }
+        if let Some(style) = self.stylesheet.style_of_rule(rule) {
+            write!(f, " style={}", quoted(style, true))?;
+        } else if let Some(action) = action {
+            match self.stylesheet.id_of_action(action) {
+                Some(id) => {
+                    write!(f, " style={}", quoted(id, true))?;
+                }
+                None => {
+                    let source = self.stylesheet.borrow_action(action).borrow_source();
+                    self.write_attributes(f, source)?;
+                }
+            }
+        }
+        writeln!(f)?;
+        for child in self.stylesheet.iter_rule_children(rule) {
+            self.write_rule(f, child, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    //mi write_element
+    /// Write an element and its content, at a depth
+    fn write_element(&self, f: &mut dyn Write, element: &Element, depth: usize) -> Result {
+        write!(
+            f,
+            "{}{}",
+            "#".repeat(depth),
+            element.header.stylable.node_type()
+        )?;
+        self.write_attributes(f, &element.header.attributes)?;
+        for s in element.content.borrow_strings() {
+            write!(f, " {}", quoted(s, false))?;
+        }
+        writeln!(f)?;
+        if let Some(group) = element.content.borrow_group() {
+            for element in &group.content {
+                self.write_element(f, element, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    //zz All done
+}
+
+//a Test
+#[cfg(test)]
+mod tests {
+    use super::quoted;
+    use crate::{Diagram, DiagramDescriptor, DiagramML};
+
+    //fi read_write
+    /// Read HML into a new diagram, and write it out again
+    fn read_write(text: &str) -> String {
+        let style_set = DiagramDescriptor::create_style_set();
+        let diagram_descriptor = DiagramDescriptor::new(&style_set);
+        let mut diagram = Diagram::new(&diagram_descriptor);
+        let mut dml = DiagramML::new(&mut diagram);
+        dml.read_file(text.as_bytes(), false).unwrap();
+        let mut output = Vec::new();
+        dml.write_file(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_quoted() {
+        assert_eq!(quoted("red", true), "red");
+        assert_eq!(quoted("red", false), "\"red\"");
+        assert_eq!(quoted("a b", true), "\"a b\"");
+        assert_eq!(quoted("#3366cc", true), "\"#3366cc\"");
+        assert_eq!(quoted("say \"hi\"", true), "'say \"hi\"'");
+        assert_eq!(quoted("'a' \"b\"", true), "#\"'a' \"b\"\"#");
+    }
+
+    #[test]
+    fn test_idempotent() {
+        let text = r##"#diagram
+;; a comment
+##var unit=4 accent=#3366cc
+##style id=s1 stroke-width='$unit * 2' fill-color=$accent
+##rule type=rect class="big red" style=s1
+###rule relation=child depth=1 priority=2 fill-color=blue
+##defs
+###marker id=arrow
+####polygon vertices=3
+###rect width=10 id=box fill-color=inherit class=a class=b
+##group class=g grid=1,1
+###use ref=box
+###text font-size=12 "Hello" 'say "hi"'
+##rect width='$unit * 3' width=5 id=r
+"##;
+        let written = read_write(text);
+        assert_eq!(
+            written,
+            r##"#diagram
+##var accent="#3366cc"
+##var unit=4
+##style id=s1 fill-color="#3366cc" stroke-width=8
+##rule type=rect class="big red" style=s1
+###rule relation=child depth=1 priority=2 fill-color=blue
+##defs
+###rect id=box class="a b" fill-color=inherit width=10
+###marker id=arrow
+####polygon vertices=3
+##group class=g grid=1,1
+###use ref=box
+###text font-size=12 "Hello" 'say "hi"'
+##rect id=r width=5
+"##
+        );
+        assert_eq!(read_write(&written), written);
+    }
+}
@@ -27,13 +27,22 @@ Make ParseError be of hml::ReaderError<R:Reader> then it will include the span
 mod diagram_ml;
 mod error;
 mod error_list;
+mod libraries;
+mod markup;
 mod ml_event;
 mod ml_reader;
+mod ml_writer;
 mod name_ids;
+mod xml_reader;
 pub use error::{MLError, MLResult};
 pub use error_list::MLErrorList;
+pub use libraries::MLLibraries;
+pub use markup::{HmlMarkup, MLMarkup, MarkupFormat};
 pub use ml_event::MLReadElement;
 pub use ml_reader::MLReader;
+pub use ml_writer::MLWriter;
 pub use name_ids::{KnownName, NameIds};
+pub use xml_reader::{XmlMarkup, XmlReader};
 
+pub(crate) use diagram_ml::read_markup;
 pub use diagram_ml::DiagramML;
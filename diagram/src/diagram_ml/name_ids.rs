@@ -31,6 +31,15 @@ pub enum KnownName {
     Id,
     Class,
     Depth,
+    Priority,
+    Type,
+    Has,
+    Relation,
+    Var,
+    Include,
+    Import,
+    File,
+    Prefix,
 
     Marker,
     Use,
@@ -42,6 +51,8 @@ pub enum KnownName {
     Polygon,
     Text,
     Path,
+    Connector,
+    Label,
 
     Debug,
     Bbox,
@@ -74,6 +85,7 @@ pub enum KnownName {
     Width,
     Height,
     Coords,
+    Segments,
     Round,
     Stellate,
     Markers,
@@ -81,11 +93,22 @@ pub enum KnownName {
     FontSize,
     FontWeight,
     FontStyle,
+    MaxWidth,
+    Wrap,
+    TextAlign,
+    LineSpacing,
+    Markup,
     Vertices,
     Flags,
     Ref,
     Point,
     Relief,
+    From,
+    To,
+    Target,
+    Along,
+    Offset,
+    Callout,
 }
 
 pub struct NameIds {
@@ -106,6 +129,15 @@ impl NameIds {
         name_ids.add_name(namespace_stack, "id", KnownName::Id);
         name_ids.add_name(namespace_stack, "class", KnownName::Class);
         name_ids.add_name(namespace_stack, "depth", KnownName::Depth);
+        name_ids.add_name(namespace_stack, "priority", KnownName::Priority);
+        name_ids.add_name(namespace_stack, "type", KnownName::Type);
+        name_ids.add_name(namespace_stack, "has", KnownName::Has);
+        name_ids.add_name(namespace_stack, "relation", KnownName::Relation);
+        name_ids.add_name(namespace_stack, "var", KnownName::Var);
+        name_ids.add_name(namespace_stack, "include", KnownName::Include);
+        name_ids.add_name(namespace_stack, "import", KnownName::Import);
+        name_ids.add_name(namespace_stack, "file", KnownName::File);
+        name_ids.add_name(namespace_stack, "prefix", KnownName::Prefix);
 
         name_ids.add_name(namespace_stack, elements::MARKER, KnownName::Marker);
         name_ids.add_name(namespace_stack, elements::USE, KnownName::Use);
@@ -117,6 +149,8 @@ impl NameIds {
         name_ids.add_name(namespace_stack, elements::POLYGON, KnownName::Polygon);
         name_ids.add_name(namespace_stack, elements::TEXT, KnownName::Text);
         name_ids.add_name(namespace_stack, elements::PATH, KnownName::Path);
+        name_ids.add_name(namespace_stack, elements::CONNECTOR, KnownName::Connector);
+        name_ids.add_name(namespace_stack, elements::LABEL, KnownName::Label);
 
         name_ids.add_name(namespace_stack, attributes::DEBUG, KnownName::Debug);
         name_ids.add_name(namespace_stack, attributes::BBOX, KnownName::Bbox);
@@ -165,6 +199,7 @@ impl NameIds {
         name_ids.add_name(namespace_stack, attributes::WIDTH, KnownName::Width);
         name_ids.add_name(namespace_stack, attributes::HEIGHT, KnownName::Height);
         name_ids.add_name(namespace_stack, attributes::COORDS, KnownName::Coords);
+        name_ids.add_name(namespace_stack, attributes::SEGMENTS, KnownName::Segments);
         name_ids.add_name(namespace_stack, attributes::ROUND, KnownName::Round);
         name_ids.add_name(namespace_stack, attributes::STELLATE, KnownName::Stellate);
         name_ids.add_name(namespace_stack, attributes::MARKERS, KnownName::Markers);
@@ -176,11 +211,26 @@ impl NameIds {
             KnownName::FontWeight,
         );
         name_ids.add_name(namespace_stack, attributes::FONTSTYLE, KnownName::FontStyle);
+        name_ids.add_name(namespace_stack, attributes::MAXWIDTH, KnownName::MaxWidth);
+        name_ids.add_name(namespace_stack, attributes::WRAP, KnownName::Wrap);
+        name_ids.add_name(namespace_stack, attributes::TEXTALIGN, KnownName::TextAlign);
+        name_ids.add_name(
+            namespace_stack,
+            attributes::LINESPACING,
+            KnownName::LineSpacing,
+        );
+        name_ids.add_name(namespace_stack, attributes::MARKUP, KnownName::Markup);
         name_ids.add_name(namespace_stack, attributes::VERTICES, KnownName::Vertices);
         name_ids.add_name(namespace_stack, attributes::FLAGS, KnownName::Flags);
         name_ids.add_name(namespace_stack, attributes::REF, KnownName::Ref);
         name_ids.add_name(namespace_stack, attributes::POINT, KnownName::Point);
         name_ids.add_name(namespace_stack, attributes::RELIEF, KnownName::Relief);
+        name_ids.add_name(namespace_stack, attributes::FROM, KnownName::From);
+        name_ids.add_name(namespace_stack, attributes::TO, KnownName::To);
+        name_ids.add_name(namespace_stack, attributes::TARGET, KnownName::Target);
+        name_ids.add_name(namespace_stack, attributes::ALONG, KnownName::Along);
+        name_ids.add_name(namespace_stack, attributes::OFFSET, KnownName::Offset);
+        name_ids.add_name(namespace_stack, attributes::CALLOUT, KnownName::Callout);
 
         name_ids
     }
@@ -0,0 +1,345 @@
+/*a Copyright
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+
+@file    xml_reader.rs
+@brief   XML front end for the diagram markup reader
+ */
+
+//a Imports
+use super::MLMarkup;
+use hml_rs::markup::ContentType as HmlContentType;
+use hml_rs::markup::Event as HmlEvent;
+use hml_rs::names::Attributes as HmlAttributes;
+use hml_rs::names::Name as HmlName;
+use hml_rs::names::NamespaceStack;
+use hml_rs::names::Tag as HmlTag;
+use hml_rs::reader::Character as HmlCharacter;
+use hml_rs::reader::Error as HmlError;
+use hml_rs::reader::Position as HmlPosition;
+use hml_rs::reader::Reader as HmlReader;
+use hml_rs::reader::ReaderError as HmlReaderError;
+use hml_rs::reader::Result as HmlResult;
+use hml_rs::reader::Span as HmlSpan;
+use xml::common::Position as XmlCommonPosition;
+use xml::reader::{EventReader, ParserConfig, XmlEvent};
+
+//a XmlPosition
+//tp XmlPosition
+/// A position within an XML document, by line and column (both
+/// starting at 1)
+#[derive(Debug, Clone, Copy)]
+pub struct XmlPosition {
+    line: usize,
+    column: usize,
+}
+
+//ip XmlPosition
+impl XmlPosition {
+    //fi of_text_position
+    fn of_text_position(posn: xml::common::TextPosition) -> Self {
+        Self {
+            line: posn.row as usize + 1,
+            column: posn.column as usize + 1,
+        }
+    }
+}
+
+//ip HmlPosition for XmlPosition
+impl HmlPosition for XmlPosition {
+    fn none() -> Self {
+        Self { line: 1, column: 1 }
+    }
+}
+
+//ip Display for XmlPosition
+impl std::fmt::Display for XmlPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "line {} column {}", self.line, self.column)
+    }
+}
+
+//a XmlCharacter
+//tp XmlCharacter
+/// A character of an XML document, or the end of the document
+#[derive(Debug, Clone, Copy)]
+pub struct XmlCharacter(Option<char>);
+
+//ip HmlCharacter for XmlCharacter
+impl HmlCharacter for XmlCharacter {
+    fn is_eof(&self) -> bool {
+        self.0.is_none()
+    }
+    fn is_not_rdy(&self) -> bool {
+        false
+    }
+    fn as_char(&self) -> Option<char> {
+        self.0
+    }
+}
+
+//ip Display for XmlCharacter
+impl std::fmt::Display for XmlCharacter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.0 {
+            Some(c) => write!(f, "{}", c),
+            None => write!(f, "None"),
+        }
+    }
+}
+
+//a XmlError
+//tp XmlError
+/// An error in the syntax of an XML document
+#[derive(Debug)]
+pub struct XmlError {
+    span: HmlSpan<XmlPosition>,
+    reason: String,
+}
+
+//ip XmlError
+impl XmlError {
+    //fi of_xml_error
+    fn of_xml_error(e: &xml::reader::Error) -> Self {
+        let posn = XmlPosition::of_text_position(e.position());
+        Self {
+            span: HmlSpan::new_at(&posn),
+            reason: e.msg().to_string(),
+        }
+    }
+}
+
+//ip HmlError for XmlError
+impl HmlError for XmlError {
+    type Position = XmlPosition;
+    fn write_without_span(&self, f: &mut dyn std::fmt::Write) -> std::fmt::Result {
+        write!(f, "XML error: {}", self.reason)
+    }
+    fn borrow_span(&self) -> Option<&HmlSpan<XmlPosition>> {
+        Some(&self.span)
+    }
+}
+
+//ip Display for XmlError
+impl std::fmt::Display for XmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.write_without_span(f)?;
+        write!(f, " at {}", self.span)
+    }
+}
+
+//ip std::error::Error for XmlError
+impl std::error::Error for XmlError {}
+
+//a XmlReader
+//tp XmlReader
+/// The text of an XML document, as a markup reader
+///
+/// The document is parsed by an `XmlMarkup`; the reader provides its
+/// positions and the context of errors within it.
+#[derive(Debug)]
+pub struct XmlReader<'a> {
+    text: &'a str,
+    chars: std::str::Chars<'a>,
+    posn: XmlPosition,
+}
+
+//ip XmlReader
+impl<'a> XmlReader<'a> {
+    //fp new
+    /// Create a new reader of the text of an XML document
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            chars: text.chars(),
+            posn: XmlPosition::none(),
+        }
+    }
+}
+
+//ip HmlReader for XmlReader
+impl<'a> HmlReader for XmlReader<'a> {
+    type Position = XmlPosition;
+    type Char = XmlCharacter;
+    type Error = XmlError;
+
+    fn next_char(&mut self) -> Result<XmlCharacter, XmlError> {
+        let ch = self.chars.next();
+        match ch {
+            Some('\n') => {
+                self.posn.line += 1;
+                self.posn.column = 1;
+            }
+            Some(_) => {
+                self.posn.column += 1;
+            }
+            None => (),
+        }
+        Ok(XmlCharacter(ch))
+    }
+
+    fn borrow_pos(&self) -> &XmlPosition {
+        &self.posn
+    }
+
+    /// Write the line of the text at the start of the context,
+    /// marking the characters of the context on that line beneath it
+    fn fmt_context(
+        &self,
+        f: &mut dyn std::fmt::Write,
+        start: &XmlPosition,
+        end: &XmlPosition,
+    ) -> std::fmt::Result {
+        let line = self.text.lines().nth(start.line - 1).unwrap_or("");
+        let length = {
+            if end.line == start.line && end.column > start.column {
+                end.column - start.column
+            } else {
+                1
+            }
+        };
+        writeln!(f, "{:5}: {}", start.line, line)?;
+        writeln!(
+            f,
+            "       {}{}",
+            " ".repeat(start.column - 1),
+            "^".repeat(length)
+        )
+    }
+}
+
+//a XmlMarkup
+//tp XmlMarkup
+/// The events of an XML document, parsed with xml-rs
+///
+/// Whitespace between elements is dropped, and the content of
+/// elements is trimmed of leading and trailing whitespace.
+///
+/// After an error in the XML the elements that are open are closed,
+/// and the document ended, so that the error is reported just once.
+pub struct XmlMarkup<'a> {
+    events: EventReader<&'a [u8]>,
+    open_elements: Vec<HmlName>,
+    failed_at: Option<XmlPosition>,
+}
+
+//ip XmlMarkup
+impl<'a> XmlMarkup<'a> {
+    //fp new
+    /// Create a new source of the events of an XML document
+    pub fn new(text: &'a str) -> Self {
+        let events = ParserConfig::new()
+            .trim_whitespace(true)
+            .cdata_to_characters(true)
+            .ignore_comments(false)
+            .create_reader(text.as_bytes());
+        Self {
+            events,
+            open_elements: Vec::new(),
+            failed_at: None,
+        }
+    }
+}
+
+//ip MLMarkup for XmlMarkup
+impl<'a, 'b> MLMarkup<XmlReader<'b>> for XmlMarkup<'a> {
+    fn next_event(
+        &mut self,
+        namespace_stack: &mut NamespaceStack,
+        _reader: &mut XmlReader<'b>,
+    ) -> HmlResult<HmlEvent<HmlSpan<XmlPosition>>, XmlPosition, XmlError> {
+        if let Some(posn) = self.failed_at {
+            let span = HmlSpan::new_at(&posn);
+            if let Some(name) = self.open_elements.pop() {
+                namespace_stack.pop_frame();
+                return Ok(HmlEvent::end_element(span, name));
+            } else {
+                return Ok(HmlEvent::end_document(span));
+            }
+        }
+        loop {
+            let event = self.events.next();
+            let posn = XmlPosition::of_text_position(self.events.position());
+            let span = HmlSpan::new_at(&posn);
+            let event = event.map_err(|e| {
+                let e = XmlError::of_xml_error(&e);
+                self.failed_at = Some(*e.span.start());
+                HmlReaderError::ReaderError(e.span, e)
+            })?;
+            match event {
+                XmlEvent::StartDocument { .. } => {
+                    return Ok(HmlEvent::start_document(span, 100));
+                }
+                XmlEvent::EndDocument => {
+                    return Ok(HmlEvent::end_document(span));
+                }
+                XmlEvent::StartElement {
+                    name,
+                    attributes,
+                    namespace,
+                } => {
+                    namespace_stack.push_frame();
+                    for (prefix, uri) in &namespace {
+                        namespace_stack.add_ns(prefix, uri);
+                    }
+                    let mut attrs = HmlAttributes::new();
+                    for a in attributes {
+                        HmlReaderError::of_markup_result(
+                            span,
+                            attrs.add(
+                                namespace_stack,
+                                a.name.prefix_ref().unwrap_or(""),
+                                &a.name.local_name,
+                                a.value,
+                            ),
+                        )?;
+                    }
+                    let tag = HmlReaderError::of_markup_result(
+                        span,
+                        HmlTag::new(
+                            namespace_stack,
+                            name.prefix_ref().unwrap_or(""),
+                            &name.local_name,
+                            attrs,
+                        ),
+                    )?;
+                    self.open_elements.push(tag.name);
+                    return Ok(HmlEvent::start_element(span, tag));
+                }
+                XmlEvent::EndElement { name } => {
+                    let name = HmlReaderError::of_markup_result(
+                        span,
+                        HmlName::new(
+                            namespace_stack,
+                            name.prefix_ref().unwrap_or(""),
+                            &name.local_name,
+                        ),
+                    )?;
+                    self.open_elements.pop();
+                    namespace_stack.pop_frame();
+                    return Ok(HmlEvent::end_element(span, name));
+                }
+                XmlEvent::Comment(text) => {
+                    let lines = text.lines().map(|l| l.to_string()).collect();
+                    return Ok(HmlEvent::comment(span, lines));
+                }
+                XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                    return Ok(HmlEvent::content(span, HmlContentType::Raw, text));
+                }
+                // Whitespace between elements, and processing
+                // instructions, are not part of the diagram
+                _ => (),
+            }
+        }
+    }
+}
@@ -52,10 +52,41 @@ Shapes are regular polygons or regular stars, circles or
 ellipses; they may be filled with a solid color, and they may have
 their edges drawn; they may have rounded corners.
 
-Text elements are multiple lines of text
-
-Path elements are single or multiple line segments defined by
-coordinates relative to the box they are placed within.
+Text elements are multiple lines of text; they are sized using the
+metrics of the font named by their *font* (a comma-separated family
+list, as for CSS *font-family*). Fonts are added to the
+`DiagramDescriptor` by family name from TrueType or OpenType files,
+using `add_font_file` (or the `--font <family>=<file>` option of the
+command-line tool); text whose families have not been added uses a
+simple monospace 'default' font. Each file provides the regular, bold,
+italic or bold italic variant of its family, and the variant used is
+selected by the *font-weight* and *font-style* of the text.
+
+Text may be wrapped at whitespace to a *max-width* (in mm); *wrap*
+selects 'greedy' (the default) or 'optimal' line breaking, and if it
+includes 'fill' the text is rewrapped to the full width of the cell it
+is placed in (for example when its *expand* lets the grid widen it).
+
+The lines of text are aligned by *text-align* ('left', 'center',
+'right' or 'justify'; justified text stretches the spaces of wrapped
+lines other than the last line of each paragraph), and spaced by
+*line-spacing* as a multiple of their height (1 by default).
+
+With *markup* set to 'inline' the text may contain runs of `*bold*`,
+`_italic_`, `` `code` `` (in the 'monospace' font family), `^{superscript}`,
+`_{subscript}` and `{color:colored}` text, which are sized separately and
+rendered as SVG *tspan*s; a backslash escapes a markup character.
+
+Lines of text may be list items: each two leading spaces is a level of
+nesting, and a line starting with `- `, `* ` or `+ ` is bulleted (with a
+different bullet at each level), while one starting with `# ` or a number
+such as `1. ` is numbered. Numbering is '1.', 'a.' and 'i.' at
+successive levels, counting from the first number given, and restarts
+for each list. Wrapped items hang indented past their bullet by the width
+of a two-digit number in the font of the text.
+
+Path elements are lines or curves defined by coordinates relative to
+the box they are placed within.
 
 ### Group element
 
@@ -81,14 +112,66 @@ of columns.
 
 ### Path element
 
-A path element consist of lines (only) at present.
+A path element is either lines between its *coords*, or the
+*segments* given in the style of the data of an SVG path: commands M,
+L, H, V, Q, T, C, S, A and Z (lower case for coordinates relative to
+the current point), with quadratic and cubic control points and
+elliptical arcs. All coordinates are relative to the box the path is
+placed in, from -1 to 1 across its width and height.
+
+```text
+##path width=20 height=10 segments="M -1 1 Q 0 -1 1 1 A 1 0.5 0 0 1 -1 1"
+```
 
-They may be rounded, and closed
+Corners between lines may be rounded, and paths may be closed (with
+*flags* of 1, or a Z segment); markers are placed at the ends of
+curves as for lines.
 
 If closed *and* the path does not end up at the same point at which it
 starts then an extra point, copied from the first point, is added to
 the path.
 
+### Connector element
+
+A connector element has no desired geometry; it connects two other
+elements within the same layout, with a path of horizontal and
+vertical segments (Manhattan style). The ends of the connector are
+given by the `from` and `to` attributes, each of which is an element
+id and an optional compass anchor (n, s, e, w, ne, nw, se, sw or
+center); the default anchor is the center.
+
+```text
+#layout
+##rect id=a grid=1,1 width=10
+##rect id=b grid=3,2 width=10
+##connector from="a e" to="b n" markers="none none arrow"
+```
+
+Once the diagram has been laid out the connector leaves each anchor
+along its normal, and is routed around the other elements of the
+layout. It is rendered as a path, and so supports stroke, markers and
+round.
+
+### Label element
+
+A label element has no desired geometry; it is text that is attached
+to a reference point on another element (the `target`) within the
+same layout. For a path, shape or connector the reference point is a
+fraction (`along`, default 0.5) of the way along its outline; for
+other elements it is the center of the element.
+
+The text of the label is placed centered at `offset` from the
+reference point, and a callout (`callout` of `line`, `bubble` or
+`none`) is drawn with the stroke from the reference point to the text.
+
+```text
+#layout
+##rect id=a grid=1,1 width=10
+##rect id=b grid=3,2 width=10
+##connector id=ab from="a e" to="b n"
+##label target=ab along=0.25 offset="0 -8" stroke-color=grey "request"
+```
+
 ### Rect element - a *shape*
 
 Rectangles are polygonan shape with four vertices, a width and a
@@ -133,6 +216,66 @@ The diagram contents may then *use* this definition, for example with:
 This instances the four rounded rectangles twice, and so there will be
 two `Queue`s in the diagram.
 
+## Libraries
+
+A library is a markup file whose top-level element is `#library`; it
+may contain definitions, styles, rules and variables, but no diagram
+elements. A diagram or library may read a library with an *include*
+or *import* element, whose `file` is found relative to the directory of
+the file being read, and then in each of the library search
+directories in turn (added with `DiagramML::add_search_path`, or the
+`-L` option of the render binary). An *import* of a file that has
+already been read has no effect, whereas an *include* reads it again;
+a library that includes itself (however indirectly) is an error.
+
+With a `prefix`, the ids of the definitions of the library are given
+that prefix and a '.'; the references of *use* elements within the
+library are to its own definitions, and are given the same prefix.
+
+```text
+#diagram
+##import file=queues.dml prefix=q
+##use ref=q.Queue grid=1,1
+```
+
+## Building diagrams in Rust
+
+A diagram may also be constructed in Rust, with `Diagram::builder`;
+the elements are built from their attributes just as they are from
+markup, and all of the errors are returned with the path to the
+element of each.
+
+```text
+let diagram = Diagram::builder(&diagram_descriptor)
+    .layout(|l| {
+        l.rect("a").grid(1, 1).fill("red");
+        l.group("g", |g| g.circle("c").width(3.)).grid(2, 1);
+    })
+    .build()?;
+```
+
+## Writing diagrams
+
+A diagram that has been read (or built) may be written out as
+canonical HML with `DiagramML::write_file` (or the `--hml_output`
+option of the command line tool). Variables, styles and rules are
+written first, then the definitions and then the contents; the
+attributes of elements are written with 'id' and 'class' first and
+then the rest sorted by name, and the values of variables and
+functions are evaluated. Reading the HML written, and writing it
+again, produces the same text.
+
+## Warnings
+
+Once a diagram has been styled and laid out, `Diagram::lint`
+returns warnings for things that are probably mistakes: attributes
+that are not styles of their element, styles that no element uses,
+rules that match no element, definitions and markers that are not
+used (other than those from libraries), grid references outside the
+cells given by the 'minx' or 'miny' of a layout, and layouts whose
+'equalx', 'equaly' or 'aspect' cannot all be met. The command line tool
+reports these, and with `--deny-warnings` it fails if there are any.
+
 ## Styling
 
 The structure of a diagram should be defined by the diagram
@@ -154,6 +297,96 @@ structure*. This is the same operation as for web pages with CSS
 diagram to be playing with the styling, compared with 10% developing
 its structure.
 
+A rule selects the elements that have *all* of its conditions: the
+element `type` (such as 'rect' or 'text'), the `id`, every one of its
+`class`es, and every style named by `has` being set on the element; a
+rule with no conditions selects every element, and `depth` limits a
+rule to elements at most that many levels within the diagram. Rules
+nested within a rule select the descendants of the elements it
+selects, or with `relation=child` only their children.
+
+```text
+##rule type=rect class="queue full" fill-color=red
+##rule has=stroke-color stroke-width=0.5
+##rule{ id=pipeline
+#rule relation=child type=text font-weight=bold
+##rule}
+```
+
+When more than one rule sets the same style of an element the value
+used is that of the rule with the highest *priority* (an integer,
+default 0); for rules of the same priority it is that of the most
+specific rule, as for CSS - matching more ids, then more classes, then
+being nested more deeply within other rules; and for rules that are
+equal in all of these it is that of the last rule.
+
+```text
+##rule class=warning fill-color=orange
+##rule id=fault fill-color=red
+##rule class=muted fill-color=grey priority=1
+```
+
+Instead of giving styles itself a rule may use a *style* by its id,
+with a `style` attribute. The style ids are resolved when the
+stylesheet is applied to the diagram, so a style may be defined after
+the rules that use it, or in another file (such as a library of
+styles); a rule whose style is not defined anywhere is an error.
+
+```text
+##rule class=warning style=alert
+##style id=alert fill-color=orange stroke-width=2
+```
+
+Rules may also be given in separate stylesheet files, in a CSS-like
+text format that is read after the markup (and so its rules follow
+those of the markup); the render binary takes any number of these with
+`--stylesheet`. A selector is a sequence of an optional type (or `*`)
+with `#id`, `.class` and `[style]` conditions, separated by whitespace
+for descendants or `>` for children; the declaration `priority` sets
+the priority of the rules.
+
+```text
+/* Registers within the pipeline */
+#pipeline > text.reg, text.reg[fill-color] {
+    fill-color: lightblue;
+    border-width: 0.5;
+    priority: 1;
+}
+```
+
+Style values may use *variables*, set with `#var` in a library or
+diagram (or `$name = value;` in a stylesheet file) before they are
+used; a library given on the command line is read before the diagram,
+so that the diagram may use its variables. A value that uses a
+variable or a color function is evaluated when it is read: numbers may
+be combined with `+`, `-`, `*`, `/` and parentheses (with `20%` being
+0.2), and colors may be computed with `lighten(c, a)`, `darken(c, a)`,
+`saturate(c, a)`, `desaturate(c, a)`, `mix(c0, c1, t)`, `rgb(r, g, b)`
+and `hsl(h, s, l)`.
+
+```text
+#library
+##var unit=4 accent=#3366cc
+##var pale="lighten($accent, 20%)"
+##rule type=rect fill-color=$pale border-width="$unit / 8"
+```
+
+Some styles are *inherited*: the fill and stroke colors, the stroke
+width and the font and text styles. An element that does not set such
+a style (from its attributes or from a rule) takes the value of its
+nearest ancestor that does - even an ancestor (such as a *layout*)
+that does not use the style itself. Any style may be given the value
+`inherit`, to take the value of its parent, or `initial`, to use the
+default value for the style rather than one that would be inherited.
+
+```text
+#layout font-size=20 fill-color=darkblue
+##text "Large and blue"
+##text font-size=initial "Normal size, blue"
+##layout border-width=2 border-color=black
+###rect border-width=inherit border-color=inherit
+```
+
 ## Layout types
 
 A grid layout uses a specification for each cell that participates
@@ -206,6 +439,91 @@ If the shapes are of different size, but the desire is to have the
 Now the minimum width (X dimension) between cell 1 and cell 2 will
  be 50. units, and the same is required between cells 2 and 3.
 
+### Row and column layouts
+
+A layout with a *direction* of `row` or `column` gives its contents
+ grid cells itself, in order, along a row (or down a column); any
+ element with its own *grid* or *place* is left alone. The cells
+ of the elements are between grid lines 1 and 2, 3 and 4, and so on,
+ with the cells between these being the *gap* between the elements.
+ If the layout has a *wrap-count* then a new row (or column) is
+ started after that many elements, with rows also separated by the
+ *gap*. (It is not *wrap*, as that is the inherited wrapping of
+ text.)
+
+The *justify* of the layout is `start`, `center`, `end`,
+ `space-between` (the extra space goes into the gaps) or `stretch`
+ (the extra space goes to the elements, which expand to fill it), for
+ when the layout is given more space than it desires along its rows.
+ The *align* is `start`, `center`, `end` or `stretch`, and places
+ each element across its row, unless the element has its own
+ *anchor* or *expand*.
+
+```text
+#layout direction=row gap=5 align=end ##circle id=a ##rect id=b ##text "c"
+```
+
+As the cells are those of the grid of the layout, *minx* and *miny*
+ apply to them too.
+
+### Named areas and grid lines
+
+A layout may name rectangular areas of its grid with *areas*: rows
+ of cell names separated by commas, with `.` for a cell that is in
+ no area. Column 1 of the rows is between grid lines 1 and 2 in X,
+ and so on, and similarly for the rows in Y. The contents of the
+ layout then use *area* rather than *grid* to be placed in an area:
+
+```text
+#layout areas="hdr hdr, nav body" ##text area=hdr "Title" ##rect area=nav ##rect area=body
+```
+
+The rows may instead each be given in double quotes, as in CSS:
+
+```text
+#layout areas='"hdr hdr" "nav body"' ##text area=hdr "Title" ##rect area=nav ##rect area=body
+```
+
+The *area* of an element may instead be four grid lines (start X,
+ start Y, end X, end Y), each a number, a name given to a line by
+ the *minx* or *miny* of the layout, or the start or end line of an
+ area, such as `hdr-start` or `body-end`:
+
+```text
+#layout minx=left,20,mid,50,right ##rect area=mid,1,right,2
+```
+
+Each name in the *areas* must form a rectangle that no other name
+ overlaps, and each row must have the same number of cells.
+
+### Equal sizes and aspect ratios
+
+A layout may require spans of its grid to have equal sizes with
+ *equalx* and *equaly*: groups of two grid cell ids (start and
+ end), each span being made the same size as the first. A group may
+ be preceded by `*` and a ratio, in which case the spans are sized in
+ proportion to their ratios; here the span from 5 to 6 is twice the
+ size of the span from 1 to 2:
+
+```text
+#layout equalx=1,2,3,4,*2,5,6
+```
+
+The *aspect* of a layout is groups of four grid cell ids (start X,
+ start Y, end X, end Y), each optionally preceded by `*` and the
+ ratio of width to height (1 if not given); each such cell is made
+ at least tall enough and wide enough for its ratio:
+
+```text
+#layout aspect=1,1,2,2,*2,2,1,3,2
+```
+
+These constraints are honoured both in the desired size of the
+ layout and when it is expanded to fill a larger space; for an
+ aspect, the height of a cell then follows its width. Constraints
+ that contradict each other cannot all be met, and are reported as a
+ warning on the layout.
+
 # Example diagrams
 
 A simple first example diagram consists of four shapes laid out in a 2-by-2 grid:
@@ -226,7 +544,6 @@ The diagram provides very simple text and polygons with the grid-based
 layout. It supports a first cut of the stylesheet mechanism.
 
 Diagrams written for this version may need to change for version 0.3.
-Path support for bezier curves and closed paths will be added.
 
 ## Upcoming changes
 
@@ -234,39 +551,6 @@ Nothing imminent
 
 ## Upcoming additions
 
-### Style rules
-
-The initial revision of style rules applies them in style-sheet order;
-last style that matches is applied. This will change to highest
-priority rule match - with each rule allowed to specify a priority,
-the default being its depth (supplying a 'longest rule wins' by
-default).
-
-### Label elements
-
-A new element *label* need to be added, which has no desired geometry,
-but is an element that is attached to a reference point that is on a
-path or object, and which has an offset from that reference point to
-the contents of the label.
-
-The label is rendered with its contents in a box of its desired
-geometry, and with a rendering of the callout from the reference point
-to the offset point as, for example, a line, or a callout bubble.
-
-An example would be to label a path on a diagram.
-
-### Connector elements
-
-A new element *connector* needs to be added, which has no desired
-geometry. It has attributes such as stroke, stroke-width, markers,
-round, etc. It will have a programmatic description of the points that
-it defines; it may be defined to be Manhattan style; it may be defined
-to have start and end points that are normals to their attachment
-points.
-
-The purpose of the path element is to provide for connectors between
-blocks on a diagram.
-
 ### Programmatic elements
 
 A simple byte-code interpreter is required that can interpret a
@@ -288,41 +572,6 @@ rendered will enable access to geometry and style attributes of
 elements, and to provide for the generation of path geometries for
 objects such as *path* elements and *connector*s.
 
-### Style propagation
-
-The design of the style system is based on inheriting the value of
-styles from the parent objects where this is specified. For example,
-one ought to be able to specify at a *layout* element that the
-contents should all have a particular border style, unless those
-elements override that.
-
-This inheritance is not yet supported.
-
-### Markup 'include's
-
-The markup should support '#include' tags that permit inclusion of
-other markup files as complete elements within the markup.
-
-This will provide for libraries and stylesheets to be specified within
-a document by default.
-
-### Style rule resolution
-
-A style *rule* specifies a *style* attribute that is a reference to a *style* with that *id*.
-
-The resolution of this id to a style currently occurs at the point in
-the markup that the rule is spotted. This precludes the use of
-stylesheets, and basically is wrong.
-
-The resolution of style names to styles must be performed at a
-stylesheet name resolution point.
-
-## Render binary additions
-
-The command-line tool for rendering diagrams must support a diagram
-description with multiple markup files; one for the diagram, others
-for stylesheets particular to rendering.
-
 # Open issues
 
 border should be  border-width
@@ -337,6 +586,8 @@ mod diagram_ml;
 
 pub(crate) use self::diagram::{DiagramContents, StyleRule, StyleSheet};
 
-pub use crate::diagram::{Diagram, DiagramDescriptor, IndentOptions};
+pub use crate::diagram::{Diagram, DiagramDescriptor, FontError, IndentOptions};
+pub use crate::diagram::{BuildError, DiagramBuilder, ElementBuilder};
+pub use crate::diagram::{ElementError, ElementErrorList};
 pub use crate::diagram::{GenerateSvg, Svg};
-pub use diagram_ml::{DiagramML, MLErrorList};
+pub use diagram_ml::{DiagramML, MLErrorList, MarkupFormat};
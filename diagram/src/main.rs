@@ -14,7 +14,9 @@ use clap::{App, Arg};
 use diagram::Diagram;
 use diagram::DiagramDescriptor;
 use diagram::DiagramML;
+use diagram::ElementErrorList;
 use diagram::IndentOptions;
+use diagram::MarkupFormat;
 use diagram::Svg;
 use indent_display::{IndentedDisplay, Indenter};
 use vg_rs::BBox;
@@ -29,15 +31,18 @@ fn exit_on_err<T, U: std::fmt::Display>(result: Result<T, U>) -> T {
     }
 }
 
-fn exit_on_err_vec<T>(result: Result<T, Vec<String>>) -> T {
-    match result {
-        Err(vs) => {
-            for v in vs {
-                eprintln!("{}\n", v);
-            }
-            std::process::exit(1);
+fn add_err_vec<T>(errors: &mut Vec<String>, result: Result<T, Vec<String>>) {
+    if let Err(mut vs) = result {
+        errors.append(&mut vs);
+    }
+}
+
+fn exit_on_err_vec(errors: Vec<String>) {
+    if !errors.is_empty() {
+        for v in errors {
+            eprintln!("{}\n", v);
         }
-        Ok(v) => v,
+        std::process::exit(1);
     }
 }
 
@@ -62,6 +67,38 @@ fn main() {
              .help("Put XML elements in SVG on newlines and indent using this string; use '' for git-friendly svg output")
              .required(false)
              .takes_value(true))
+        .arg(Arg::with_name("font")
+             .long("font")
+             .help("Add a font family from a .ttf or .otf file, as <family>=<file>; used for text metrics")
+             .required(false)
+             .takes_value(true)
+             .number_of_values(1)
+             .multiple(true))
+        .arg(Arg::with_name("stylesheet")
+             .long("stylesheet")
+             .help("Add the rules of a stylesheet in the CSS-like .dss format, after those of the input files")
+             .required(false)
+             .takes_value(true)
+             .number_of_values(1)
+             .multiple(true))
+        .arg(Arg::with_name("library_path")
+             .long("library_path")
+             .short("L")
+             .help("Add a directory to search for the libraries named by 'include' and 'import' elements")
+             .required(false)
+             .takes_value(true)
+             .number_of_values(1)
+             .multiple(true))
+        .arg(Arg::with_name("format")
+             .long("format")
+             .help("Markup format of the input files, 'hml' or 'xml'; by default a '.xml' file is read as XML, and others as HML")
+             .required(false)
+             .takes_value(true))
+        .arg(Arg::with_name("hml_output")
+             .long("hml_output")
+             .help("Write the diagram (after reading the input files and stylesheets) as canonical HML to this file")
+             .required(false)
+             .takes_value(true))
         .arg(Arg::with_name("debug")
              .short("d")
              .multiple(true))
@@ -85,28 +122,72 @@ fn main() {
              .long("diag_display")
              .help("Display diagram hierarchy")
              .multiple(false))
+        .arg(Arg::with_name("deny_warnings")
+             .long("deny-warnings")
+             .help("Treat warnings about the diagram (such as unused definitions or styles) as errors")
+             .multiple(false))
         .arg(Arg::with_name("file")
-             .help("Input files to read; first must be a diagram, others must be library")
+             .help("Input files to read; first must be a diagram, others must be library (and are read first)")
              .multiple(true))
         .get_matches();
 
     let style_set = DiagramDescriptor::create_style_set();
-    let diagram_descriptor = DiagramDescriptor::new(&style_set);
+    let mut diagram_descriptor = DiagramDescriptor::new(&style_set);
+    if let Some(fonts) = matches.values_of("font") {
+        for font in fonts {
+            match font.split_once('=') {
+                Some((family, filename)) => {
+                    exit_on_err(diagram_descriptor.add_font_file(family, filename));
+                }
+                None => {
+                    eprintln!("Font '{}' must be given as <family>=<file>", font);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
     let mut diagram = Diagram::new(&diagram_descriptor);
+    // Errors in reading any file are reported together, once all
+    // the files and stylesheets have been read
+    let mut read_errors = Vec::new();
     match matches.values_of("file") {
         None => {
             println!("Should read stdin");
         }
         Some(vf) => {
+            // The first file is the diagram; the rest are libraries,
+            // which are read first so that their variables may be
+            // used in the diagram
+            let filenames: Vec<&str> = vf.collect();
             let mut diagram_ml = DiagramML::new(&mut diagram);
-            let mut is_library = false;
-            for filename in vf {
-                let file = File::open(filename).unwrap();
-                exit_on_err_vec(diagram_ml.read_file(file, is_library));
-                is_library = true;
+            if let Some(format) = matches.value_of("format") {
+                diagram_ml.set_format(exit_on_err(format.parse::<MarkupFormat>()));
+            }
+            if let Some(paths) = matches.values_of("library_path") {
+                for path in paths {
+                    diagram_ml.add_search_path(path);
+                }
+            }
+            for filename in &filenames[1..] {
+                add_err_vec(&mut read_errors, diagram_ml.read_path(filename, true));
             }
+            add_err_vec(&mut read_errors, diagram_ml.read_path(filenames[0], false));
         }
     }
+    if let Some(stylesheets) = matches.values_of("stylesheet") {
+        let mut diagram_ml = DiagramML::new(&mut diagram);
+        for filename in stylesheets {
+            match File::open(filename) {
+                Ok(file) => add_err_vec(&mut read_errors, diagram_ml.read_stylesheet(file)),
+                Err(e) => read_errors.push(format!("{}: {}", filename, e)),
+            }
+        }
+    }
+    exit_on_err_vec(read_errors);
+    if let Some(filename) = matches.value_of("hml_output") {
+        let file_out = exit_on_err(File::create(filename));
+        exit_on_err(DiagramML::new(&mut diagram).write_file(std::io::BufWriter::new(file_out)));
+    }
     let svg_version = {
         match matches.value_of("svg_version") {
             Some("1.0") => 10,
@@ -124,24 +205,35 @@ fn main() {
     if DEBUG_MAIN {
         println!("Uniqify");
     }
-    exit_on_err(diagram.uniquify());
+    // Every stage is run even if an earlier one has errors, so that
+    // all of the errors in the diagram are reported together
+    let mut errors = ElementErrorList::new();
+    errors.append(diagram.uniquify());
     if DEBUG_MAIN {
         println!("Apply stylesheet");
     }
-    diagram.apply_stylesheet();
+    errors.append(diagram.apply_stylesheet());
     if DEBUG_MAIN {
         println!("Style");
     }
-    exit_on_err(diagram.style());
+    errors.append(diagram.style());
     if DEBUG_MAIN {
         println!("Lay out");
     }
-    // exit_on_err( diagram.layout(&BBox::new(0.,0.,297.,210.)) );
-    exit_on_err(diagram.layout(&BBox::none()));
+    // errors.append( diagram.layout(&BBox::new(0.,0.,297.,210.)) );
+    errors.append(diagram.layout(&BBox::none()));
     if DEBUG_MAIN {
         println!("Generate geometry");
     }
-    exit_on_err(diagram.geometry());
+    errors.append(diagram.geometry());
+    exit_on_err(errors.as_err(()));
+    let warnings = diagram.lint();
+    if !warnings.is_empty() {
+        eprint!("{}", warnings);
+        if matches.is_present("deny_warnings") {
+            std::process::exit(1);
+        }
+    }
     let mut stdout = std::io::stdout();
     let mut ind = Indenter::new(&mut stdout, "  ", &IndentOptions {});
     diagram.indent(&mut ind).unwrap();
@@ -29,6 +29,11 @@ use parameter::Parameter;
 pub use traits::*;
 
 //a Font
+/// Flag in a `FontStyle` for a bold font
+pub const FONT_BOLD: usize = 1;
+/// Flag in a `FontStyle` for an italic (or oblique) font
+pub const FONT_ITALIC: usize = 2;
+
 //tp FontStyle
 /// A font style as a size in points and flags for font styling options
 #[derive(Clone, Copy, Debug)]
@@ -41,20 +46,32 @@ pub struct FontStyle {
 impl FontStyle {
     //fp new
     /// Create a new simple font style
-    pub fn new(size: f64, weight: Option<&str>, _style: Option<&str>) -> Self {
+    pub fn new(size: f64, weight: Option<&str>, style: Option<&str>) -> Self {
         let weight_flags = {
             match weight {
-                Some("bold") | Some("Bold") => 1,
+                Some("bold") | Some("Bold") => FONT_BOLD,
                 _ => 0,
             }
         };
         let style_flags = {
-            match weight {
-                Some("italic") | Some("Italic") => 1,
+            match style {
+                Some("italic") | Some("Italic") => FONT_ITALIC,
                 _ => 0,
             }
         };
-        let flags = weight_flags + style_flags;
+        let flags = weight_flags | style_flags;
         Self { size, flags }
     }
+
+    //mp is_bold
+    /// Return true if the style is bold
+    pub fn is_bold(&self) -> bool {
+        (self.flags & FONT_BOLD) != 0
+    }
+
+    //mp is_italic
+    /// Return true if the style is italic
+    pub fn is_italic(&self) -> bool {
+        (self.flags & FONT_ITALIC) != 0
+    }
 }
@@ -0,0 +1,619 @@
+/*a Copyright
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+
+@file    dss.rs
+@brief   Reader for stylesheets in a CSS-like text format
+ */
+
+//! A stylesheet text ('.dss') is a sequence of rule sets, in the
+//! manner of CSS:
+//!
+//! ```text
+//! /* Comments are as for CSS */
+//! layout.pipeline > text.reg, #title {
+//!     fill-color: lightblue;
+//!     border-width: 0.5;
+//! }
+//! ```
+//!
+//! Each selector is a sequence of compound selectors separated by
+//! whitespace (descendant) or '>' (child); a compound selector is an
+//! optional node type (or '*'), and any number of '#id', '.class' and
+//! '[style]' conditions. Each declaration sets a style; the
+//! declaration 'priority' sets the priority of the rules instead.
+//!
+//! A variable may be set between rule sets with '$name = value;', for
+//! use in the values of the declarations that follow it.
+
+//a Imports
+use crate::{StylableNodeAction, StylableNodeRule, Stylesheet};
+
+//a DssError
+//tp DssError
+/// An error in a stylesheet text, with the position of the text in
+/// error (line and column number from 1, and the number of
+/// characters)
+#[derive(Debug, Clone, PartialEq)]
+pub struct DssError {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub reason: String,
+}
+
+//ip DssError
+impl DssError {
+    //fi new
+    /// Create an error for a byte range of the text
+    fn new(text: &str, start: usize, end: usize, reason: &str) -> Self {
+        let line_start = text[..start].rfind('\n').map(|n| n + 1).unwrap_or(0);
+        let line = text[..start].matches('\n').count() + 1;
+        let column = text[line_start..start].chars().count() + 1;
+        let line_end = text[start..]
+            .find('\n')
+            .map(|n| n + start)
+            .unwrap_or(text.len());
+        let length = text[start..end.clamp(start, line_end)].chars().count();
+        Self {
+            line,
+            column,
+            length,
+            reason: reason.into(),
+        }
+    }
+
+    //mp fmt_context
+    /// Write the line of the text with the error, marking the
+    /// characters in error beneath it
+    pub fn fmt_context(&self, f: &mut dyn std::fmt::Write, text: &str) -> std::fmt::Result {
+        let line = text.lines().nth(self.line - 1).unwrap_or("");
+        writeln!(f, "{:5}: {}", self.line, line)?;
+        writeln!(
+            f,
+            "       {}{}",
+            " ".repeat(self.column - 1),
+            "^".repeat(self.length.max(1))
+        )
+    }
+}
+
+//ip Display for DssError
+impl std::fmt::Display for DssError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at line {} column {}",
+            self.reason, self.line, self.column
+        )
+    }
+}
+
+//fi unquote
+/// Remove matching quotes from around a value
+fn unquote(value: &str) -> &str {
+    if value.len() >= 2
+        && (value.starts_with('"') && value.ends_with('"')
+            || value.starts_with('\'') && value.ends_with('\''))
+    {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    }
+}
+
+//a Selectors
+//tp Compound
+/// A compound selector of a selector, and whether it must be a child
+/// (rather than any descendant) of the previous compound selector
+struct Compound {
+    rule: StylableNodeRule,
+    child: bool,
+}
+
+//fi is_name_char
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+//fi name_end
+/// Find the end of a name starting at a byte offset in the text
+fn name_end(text: &str, start: usize) -> usize {
+    text[start..]
+        .find(|c: char| !is_name_char(c))
+        .map(|n| n + start)
+        .unwrap_or(text.len())
+}
+
+//fi char_end
+/// Find the end of the character at a byte offset in the text (the
+/// offset itself at the end of the text)
+fn char_end(text: &str, pos: usize) -> usize {
+    text[pos..]
+        .chars()
+        .next()
+        .map_or(pos, |c| pos + c.len_utf8())
+}
+
+//fi parse_selector
+/// Parse a selector in the byte range of the text, into its compound
+/// selectors
+fn parse_selector(text: &str, start: usize, end: usize) -> Result<Vec<Compound>, DssError> {
+    let mut compounds = Vec::new();
+    let mut pos = start;
+    let mut child = false;
+    let mut in_compound = false;
+    while pos < end {
+        let c = text[pos..].chars().next().unwrap();
+        let c_end = pos + c.len_utf8();
+        if c.is_whitespace() {
+            in_compound = false;
+            pos = c_end;
+            continue;
+        }
+        if c == '>' {
+            if compounds.is_empty() || child {
+                return Err(DssError::new(text, pos, c_end, "misplaced '>' in selector"));
+            }
+            child = true;
+            in_compound = false;
+            pos = c_end;
+            continue;
+        }
+        if !in_compound {
+            compounds.push(Compound {
+                rule: StylableNodeRule::new(),
+                child,
+            });
+            child = false;
+            in_compound = true;
+        }
+        let compound = compounds.last_mut().unwrap();
+        let rule = std::mem::take(&mut compound.rule);
+        match c {
+            '*' => {
+                compound.rule = rule;
+                pos = c_end;
+            }
+            '#' | '.' | '[' => {
+                let n_end = name_end(text, c_end).min(end);
+                if n_end == c_end {
+                    return Err(DssError::new(
+                        text,
+                        pos,
+                        char_end(text, c_end),
+                        "expected a name",
+                    ));
+                }
+                let name = &text[c_end..n_end];
+                compound.rule = match c {
+                    '#' => rule.has_id(name),
+                    '.' => rule.has_class(name),
+                    _ => rule.has_style(name),
+                };
+                pos = n_end;
+                if c == '[' {
+                    if !text[pos..end].starts_with(']') {
+                        return Err(DssError::new(
+                            text,
+                            pos,
+                            char_end(text, pos),
+                            "expected ']'",
+                        ));
+                    }
+                    pos += 1;
+                }
+            }
+            c if is_name_char(c) => {
+                let n_end = name_end(text, pos).min(end);
+                compound.rule = rule.has_type(&text[pos..n_end]);
+                pos = n_end;
+            }
+            _ => {
+                return Err(DssError::new(
+                    text,
+                    pos,
+                    c_end,
+                    &format!("unexpected '{}' in selector", c),
+                ));
+            }
+        }
+    }
+    if compounds.is_empty() || child {
+        return Err(DssError::new(text, start, end, "incomplete selector"));
+    }
+    Ok(compounds)
+}
+
+//a Reader
+//tp DssReader
+/// A reader of a stylesheet text, which adds rules and actions to a
+/// stylesheet, accumulating errors
+struct DssReader<'t> {
+    text: &'t str,
+    pos: usize,
+    errors: Vec<DssError>,
+}
+
+//ip DssReader
+impl<'t> DssReader<'t> {
+    //mi skip_space
+    /// Skip whitespace and comments
+    fn skip_space(&mut self) {
+        loop {
+            let rest = &self.text[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if let Some(comment) = trimmed.strip_prefix("/*") {
+                match comment.find("*/") {
+                    Some(n) => {
+                        self.pos += n + 4;
+                    }
+                    None => {
+                        self.errors.push(DssError::new(
+                            self.text,
+                            self.pos,
+                            self.pos + 2,
+                            "unterminated comment",
+                        ));
+                        self.pos = self.text.len();
+                    }
+                }
+            } else {
+                return;
+            }
+        }
+    }
+
+    //mi find_outside_quotes
+    /// Find the first of a set of characters from the current
+    /// position that is not within quotes, or the end of the text
+    fn find_outside_quotes(&self, chars: &[char]) -> usize {
+        let mut quote = None;
+        for (i, c) in self.text[self.pos..].char_indices() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => (),
+                None if c == '"' || c == '\'' => quote = Some(c),
+                None if chars.contains(&c) => return self.pos + i,
+                None => (),
+            }
+        }
+        self.text.len()
+    }
+
+    //mi trim
+    /// Trim a byte range of the text of whitespace
+    fn trim(&self, start: usize, end: usize) -> (usize, usize) {
+        let s = &self.text[start..end];
+        let start = start + (s.len() - s.trim_start().len());
+        (start, start + s.trim().len())
+    }
+
+    //mi read_declarations
+    /// Read the declarations of a block up to its closing '}',
+    /// returning the action of the styles and the priority of the
+    /// rule
+    fn read_declarations(&mut self, stylesheet: &Stylesheet) -> (StylableNodeAction, isize) {
+        let mut styling = Vec::new();
+        let mut inherit = Vec::new();
+        let mut source = Vec::new();
+        let mut priority = 0;
+        loop {
+            self.skip_space();
+            let end = self.find_outside_quotes(&[';', '}']);
+            if end > self.pos {
+                let decl_start = self.pos;
+                match self.text[decl_start..end].find(':') {
+                    None => {
+                        let (s, e) = self.trim(decl_start, end);
+                        self.errors.push(DssError::new(
+                            self.text,
+                            s,
+                            e,
+                            "expected '<style>: <value>'",
+                        ));
+                    }
+                    Some(colon) => {
+                        let (ns, ne) = self.trim(decl_start, decl_start + colon);
+                        let (vs, ve) = self.trim(decl_start + colon + 1, end);
+                        let name = &self.text[ns..ne];
+                        let value = unquote(&self.text[vs..ve]);
+                        if name == "priority" {
+                            match value.parse::<isize>() {
+                                Ok(p) => priority = p,
+                                Err(_) => self.errors.push(DssError::new(
+                                    self.text,
+                                    vs,
+                                    ve,
+                                    "bad priority",
+                                )),
+                            }
+                        } else if name.is_empty() || name_end(name, 0) != name.len() {
+                            self.errors.push(DssError::new(
+                                self.text,
+                                ns,
+                                ne.max(ns + 1),
+                                "bad style name",
+                            ));
+                        } else if value == "inherit" && stylesheet.has_style(name) {
+                            inherit.push(name);
+                            source.push((name, value.to_string()));
+                        } else {
+                            match stylesheet
+                                .style_value(name, value)
+                                .and_then(|v| Ok((v, stylesheet.variables().evaluate(value)?)))
+                            {
+                                Ok((v, text)) => {
+                                    styling.push((name.to_string(), v));
+                                    source.push((name, text.into_owned()));
+                                }
+                                Err(e) => {
+                                    // Mark the name if it is not a style, else the value
+                                    let (s, e2) = {
+                                        if stylesheet.has_style(name) {
+                                            (vs, ve)
+                                        } else {
+                                            (ns, ne)
+                                        }
+                                    };
+                                    self.errors.push(DssError::new(
+                                        self.text,
+                                        s,
+                                        e2,
+                                        &e.to_string(),
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            self.pos = end;
+            if end >= self.text.len() {
+                self.errors.push(DssError::new(
+                    self.text,
+                    self.text.len(),
+                    self.text.len(),
+                    "missing '}' at end of stylesheet",
+                ));
+                break;
+            }
+            self.pos += 1;
+            if self.text[end..].starts_with('}') {
+                break;
+            }
+        }
+        let mut action = StylableNodeAction::new(styling);
+        for name in inherit {
+            action = action.inherit(name);
+        }
+        for (name, value) in source {
+            action = action.source(name, &value);
+        }
+        (action, priority)
+    }
+
+    //mi read_variable
+    /// Read a variable definition '$name = value;' and set it in the
+    /// stylesheet
+    fn read_variable(&mut self, stylesheet: &mut Stylesheet) {
+        let start = self.pos;
+        let end = self.find_outside_quotes(&[';']);
+        self.pos = (end + 1).min(self.text.len());
+        let equals = match self.text[start..end].find('=') {
+            Some(equals) => equals,
+            None => {
+                let (s, e) = self.trim(start, end);
+                self.errors.push(DssError::new(
+                    self.text,
+                    s,
+                    e,
+                    "expected '$<name> = <value>;'",
+                ));
+                return;
+            }
+        };
+        let (ns, ne) = self.trim(start + 1, start + equals);
+        let (vs, ve) = self.trim(start + equals + 1, end);
+        let name = &self.text[ns..ne];
+        if name.is_empty() || name_end(name, 0) != name.len() {
+            self.errors.push(DssError::new(
+                self.text,
+                start,
+                ne.max(start + 1),
+                "bad variable name",
+            ));
+        } else if let Err(e) = stylesheet.set_variable(name, unquote(&self.text[vs..ve])) {
+            self.errors
+                .push(DssError::new(self.text, vs, ve, &e.to_string()));
+        }
+    }
+
+    //mi read_rule_set
+    /// Read a rule set (selectors and a block of declarations) and
+    /// add its rules and action to the stylesheet
+    fn read_rule_set(&mut self, stylesheet: &mut Stylesheet) {
+        let sel_start = self.pos;
+        let sel_end = self.find_outside_quotes(&['{', '}']);
+        if sel_end >= self.text.len() || self.text[sel_end..].starts_with('}') {
+            let (s, e) = self.trim(sel_start, sel_end);
+            self.errors.push(DssError::new(
+                self.text,
+                s,
+                e,
+                "expected '{' after selector",
+            ));
+            self.pos = (sel_end + 1).min(self.text.len());
+            return;
+        }
+        let mut selectors = Vec::new();
+        let mut start = sel_start;
+        for (i, c) in self.text[sel_start..sel_end].char_indices() {
+            if c == ',' {
+                selectors.push(self.trim(start, sel_start + i));
+                start = sel_start + i + 1;
+            }
+        }
+        selectors.push(self.trim(start, sel_end));
+        let selectors: Vec<_> = selectors
+            .into_iter()
+            .filter_map(|(s, e)| match parse_selector(self.text, s, e) {
+                Ok(compounds) => Some(compounds),
+                Err(e) => {
+                    self.errors.push(e);
+                    None
+                }
+            })
+            .collect();
+        self.pos = sel_end + 1;
+        let (action, priority) = self.read_declarations(stylesheet);
+        if selectors.is_empty() {
+            return;
+        }
+        let action = stylesheet.add_action(None, action);
+        for compounds in selectors {
+            let n = compounds.len();
+            let mut parent = None;
+            for (i, c) in compounds.into_iter().enumerate() {
+                let rule = c.rule.child(c.child).priority(priority);
+                let action = if i + 1 == n { Some(action) } else { None };
+                parent = Some(stylesheet.add_rule(parent, rule, action));
+            }
+        }
+    }
+}
+
+//ip Stylesheet
+impl Stylesheet<'_> {
+    //mp read_dss
+    /// Read a stylesheet text, adding its rules to the stylesheet;
+    /// all the errors in the text are returned
+    pub fn read_dss(&mut self, text: &str) -> Result<(), Vec<DssError>> {
+        let mut reader = DssReader {
+            text,
+            pos: 0,
+            errors: Vec::new(),
+        };
+        loop {
+            reader.skip_space();
+            if reader.pos >= text.len() {
+                break;
+            }
+            if text[reader.pos..].starts_with('$') {
+                reader.read_variable(self);
+            } else {
+                reader.read_rule_set(self);
+            }
+        }
+        if reader.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(reader.errors)
+        }
+    }
+}
+
+//a Test
+#[cfg(test)]
+mod test_dss {
+    use super::*;
+    use crate::{StyleTypeValue, TypeSet};
+
+    fn style_set() -> TypeSet {
+        let int_type = StyleTypeValue::new(Option::<isize>::None);
+        TypeSet::default()
+            .add_type("x", int_type.clone(), false)
+            .add_type("y", int_type, false)
+    }
+
+    //ft test_read
+    #[test]
+    fn test_read() {
+        let style_set = style_set();
+        let mut stylesheet = Stylesheet::new(&style_set);
+        stylesheet
+            .read_dss(
+                "/* comment */ g.box > pt.a.b, #p1 { x: 1; y : '2' }\n\
+                 pt[y] { x: 3; priority: 2 }\n* {}",
+            )
+            .unwrap();
+        // g.box, pt.a.b, #p1, pt[y] and *
+        assert_eq!(stylesheet.num_rules(), 5);
+    }
+
+    //ft test_variables
+    #[test]
+    fn test_variables() {
+        let style_set = style_set();
+        let mut stylesheet = Stylesheet::new(&style_set);
+        stylesheet
+            .read_dss("$unit = 2;\n$big = $unit * 3;\npt { x: $big + 1; y: $unit }")
+            .unwrap();
+        assert_eq!(stylesheet.variables().get("big"), Some("6"));
+        assert_eq!(stylesheet.num_rules(), 1);
+        let errors = stylesheet
+            .read_dss("$ = 1;\n$a 1;\n$b = $c;\npt { x: $d }")
+            .unwrap_err();
+        let positions: Vec<_> = errors
+            .iter()
+            .map(|e| (e.line, e.column, e.length))
+            .collect();
+        assert_eq!(positions, vec![(1, 1, 2), (2, 1, 4), (3, 6, 2), (4, 9, 2)]);
+    }
+
+    //ft test_errors
+    #[test]
+    fn test_errors() {
+        let style_set = style_set();
+        let mut stylesheet = Stylesheet::new(&style_set);
+        let errors = stylesheet
+            .read_dss("pt { x: 1; z: 2 }\npt > { priority: high }\n  pt { x 1 }\n g { y: 1")
+            .unwrap_err();
+        let positions: Vec<_> = errors
+            .iter()
+            .map(|e| (e.line, e.column, e.length))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![(1, 12, 1), (2, 1, 4), (2, 18, 4), (3, 8, 3), (4, 10, 0)]
+        );
+        let mut s = String::new();
+        errors[1]
+            .fmt_context(&mut s, "pt { x: 1; z: 2 }\npt > { priority: high }")
+            .unwrap();
+        assert_eq!(s, "    2: pt > { priority: high }\n       ^^^^\n");
+    }
+
+    //ft test_errors_non_ascii
+    #[test]
+    fn test_errors_non_ascii() {
+        let style_set = style_set();
+        let mut stylesheet = Stylesheet::new(&style_set);
+        let errors = stylesheet
+            .read_dss("#\u{2192} { }\npt[x\u{2192} { }\n\u{e9}t\u{e9} > \u{2192} { }")
+            .unwrap_err();
+        let positions: Vec<_> = errors
+            .iter()
+            .map(|e| (e.line, e.column, e.length, e.reason.as_str()))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                (1, 1, 2, "expected a name"),
+                (2, 5, 1, "expected ']'"),
+                (3, 7, 1, "unexpected '\u{2192}' in selector"),
+            ]
+        );
+    }
+}
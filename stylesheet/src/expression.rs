@@ -0,0 +1,472 @@
+/*a Copyright
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+
+@file    expression.rs
+@brief   Variables and computed values in style value strings
+ */
+
+//! The string for a style value may use variables and compute
+//! values; such a string is evaluated to a plain string before it is
+//! parsed for the type of the style.
+//!
+//! A string is evaluated only if it contains a variable reference
+//! (`$name`) or a call of one of the color functions; it is then a
+//! list of expressions, separated by commas or whitespace. An
+//! expression may use `+`, `-`, `*`, `/` and parentheses on numbers
+//! (where `20%` is 0.2), and the color functions:
+//!
+//! * `lighten(c, a)`, `darken(c, a)` - change the HSL lightness by a
+//! * `saturate(c, a)`, `desaturate(c, a)` - change the HSL saturation by a
+//! * `mix(c0, c1, t)` - mix from c0 to c1 by t (default 0.5)
+//! * `rgb(r, g, b)` - a color from components 0 to 255
+//! * `hsl(h, s, l)` - a color from hue in degrees, saturation and lightness
+//!
+//! Colors may be given as names or as '#rgb' or '#rrggbb'; anything
+//! else in an expression that is not a number is left as text.
+
+//a Imports
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use vg_rs::{Rgba, COLOR_DB_SVG};
+
+use crate::ValueError;
+
+//a Constants
+/// The names of the functions that may be used in a style value
+const FUNCTIONS: &[&str] = &[
+    "lighten",
+    "darken",
+    "saturate",
+    "desaturate",
+    "mix",
+    "rgb",
+    "hsl",
+];
+
+//fi is_name_char
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+//fi needs_evaluation
+/// Return true if a string uses a variable or a function
+fn needs_evaluation(s: &str) -> bool {
+    if s.contains('$') {
+        return true;
+    }
+    s.match_indices('(').any(|(i, _)| {
+        let start = s[..i]
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !is_name_char(*c))
+            .map(|(n, c)| n + c.len_utf8())
+            .unwrap_or(0);
+        FUNCTIONS.contains(&&s[start..i])
+    })
+}
+
+//a Value
+//ti Value
+/// A value while evaluating an expression
+#[derive(Debug, Clone)]
+enum Value {
+    Number(f64),
+    Text(String),
+}
+
+//ii Value
+impl Value {
+    //fi of_text
+    /// Create a value from the (evaluated) text of a variable
+    fn of_text(s: &str) -> Self {
+        match s.trim().parse::<f64>() {
+            Ok(n) => Self::Number(n),
+            _ => Self::Text(s.to_string()),
+        }
+    }
+
+    //fi of_color
+    fn of_color(rgba: Rgba) -> Self {
+        Self::Text(rgba.into())
+    }
+
+    //mi as_number
+    fn as_number(&self) -> Result<f64, ValueError> {
+        match self {
+            Self::Number(n) => Ok(*n),
+            Self::Text(s) => Err(ValueError::bad_value(format!(
+                "expected a number but got '{}'",
+                s
+            ))),
+        }
+    }
+
+    //mi as_color
+    fn as_color(&self) -> Result<Rgba, ValueError> {
+        let color = match self {
+            Self::Text(s) => COLOR_DB_SVG
+                .find_color(s)
+                .map(|c| *c.rgba())
+                .or_else(|| Rgba::of_str(s)),
+            _ => None,
+        };
+        color.ok_or_else(|| ValueError::bad_value(format!("expected a color but got '{}'", self)))
+    }
+}
+
+//ip Display for Value
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Text(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+//a Parser
+//ti Parser
+/// A recursive-descent evaluator of the expressions in a string
+struct Parser<'a> {
+    variables: &'a Variables,
+    text: &'a str,
+    pos: usize,
+}
+
+//ii Parser
+impl<'a> Parser<'a> {
+    //mi error
+    fn error(&self, reason: &str) -> ValueError {
+        ValueError::bad_value(format!(
+            "{} at '{}' in '{}'",
+            reason,
+            &self.text[self.pos..],
+            self.text
+        ))
+    }
+
+    //mi peek
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    //mi skip_space
+    /// Skip whitespace, returning true if there was any
+    fn skip_space(&mut self) -> bool {
+        let rest = &self.text[self.pos..];
+        let n = rest.len() - rest.trim_start().len();
+        self.pos += n;
+        n > 0
+    }
+
+    //mi take_while
+    fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> &'a str {
+        let start = self.pos;
+        let rest = &self.text[start..];
+        self.pos += rest.find(|c| !f(c)).unwrap_or(rest.len());
+        &self.text[start..self.pos]
+    }
+
+    //mi list
+    /// Evaluate the whole text as a list of expressions, keeping the
+    /// separators
+    fn list(&mut self) -> Result<String, ValueError> {
+        let mut result = String::new();
+        self.skip_space();
+        while self.pos < self.text.len() {
+            let value = self.sum()?;
+            result.push_str(&value.to_string());
+            let spaced = self.skip_space();
+            match self.peek() {
+                None => break,
+                Some(',') => {
+                    self.pos += 1;
+                    self.skip_space();
+                    result.push_str(", ");
+                }
+                Some(_) if spaced => {
+                    result.push(' ');
+                }
+                Some(_) => {
+                    return Err(self.error("expected ','"));
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    //mi sum
+    fn sum(&mut self) -> Result<Value, ValueError> {
+        let mut value = self.product()?;
+        loop {
+            let pos = self.pos;
+            self.skip_space();
+            let op = self.peek();
+            if op != Some('+') && op != Some('-') {
+                self.pos = pos;
+                return Ok(value);
+            }
+            self.pos += 1;
+            self.skip_space();
+            let lhs = value.as_number()?;
+            let rhs = self.product()?.as_number()?;
+            value = Value::Number(if op == Some('+') {
+                lhs + rhs
+            } else {
+                lhs - rhs
+            });
+        }
+    }
+
+    //mi product
+    fn product(&mut self) -> Result<Value, ValueError> {
+        let mut value = self.unary()?;
+        loop {
+            let pos = self.pos;
+            self.skip_space();
+            let op = self.peek();
+            if op != Some('*') && op != Some('/') {
+                self.pos = pos;
+                return Ok(value);
+            }
+            self.pos += 1;
+            self.skip_space();
+            let lhs = value.as_number()?;
+            let rhs = self.unary()?.as_number()?;
+            value = Value::Number(if op == Some('*') {
+                lhs * rhs
+            } else {
+                lhs / rhs
+            });
+        }
+    }
+
+    //mi unary
+    fn unary(&mut self) -> Result<Value, ValueError> {
+        if self.peek() == Some('-') {
+            self.pos += 1;
+            Ok(Value::Number(-self.unary()?.as_number()?))
+        } else {
+            self.primary()
+        }
+    }
+
+    //mi primary
+    fn primary(&mut self) -> Result<Value, ValueError> {
+        match self.peek() {
+            None => Err(self.error("expected a value")),
+            Some('(') => {
+                self.pos += 1;
+                self.skip_space();
+                let value = self.sum()?;
+                self.skip_space();
+                if self.peek() != Some(')') {
+                    return Err(self.error("expected ')'"));
+                }
+                self.pos += 1;
+                Ok(value)
+            }
+            Some('$') => {
+                self.pos += 1;
+                let name = self.take_while(is_name_char);
+                match self.variables.get(name) {
+                    Some(v) => Ok(Value::of_text(v)),
+                    None => Err(ValueError::bad_value(format!(
+                        "unknown variable '${}'",
+                        name
+                    ))),
+                }
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let number = self.take_while(|c| c.is_ascii_digit() || c == '.');
+                let mut n = number
+                    .parse::<f64>()
+                    .map_err(|_| self.error("bad number"))?;
+                if self.peek() == Some('%') {
+                    self.pos += 1;
+                    n /= 100.;
+                }
+                Ok(Value::Number(n))
+            }
+            Some(_) => {
+                let word = self.take_while(|c| !c.is_whitespace() && !",()".contains(c));
+                if word.is_empty() {
+                    Err(self.error("unexpected character"))
+                } else if self.peek() == Some('(') {
+                    self.call(word)
+                } else {
+                    Ok(Value::Text(word.to_string()))
+                }
+            }
+        }
+    }
+
+    //mi call
+    /// Evaluate a function call, with the position at its '('
+    fn call(&mut self, name: &str) -> Result<Value, ValueError> {
+        self.pos += 1;
+        let mut args = Vec::new();
+        loop {
+            self.skip_space();
+            args.push(self.sum()?);
+            self.skip_space();
+            match self.peek() {
+                Some(',') => {
+                    self.pos += 1;
+                }
+                Some(')') => {
+                    self.pos += 1;
+                    break;
+                }
+                _ => {
+                    return Err(self.error("expected ',' or ')'"));
+                }
+            }
+        }
+        let (min_args, max_args) = match name {
+            "mix" => (2, 3),
+            "rgb" | "hsl" => (3, 3),
+            _ => (2, 2),
+        };
+        if args.len() < min_args || args.len() > max_args {
+            return Err(ValueError::bad_value(format!(
+                "wrong number of arguments to function '{}'",
+                name
+            )));
+        }
+        let hsl_by = |args: &[Value], dl: f64, ds: f64| -> Result<Value, ValueError> {
+            let (h, s, l) = args[0].as_color()?.as_hsl();
+            let by = args[1].as_number()? as f32;
+            let s = (s + by * ds as f32).clamp(0., 1.);
+            let l = (l + by * dl as f32).clamp(0., 1.);
+            Ok(Value::of_color(Rgba::of_hsl(h, s, l)))
+        };
+        match name {
+            "lighten" => hsl_by(&args, 1., 0.),
+            "darken" => hsl_by(&args, -1., 0.),
+            "saturate" => hsl_by(&args, 0., 1.),
+            "desaturate" => hsl_by(&args, 0., -1.),
+            "mix" => {
+                let (r0, g0, b0) = args[0].as_color()?.as_tuple_rgb_f32();
+                let (r1, g1, b1) = args[1].as_color()?.as_tuple_rgb_f32();
+                let t = args.get(2).map(|t| t.as_number()).unwrap_or(Ok(0.5))? as f32;
+                let mix = |c0: f32, c1: f32| c0 + (c1 - c0) * t;
+                Ok(Value::of_color(
+                    (mix(r0, r1), mix(g0, g1), mix(b0, b1)).into(),
+                ))
+            }
+            "rgb" => {
+                let mut rgb = [0.; 3];
+                for (c, a) in rgb.iter_mut().zip(args.iter()) {
+                    *c = (a.as_number()? / 255.).clamp(0., 1.) as f32;
+                }
+                Ok(Value::of_color((rgb[0], rgb[1], rgb[2]).into()))
+            }
+            "hsl" => {
+                let h = args[0].as_number()? as f32;
+                let s = args[1].as_number()?.clamp(0., 1.) as f32;
+                let l = args[2].as_number()?.clamp(0., 1.) as f32;
+                Ok(Value::of_color(Rgba::of_hsl(h, s, l)))
+            }
+            _ => Err(ValueError::bad_value(format!(
+                "unknown function '{}'",
+                name
+            ))),
+        }
+    }
+}
+
+//a Variables
+//tp Variables
+/// A set of named variables that may be used in the strings of style
+/// values, each holding the evaluated text of its value
+#[derive(Debug, Default, Clone)]
+pub struct Variables {
+    values: HashMap<String, String>,
+}
+
+//ip Variables
+impl Variables {
+    //mp set
+    /// Set a variable (named without its '$') to the evaluation of a
+    /// string; variables used in the string must already be set
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), ValueError> {
+        let value = self.evaluate(value)?.into_owned();
+        self.values.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    //ap get
+    /// Get the text of a variable, if it is set
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|s| s.as_str())
+    }
+
+    //mp iter
+    /// Iterate over the names and (evaluated) text of the variables,
+    /// in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+
+    //mp evaluate
+    /// Evaluate the variables and functions in a string, returning
+    /// the plain string (borrowed if there is nothing to evaluate)
+    pub fn evaluate<'s>(&self, s: &'s str) -> Result<Cow<'s, str>, ValueError> {
+        if !needs_evaluation(s) {
+            return Ok(Cow::Borrowed(s));
+        }
+        let mut parser = Parser {
+            variables: self,
+            text: s,
+            pos: 0,
+        };
+        Ok(Cow::Owned(parser.list()?))
+    }
+}
+
+//a Test
+#[cfg(test)]
+mod test_expression {
+    use super::*;
+
+    //ft test_evaluate
+    #[test]
+    fn test_evaluate() {
+        let mut vars = Variables::default();
+        vars.set("unit", "2").unwrap();
+        vars.set("accent", "#3366cc").unwrap();
+        vars.set("pad", "$unit * 1.5").unwrap();
+        assert_eq!(vars.get("pad"), Some("3"));
+        assert_eq!(vars.evaluate("1, 2 (3)").unwrap(), "1, 2 (3)");
+        assert_eq!(vars.evaluate("$unit * 2").unwrap(), "4");
+        assert_eq!(vars.evaluate("$unit, -$pad").unwrap(), "2, -3");
+        assert_eq!(vars.evaluate("$unit $unit+1").unwrap(), "2 3");
+        assert_eq!(vars.evaluate("($unit + 1) * 2").unwrap(), "6");
+        assert_eq!(vars.evaluate("$accent").unwrap(), "#3366cc");
+        assert_eq!(vars.evaluate("mix(black, white)").unwrap(), "#7f7f7f");
+        assert_eq!(vars.evaluate("mix(red, $accent, 0%)").unwrap(), "#ff0000");
+        assert_eq!(vars.evaluate("rgb(255, 128, 0)").unwrap(), "#ff8000");
+        assert_eq!(vars.evaluate("hsl(120, 1, 0.5)").unwrap(), "#00ff00");
+        assert_eq!(vars.evaluate("lighten(#000, 50%)").unwrap(), "#7f7f7f");
+        assert_eq!(vars.evaluate("darken(white, 100%)").unwrap(), "#000000");
+        assert_eq!(vars.evaluate("desaturate(red, 1)").unwrap(), "#7f7f7f");
+        assert!(vars.evaluate("$unknown").is_err());
+        assert!(vars.evaluate("$accent * 2").is_err());
+        assert!(vars.evaluate("lighten(fred, 1)").is_err());
+        assert!(vars.evaluate("mix(red)").is_err());
+        assert!(vars.evaluate("rgb(1, 2, 3").is_err());
+    }
+}
@@ -29,14 +29,18 @@ extern crate regex;
 #[macro_use]
 extern crate lazy_static;
 
+mod dss;
 mod error;
+mod expression;
 mod style;
 mod stylesheet;
 mod tree_rules;
 mod type_values;
 mod utils;
 
+pub use dss::DssError;
 pub use error::ValueError;
+pub use expression::Variables;
 
 pub use type_values::{StyleTypeValue, TypeValue};
 
@@ -44,7 +48,7 @@ pub use style::Descriptor;
 pub use style::TypeSet;
 pub use style::{StylableNode, StylableNodeAction, StylableNodeRule};
 
-pub use tree_rules::{Action, RuleFn, RuleResult, RuleSet};
+pub use tree_rules::{Action, RuleFn, RuleResult, RuleSet, Specificity};
 pub use tree_rules::{BitMask, BitMaskU32, BitMaskU64};
 pub use tree_rules::{Tree, TreeIterOp};
 pub use tree_rules::{TreeApplicator32, TreeApplicator64, TreeApplicatorX};
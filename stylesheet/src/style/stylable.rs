@@ -17,8 +17,8 @@ limitations under the License.
  */
 
 //a Imports
-use crate::{Action, RuleFn, RuleResult};
-use crate::{Descriptor, StyleTypeValue, ValueError};
+use crate::{Action, RuleFn, RuleResult, Specificity};
+use crate::{Descriptor, StyleTypeValue, ValueError, Variables};
 
 //tp StylableNode
 /// A `StylableNode` is an element that is part of a hierarchy of elements, which
@@ -61,6 +61,13 @@ pub struct StylableNode<'desc> {
     /// the descriptor; it contains bool and value, the bool
     /// indicating whether it is set by the node or not
     values: Vec<(bool, StyleTypeValue)>,
+    /// `inherit` contains the names of styles that are to be
+    /// inherited from the parent of the node, even if they are not
+    /// inheritable by default
+    inherit: Vec<String>,
+    /// `applied_rules` contains the indices of the rules of a
+    /// stylesheet whose actions have been applied to the node
+    applied_rules: Vec<usize>,
 }
 
 //ip StylableNode
@@ -84,6 +91,8 @@ impl<'desc> StylableNode<'desc> {
             id_name,
             node_type: node_type.to_string(),
             classes,
+            inherit: Vec::new(),
+            applied_rules: Vec::new(),
         }
     }
 
@@ -104,6 +113,8 @@ impl<'desc> StylableNode<'desc> {
             id_name,
             node_type: self.node_type.clone(),
             classes,
+            inherit: self.inherit.clone(),
+            applied_rules: Vec::new(),
         }
     }
 
@@ -117,7 +128,32 @@ impl<'desc> StylableNode<'desc> {
 
     //mp add_name_value
     pub fn add_name_value(&mut self, name: &str, value: &str) -> Result<(), ValueError> {
-        if name == "id" {
+        self.add_name_value_with(name, value, &Variables::default())
+    }
+
+    //mp add_name_value_with
+    /// Add a name and value, evaluating the value using a set of variables
+    ///
+    /// The value 'inherit' requires the style to be inherited from
+    /// the parent of the node; the value 'initial' sets the style to
+    /// its default, so that it is not inherited
+    pub fn add_name_value_with(
+        &mut self,
+        name: &str,
+        value: &str,
+        variables: &Variables,
+    ) -> Result<(), ValueError> {
+        if (value == "inherit" || value == "initial")
+            && self.descriptor.style_set.borrow_type(name).is_some()
+        {
+            if value == "inherit" {
+                self.set_inherit(name);
+            } else {
+                let (v, _) = self.descriptor.style_set.borrow_type(name).unwrap();
+                self.set_style_value(name, v.new_value());
+            }
+            Ok(())
+        } else if name == "id" {
             self.id_name = Some(value.to_string());
             Ok(())
         } else if name == "class" {
@@ -126,12 +162,12 @@ impl<'desc> StylableNode<'desc> {
             }
             Ok(())
         } else if let Some(n) = self.descriptor.find_style_index(name) {
-            self.values[n].1.from_string(value)?;
+            self.values[n].1.from_string_with(value, variables)?;
             self.values[n].0 = true;
             Ok(())
         } else if let Some((v, _inheritable)) = self.descriptor.style_set.borrow_type(name) {
             let mut v = v.new_value();
-            v.from_string(value)?;
+            v.from_string_with(value, variables)?;
             self.extra_sids.push((name.to_string(), v));
             Ok(())
         } else {
@@ -142,6 +178,12 @@ impl<'desc> StylableNode<'desc> {
         }
     }
 
+    //mp node_type
+    /// Get the type of the node, such as 'rect'
+    pub fn node_type(&self) -> &str {
+        &self.node_type
+    }
+
     //mp find_style_index -- was find_sid_index(_exn)
     pub fn find_style_index(&self, s: &str) -> Option<usize> {
         // println!("Find style index {} {}",s,self.values.len());
@@ -183,6 +225,71 @@ impl<'desc> StylableNode<'desc> {
         }
     }
 
+    //mp set_style_value
+    /// Set the value of a style for the node; if the node's
+    /// descriptor does not have the style then it is kept so that it
+    /// may be inherited by the node's children
+    pub fn set_style_value(&mut self, name: &str, value: StyleTypeValue) {
+        self.inherit.retain(|s| s != name);
+        if let Some(n) = self.descriptor.find_style_index(name) {
+            self.values[n] = (true, value);
+        } else if let Some(n) = self.extra_sids.iter().position(|(s, _)| s == name) {
+            self.extra_sids[n].1 = value;
+        } else {
+            self.extra_sids.push((name.to_string(), value));
+        }
+    }
+
+    //mp set_inherit
+    /// Require a style to be inherited from the parent of the node,
+    /// removing any value set for it by the node
+    pub fn set_inherit(&mut self, name: &str) {
+        if let Some(n) = self.descriptor.find_style_index(name) {
+            self.values[n] = (false, self.descriptor.styles[n].1.new_value());
+        }
+        self.extra_sids.retain(|(s, _)| s != name);
+        if !self.inherit.iter().any(|s| s == name) {
+            self.inherit.push(name.to_string());
+        }
+    }
+
+    //mp inherit_from
+    /// Inherit style values from the parent of the node (which must
+    /// already have inherited from its own parent)
+    ///
+    /// Every style that is set by the parent (or that it has
+    /// inherited) which is inheritable - by default, or because the
+    /// node requires it - is inherited if the node does not set it
+    pub fn inherit_from(&mut self, parent: &Self) {
+        let parent_values = parent
+            .descriptor
+            .styles
+            .iter()
+            .zip(parent.values.iter())
+            .filter(|(_, (set, _))| *set)
+            .map(|((name, _, _), (_, value))| (name, value))
+            .chain(parent.extra_sids.iter().map(|(name, value)| (name, value)));
+        for (name, value) in parent_values {
+            let inheritable = self.inherit.iter().any(|s| s == name)
+                || self
+                    .descriptor
+                    .style_set
+                    .borrow_type(name)
+                    .map(|(_, inheritable)| inheritable)
+                    .unwrap_or(false);
+            if !inheritable {
+                continue;
+            }
+            if let Some(n) = self.descriptor.find_style_index(name) {
+                if !self.values[n].0 {
+                    self.values[n] = (true, value.clone());
+                }
+            } else if !self.extra_sids.iter().any(|(s, _)| s == name) {
+                self.extra_sids.push((name.clone(), value.clone()));
+            }
+        }
+    }
+
     //mp has_id
     pub fn has_id(&self, s: &str) -> bool {
         match &self.id_name {
@@ -191,6 +298,37 @@ impl<'desc> StylableNode<'desc> {
         }
     }
 
+    //mp is_type
+    /// Return true if the node is of a node type
+    pub fn is_type(&self, s: &str) -> bool {
+        self.node_type == s
+    }
+
+    //mp is_node_style
+    /// Return true if a style is one of the node's own styles (from
+    /// its descriptor), rather than an extra style that it can only
+    /// pass on to its children
+    pub fn is_node_style(&self, s: &str) -> bool {
+        self.descriptor.find_style_index(s).is_some()
+    }
+
+    //mp applied_rules
+    /// Borrow the indices of the rules whose actions have been
+    /// applied to the node
+    pub fn applied_rules(&self) -> &[usize] {
+        &self.applied_rules
+    }
+
+    //mp has_style
+    /// Return true if the node has a value set for a style, rather
+    /// than its default
+    pub fn has_style(&self, s: &str) -> bool {
+        match self.descriptor.find_style_index(s) {
+            Some(n) => self.values[n].0,
+            None => self.extra_sids.iter().any(|(sn, _)| sn == s),
+        }
+    }
+
     //mp has_class
     pub fn has_class(&self, s: &str) -> bool {
         for c in &self.classes {
@@ -251,29 +389,74 @@ impl<'desc> StylableNode<'desc> {
 #[derive(Debug)]
 pub struct StylableNodeAction {
     values: Vec<(String, StyleTypeValue)>,
+    inherit: Vec<String>,
+    /// The names and (evaluated) value strings the action was
+    /// created from, so that it may be written out again
+    source: Vec<(String, String)>,
 }
 impl StylableNodeAction {
     pub fn new(values: Vec<(String, StyleTypeValue)>) -> Self {
-        Self { values }
+        let inherit = Vec::new();
+        let source = Vec::new();
+        Self {
+            values,
+            inherit,
+            source,
+        }
+    }
+    /// Require the nodes the action is applied to to inherit a style
+    /// from their parents
+    pub fn inherit(mut self, name: &str) -> Self {
+        self.inherit.push(name.to_string());
+        self
+    }
+    /// Record a name and value string that the action was created
+    /// from
+    pub fn source(mut self, name: &str, value: &str) -> Self {
+        self.source.push((name.to_string(), value.to_string()));
+        self
+    }
+    /// Borrow the names and value strings that the action was
+    /// created from, in the order they were given
+    pub fn borrow_source(&self) -> &[(String, String)] {
+        &self.source
     }
 }
 impl<'desc> Action<StylableNode<'desc>> for StylableNodeAction {
-    fn apply(&self, _rule: usize, _depth: usize, value: &mut StylableNode<'desc>) {
+    fn apply(&self, rule: usize, _depth: usize, value: &mut StylableNode<'desc>) {
+        if !value.applied_rules.contains(&rule) {
+            value.applied_rules.push(rule);
+        }
         for (n, v) in &self.values {
-            if let Some(x) = value.borrow_mut_style_value_of_name(n) {
-                *x = v.clone();
-            }
+            value.set_style_value(n, v.clone());
+        }
+        for n in &self.inherit {
+            value.set_inherit(n);
         }
     }
 }
 
 //a StylableNodeRule
+//tp StylableNodeRule
+/// A rule that matches stylable nodes; the selector of the rule
+/// requires *all* of its conditions to hold - the node type, the id,
+/// each of the classes, and the presence of each of the styles - and a
+/// rule with no conditions matches every node
+///
+/// A rule that is a child of another rule is applied to the
+/// descendants of the nodes that the parent matches (or, if the
+/// parent is 'sideways', to those nodes themselves); if it is a
+/// 'child' rule then it is applied only to their children
 #[derive(Debug, Default)]
 pub struct StylableNodeRule {
+    node_type: Option<String>,
     id_matches: Option<String>,
-    has_class: Option<String>,
+    classes: Vec<String>,
+    has_styles: Vec<String>,
     sideways: bool,
+    child: bool,
     propagate_depth: usize,
+    priority: isize,
 }
 impl StylableNodeRule {
     pub fn new() -> Self {
@@ -283,36 +466,102 @@ impl StylableNodeRule {
         self.sideways = sideways;
         self
     }
+    /// Set the rule to apply only to the children of the nodes its
+    /// parent rule matches, rather than to all their descendants
+    pub fn child(mut self, child: bool) -> Self {
+        self.child = child;
+        self
+    }
     pub fn max_depth(mut self, depth: usize) -> Self {
         self.propagate_depth = depth;
         self
     }
+    /// Require the node to be of a node type
+    pub fn has_type(mut self, s: &str) -> Self {
+        self.node_type = Some(s.to_string());
+        self
+    }
     pub fn has_id(mut self, s: &str) -> Self {
         self.id_matches = Some(s.to_string());
         self
     }
+    /// Require the node to be in a class; this may be used more than
+    /// once to require many classes
     pub fn has_class(mut self, s: &str) -> Self {
-        self.has_class = Some(s.to_string());
+        self.classes.push(s.to_string());
         self
     }
+    /// Require the node to have a value set for a style; this may be
+    /// used more than once to require many styles
+    pub fn has_style(mut self, s: &str) -> Self {
+        self.has_styles.push(s.to_string());
+        self
+    }
+    /// Set the priority of the rule, which takes precedence over its
+    /// specificity; the default priority is 0
+    pub fn priority(mut self, priority: isize) -> Self {
+        self.priority = priority;
+        self
+    }
+    /// Get the node type the rule requires, if any
+    pub fn node_type(&self) -> Option<&str> {
+        self.node_type.as_deref()
+    }
+    /// Get the id the rule requires, if any
+    pub fn id_matches(&self) -> Option<&str> {
+        self.id_matches.as_deref()
+    }
+    /// Borrow the classes the rule requires
+    pub fn classes(&self) -> &[String] {
+        &self.classes
+    }
+    /// Borrow the names of the styles the rule requires values for
+    pub fn has_styles(&self) -> &[String] {
+        &self.has_styles
+    }
+    /// Return true if the rule applies only to children
+    pub fn is_child(&self) -> bool {
+        self.child
+    }
+    /// Get the maximum depth of the rule, or 0 if it is unlimited
+    pub fn get_max_depth(&self) -> usize {
+        self.propagate_depth
+    }
+    /// Get the priority of the rule
+    pub fn get_priority(&self) -> isize {
+        self.priority
+    }
+    /// Return true if the node satisfies all the conditions of the rule
+    pub fn matches(&self, value: &StylableNode) -> bool {
+        self.node_type.as_ref().is_none_or(|t| value.is_type(t))
+            && self.id_matches.as_ref().is_none_or(|s| value.has_id(s))
+            && self.classes.iter().all(|c| value.has_class(c))
+            && self.has_styles.iter().all(|s| value.has_style(s))
+    }
 }
 impl<'desc> RuleFn<StylableNode<'desc>> for StylableNodeRule {
     fn apply(&self, depth: usize, value: &StylableNode<'desc>) -> RuleResult {
-        let matched = {
-            if let Some(s) = self.id_matches.as_ref() {
-                value.has_id(s)
-            } else {
-                false
-            }
-        };
-        let matched = matched || {
-            if let Some(s) = self.has_class.as_ref() {
-                value.has_class(s)
+        let matched = self.matches(value);
+        // A child rule is tried on just the one level of the tree
+        let max_depth = {
+            if self.child && (self.propagate_depth == 0 || depth < self.propagate_depth) {
+                depth
             } else {
-                false
+                self.propagate_depth
             }
         };
-        RuleResult::new(depth, matched, self.sideways, self.propagate_depth)
+        RuleResult::new(depth, matched, self.sideways, max_depth)
+    }
+    fn priority(&self) -> isize {
+        self.priority
+    }
+    fn specificity(&self) -> Specificity {
+        Specificity {
+            ids: self.id_matches.is_some() as usize,
+            classes: self.classes.len() + self.has_styles.len(),
+            types: self.node_type.is_some() as usize,
+            depth: 0,
+        }
     }
 }
 
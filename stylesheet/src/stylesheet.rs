@@ -19,8 +19,8 @@ limitations under the License.
 //a Imports
 use crate::{RuleSet, Tree};
 use crate::{StylableNode, StylableNodeAction, StylableNodeRule};
+use crate::{StyleTypeValue, TypeSet, ValueError, Variables};
 use crate::{TreeApplicator32, TreeApplicator64, TreeApplicatorX};
-use crate::{TypeSet, ValueError};
 use std::collections::HashMap;
 
 //a Constants for debug
@@ -29,10 +29,18 @@ const DEBUG_STYLESHEETTREE: bool = 1 == 0;
 //a Stylesheet
 //tp Stylesheet
 /// The Stylesheet
+///
+/// A rule may refer to a style by its id rather than by action; such
+/// references are resolved by `link`, so the style may be added
+/// before or after the rule
 pub struct Stylesheet<'a> {
     style_set: &'a TypeSet,
     rules: RuleSet<StylableNode<'a>, StylableNodeAction, StylableNodeRule>,
     style_of_id: HashMap<String, usize>,
+    /// The id of the style that each rule which refers to a style by
+    /// id uses
+    style_of_rule: HashMap<usize, String>,
+    variables: Variables,
 }
 
 impl<'a> Stylesheet<'a> {
@@ -41,6 +49,8 @@ impl<'a> Stylesheet<'a> {
             style_set,
             rules: RuleSet::new(),
             style_of_id: HashMap::new(),
+            style_of_rule: HashMap::new(),
+            variables: Variables::default(),
         }
     }
     //mp add_action
@@ -67,21 +77,112 @@ impl<'a> Stylesheet<'a> {
     ) -> Result<usize, ValueError> {
         let mut id = None;
         let mut styling = Vec::new();
+        let mut inherit = Vec::new();
+        let mut source = Vec::new();
         for (name, value) in name_values {
             if name == "id" {
                 id = Some(value);
-            } else if let Some((value_type, _)) = self.style_set.borrow_type(&name) {
-                let mut v = value_type.new_value();
-                v.from_string(value)?;
-                styling.push(((*name).into(), v));
+            } else if value == "inherit" && self.has_style(&name) {
+                source.push((name.clone(), value.to_string()));
+                inherit.push(name);
             } else {
-                return Err(ValueError::bad_value(format!(
-                    "unknown style name {} with value {}",
-                    name, value
-                )));
+                let v = self.style_value(&name, value)?;
+                source.push((name.clone(), self.variables.evaluate(value)?.into_owned()));
+                styling.push((name, v));
             }
         }
-        Ok(self.add_action(id, StylableNodeAction::new(styling)))
+        let mut action = StylableNodeAction::new(styling);
+        for name in &inherit {
+            action = action.inherit(name);
+        }
+        for (name, value) in &source {
+            action = action.source(name, value);
+        }
+        Ok(self.add_action(id, action))
+    }
+
+    //mp set_variable
+    /// Set a variable that may be used in the values of styles added
+    /// after it
+    pub fn set_variable(&mut self, name: &str, value: &str) -> Result<(), ValueError> {
+        self.variables.set(name, value)
+    }
+
+    //ap variables
+    /// Borrow the variables of the stylesheet
+    pub fn variables(&self) -> &Variables {
+        &self.variables
+    }
+
+    //mp num_actions
+    /// Get the number of actions (styles) in the stylesheet
+    pub fn num_actions(&self) -> usize {
+        self.rules.num_actions()
+    }
+
+    //mp borrow_action
+    /// Borrow an action of the stylesheet by its index
+    pub fn borrow_action(&self, action: usize) -> &StylableNodeAction {
+        self.rules.borrow_action(action)
+    }
+
+    //mp id_of_action
+    /// Get the id of an action, if it was added with one
+    pub fn id_of_action(&self, action: usize) -> Option<&str> {
+        self.style_of_id
+            .iter()
+            .find(|(_, a)| **a == action)
+            .map(|(id, _)| id.as_str())
+    }
+
+    //mp borrow_rule
+    /// Borrow a rule of the stylesheet by its index, and get its
+    /// action (if any)
+    pub fn borrow_rule(&self, rule: usize) -> (&StylableNodeRule, Option<usize>) {
+        (self.rules.borrow_rule_fn(rule), self.rules.action(rule))
+    }
+
+    //mp is_toplevel_rule
+    /// Return true if a rule is not the child of another rule
+    pub fn is_toplevel_rule(&self, rule: usize) -> bool {
+        self.rules.is_toplevel(rule)
+    }
+
+    //mp iter_rule_children
+    /// Iterate over the indices of the child rules of a rule
+    pub fn iter_rule_children(&self, rule: usize) -> impl Iterator<Item = usize> + '_ {
+        self.rules.iter_children(rule).copied()
+    }
+
+    //mp has_style
+    /// Return true if the style set has a style of a name
+    pub fn has_style(&self, name: &str) -> bool {
+        self.style_set.borrow_type(name).is_some()
+    }
+
+    //mp num_rules
+    /// Get the number of rules in the stylesheet
+    pub fn num_rules(&self) -> usize {
+        self.rules.num_rules()
+    }
+
+    //mp style_value
+    /// Get the value of a style from a string, if the style is in the
+    /// style set and the string is valid for its type; the string
+    /// 'initial' is the default value of the type
+    pub fn style_value(&self, name: &str, value: &str) -> Result<StyleTypeValue, ValueError> {
+        if let Some((value_type, _)) = self.style_set.borrow_type(name) {
+            let mut v = value_type.new_value();
+            if value != "initial" {
+                v.from_string_with(value, &self.variables)?;
+            }
+            Ok(v)
+        } else {
+            Err(ValueError::bad_value(format!(
+                "unknown style name {} with value {}",
+                name, value
+            )))
+        }
     }
 
     //mp get_action_index
@@ -104,6 +205,66 @@ impl<'a> Stylesheet<'a> {
         self.rules.add_rule(parent, rule, action)
     }
 
+    //mp add_rule_of_style
+    /// Add a rule to the set that uses the style with an id, and
+    /// return its handle, as for `add_rule`
+    ///
+    /// The style need not have been added yet; the rule has no
+    /// action until `link` is invoked
+    pub fn add_rule_of_style(
+        &mut self,
+        parent: Option<usize>,
+        rule: StylableNodeRule,
+        style: &str,
+    ) -> usize {
+        let rule = self.rules.add_rule(parent, rule, None);
+        self.style_of_rule.insert(rule, style.to_string());
+        rule
+    }
+
+    //mp style_of_rule
+    /// Get the id of the style that a rule uses, if it was added
+    /// with `add_rule_of_style`
+    pub fn style_of_rule(&self, rule: usize) -> Option<&str> {
+        self.style_of_rule.get(&rule).map(|s| s.as_str())
+    }
+
+    //mp link
+    /// Resolve the style ids that rules use to the styles of the
+    /// stylesheet; this must be invoked after all the styles and
+    /// rules have been added, and before the rules are applied
+    ///
+    /// It may be invoked more than once; if any style id is not
+    /// known then the rules that use them are returned, in order,
+    /// and those rules have no action
+    pub fn link(&mut self) -> Result<(), Vec<usize>> {
+        let mut unresolved = Vec::new();
+        for (rule, style) in self.style_of_rule.iter() {
+            let action = self.style_of_id.get(style).copied();
+            if action.is_none() {
+                unresolved.push(*rule);
+            }
+            self.rules.set_action(*rule, action);
+        }
+        if unresolved.is_empty() {
+            Ok(())
+        } else {
+            unresolved.sort();
+            Err(unresolved)
+        }
+    }
+
+    //mp apply_inheritance_to_tree
+    /// Inherit style values down the tree, after the rules have been
+    /// applied
+    pub fn apply_inheritance_to_tree(&self, tree: &mut Tree<StylableNode<'a>>) {
+        for n in 1..tree.num_nodes() {
+            if let Some((parent, node)) = tree.borrow_parent_and_mut(n) {
+                node.inherit_from(parent);
+            }
+        }
+    }
+
     //mp apply_rule
     pub fn apply_rules_to_tree(&self, tree: &mut Tree<StylableNode<'a>>) {
         let num_rules = self.rules.num_rules();
@@ -132,7 +293,7 @@ impl<'a> Stylesheet<'a> {
 #[cfg(test)]
 mod test_stylesheet {
     use super::*;
-    use crate::{Descriptor, StyleTypeValue, TypeSet};
+    use crate::{Descriptor, TypeSet};
     struct Element<'a> {
         pub stylable: StylableNode<'a>,
         pub children: Vec<Element<'a>>,
@@ -156,6 +317,7 @@ mod test_stylesheet {
                 for c in self.children.iter_mut() {
                     tree = c.add_to_tree(tree);
                 }
+                tree.close_container();
             } else {
                 tree.add_node(&mut self.stylable);
             }
@@ -219,4 +381,241 @@ mod test_stylesheet {
         // FIXME make this a useful test
         // assert!(false);
     }
+
+    //fi styled_x
+    /// Apply a stylesheet to a group with three children, with the
+    /// given id and classes, and return the style 'x' of the children
+    fn styled_x(stylesheet: &Stylesheet, d_pt: &Descriptor, d_g: &Descriptor) -> Vec<isize> {
+        let mut group = StylableNode::new("g", d_g);
+        group.add_name_value("class", "grp").unwrap();
+        let mut group = Element::new(group);
+        for (id, class) in [("pt0", "a b"), ("pt1", "a"), ("pt2", "b")] {
+            let mut node = StylableNode::new("pt", d_pt);
+            node.add_name_value("id", id).unwrap();
+            node.add_name_value("class", class).unwrap();
+            node.add_name_value("x", "0").unwrap();
+            group.add_child(Element::new(node));
+        }
+        {
+            let mut tree = group.create_tree();
+            stylesheet.apply_rules_to_tree(&mut tree);
+        }
+        group
+            .children
+            .iter()
+            .map(|c| {
+                c.stylable
+                    .get_style_value_of_name("x")
+                    .and_then(|v| v.as_isize())
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    //ft test_precedence
+    #[test]
+    fn test_precedence() {
+        let int_type = StyleTypeValue::new(Option::<isize>::None);
+        let style_set = TypeSet::default().add_type("x", int_type.clone(), false);
+        let mut d_pt = Descriptor::new(&style_set);
+        d_pt.add_style("x");
+        let d_g = Descriptor::new(&style_set);
+        let set_x = |stylesheet: &mut Stylesheet, x: &str| {
+            let mut nv = [("x".to_string(), x)].into_iter();
+            stylesheet.add_action_from_name_values(&mut nv).unwrap()
+        };
+
+        // Of two class rules the last wins; an id rule beats a class rule
+        let mut stylesheet = Stylesheet::new(&style_set);
+        let (a1, a2, a3) = (
+            set_x(&mut stylesheet, "1"),
+            set_x(&mut stylesheet, "2"),
+            set_x(&mut stylesheet, "3"),
+        );
+        stylesheet.add_rule(None, StylableNodeRule::new().has_id("pt1"), Some(a3));
+        stylesheet.add_rule(None, StylableNodeRule::new().has_class("a"), Some(a1));
+        stylesheet.add_rule(None, StylableNodeRule::new().has_class("b"), Some(a2));
+        assert_eq!(styled_x(&stylesheet, &d_pt, &d_g), vec![2, 3, 2]);
+
+        // An explicit priority beats specificity and order
+        let mut stylesheet = Stylesheet::new(&style_set);
+        let (a1, a2, a3) = (
+            set_x(&mut stylesheet, "1"),
+            set_x(&mut stylesheet, "2"),
+            set_x(&mut stylesheet, "3"),
+        );
+        stylesheet.add_rule(None, StylableNodeRule::new().has_id("pt1"), Some(a3));
+        stylesheet.add_rule(
+            None,
+            StylableNodeRule::new().has_class("a").priority(1),
+            Some(a1),
+        );
+        stylesheet.add_rule(None, StylableNodeRule::new().has_class("b"), Some(a2));
+        assert_eq!(styled_x(&stylesheet, &d_pt, &d_g), vec![1, 1, 2]);
+
+        // A nested rule is more specific than a toplevel rule
+        let mut stylesheet = Stylesheet::new(&style_set);
+        let (a1, a2) = (set_x(&mut stylesheet, "1"), set_x(&mut stylesheet, "2"));
+        let grp = stylesheet.add_rule(None, StylableNodeRule::new().has_class("grp"), None);
+        stylesheet.add_rule(Some(grp), StylableNodeRule::new().has_class("a"), Some(a1));
+        stylesheet.add_rule(None, StylableNodeRule::new().has_class("a"), Some(a2));
+        assert_eq!(styled_x(&stylesheet, &d_pt, &d_g), vec![1, 1, 0]);
+    }
+
+    //ft test_selectors
+    #[test]
+    fn test_selectors() {
+        let int_type = StyleTypeValue::new(Option::<isize>::None);
+        let style_set = TypeSet::default()
+            .add_type("x", int_type.clone(), false)
+            .add_type("y", int_type.clone(), false);
+        let mut d_pt = Descriptor::new(&style_set);
+        d_pt.add_style("x");
+        d_pt.add_style("y");
+        let d_g = Descriptor::new(&style_set);
+        let set = |stylesheet: &mut Stylesheet, name: &str, v: &str| {
+            let mut nv = [(name.to_string(), v)].into_iter();
+            stylesheet.add_action_from_name_values(&mut nv).unwrap()
+        };
+        let pt = |class: &str, y: Option<&str>| {
+            let mut node = StylableNode::new("pt", &d_pt);
+            node.add_name_value("class", class).unwrap();
+            node.add_name_value("x", "0").unwrap();
+            if let Some(y) = y {
+                node.add_name_value("y", y).unwrap();
+            }
+            Element::new(node)
+        };
+
+        let mut stylesheet = Stylesheet::new(&style_set);
+        let (x1, x3, y2) = (
+            set(&mut stylesheet, "x", "1"),
+            set(&mut stylesheet, "x", "3"),
+            set(&mut stylesheet, "y", "2"),
+        );
+        let rule = StylableNodeRule::new()
+            .has_type("pt")
+            .has_class("a")
+            .has_class("b");
+        stylesheet.add_rule(None, rule, Some(x1));
+        let rule = StylableNodeRule::new().has_type("g").has_class("a");
+        stylesheet.add_rule(None, rule, Some(x3));
+        stylesheet.add_rule(None, StylableNodeRule::new().has_style("y"), Some(x3));
+        let box_rule = stylesheet.add_rule(None, StylableNodeRule::new().has_class("box"), None);
+        let rule = StylableNodeRule::new().has_type("pt").child(true);
+        stylesheet.add_rule(Some(box_rule), rule, Some(y2));
+
+        let mut group = StylableNode::new("g", &d_g);
+        group.add_name_value("class", "box a").unwrap();
+        let mut group = Element::new(group);
+        group.add_child(pt("a b", None));
+        group.add_child(pt("a", Some("0")));
+        let mut inner = Element::new(StylableNode::new("g", &d_g));
+        inner.add_child(pt("a b", None));
+        group.add_child(inner);
+        {
+            let mut tree = group.create_tree();
+            stylesheet.apply_rules_to_tree(&mut tree);
+        }
+        let style = |e: &Element, name: &str| {
+            e.stylable
+                .get_style_value_of_name(name)
+                .and_then(|v| v.as_isize())
+        };
+        let pts = [
+            &group.children[0],
+            &group.children[1],
+            &group.children[2].children[0],
+        ];
+        let x: Vec<_> = pts.iter().map(|e| style(e, "x")).collect();
+        let y: Vec<_> = pts.iter().map(|e| style(e, "y")).collect();
+        assert_eq!(x, vec![Some(1), Some(3), Some(1)]);
+        assert_eq!(y, vec![Some(2), Some(2), None]);
+    }
+
+    //ft test_inheritance
+    #[test]
+    fn test_inheritance() {
+        let int_type = StyleTypeValue::new(Option::<isize>::None);
+        let style_set = TypeSet::default()
+            .add_type("x", int_type.clone(), true)
+            .add_type("y", int_type.clone(), false);
+        let mut d_pt = Descriptor::new(&style_set);
+        d_pt.add_style("x");
+        d_pt.add_style("y");
+        let d_g = Descriptor::new(&style_set);
+        let pt = |name_values: &[(&str, &str)]| {
+            let mut node = StylableNode::new("pt", &d_pt);
+            for (n, v) in name_values {
+                node.add_name_value(n, v).unwrap();
+            }
+            Element::new(node)
+        };
+
+        // The inner group takes x from a rule, and inherits y
+        let mut stylesheet = Stylesheet::new(&style_set);
+        let mut nv = [("x".to_string(), "7"), ("y".to_string(), "inherit")].into_iter();
+        let action = stylesheet.add_action_from_name_values(&mut nv).unwrap();
+        stylesheet.add_rule(None, StylableNodeRule::new().has_class("in"), Some(action));
+
+        let mut group = StylableNode::new("g", &d_g);
+        group.add_name_value("x", "5").unwrap();
+        group.add_name_value("y", "2").unwrap();
+        let mut group = Element::new(group);
+        group.add_child(pt(&[]));
+        group.add_child(pt(&[("x", "1")]));
+        group.add_child(pt(&[("x", "initial"), ("y", "inherit")]));
+        let mut inner = StylableNode::new("g", &d_g);
+        inner.add_name_value("class", "in").unwrap();
+        let mut inner = Element::new(inner);
+        inner.add_child(pt(&[("y", "inherit")]));
+        group.add_child(inner);
+        let mut plain = Element::new(StylableNode::new("g", &d_g));
+        plain.add_child(pt(&[]));
+        group.add_child(plain);
+        {
+            let mut tree = group.create_tree();
+            stylesheet.apply_rules_to_tree(&mut tree);
+            stylesheet.apply_inheritance_to_tree(&mut tree);
+        }
+        let style = |e: &Element, name: &str| {
+            e.stylable
+                .get_style_value_of_name(name)
+                .and_then(|v| v.as_isize())
+        };
+        let pts = [
+            &group.children[0],
+            &group.children[1],
+            &group.children[2],
+            &group.children[3].children[0],
+            &group.children[4].children[0],
+        ];
+        let x: Vec<_> = pts.iter().map(|e| style(e, "x")).collect();
+        let y: Vec<_> = pts.iter().map(|e| style(e, "y")).collect();
+        assert_eq!(x, vec![Some(5), Some(1), None, Some(7), Some(5)]);
+        assert_eq!(y, vec![None, None, Some(2), Some(2), None]);
+    }
+
+    //ft test_link
+    #[test]
+    fn test_link() {
+        let int_type = StyleTypeValue::new(Option::<isize>::None);
+        let style_set = TypeSet::default().add_type("x", int_type.clone(), false);
+        let mut d_pt = Descriptor::new(&style_set);
+        d_pt.add_style("x");
+        let d_g = Descriptor::new(&style_set);
+
+        // Rules may use styles that are added after them
+        let mut stylesheet = Stylesheet::new(&style_set);
+        stylesheet.add_rule_of_style(None, StylableNodeRule::new().has_class("a"), "one");
+        let bad = stylesheet.add_rule_of_style(None, StylableNodeRule::new().has_id("pt2"), "none");
+        stylesheet.add_rule_of_style(None, StylableNodeRule::new().has_id("pt1"), "two");
+        for (id, x) in [("one", "1"), ("two", "2")] {
+            let mut nv = [("id".to_string(), id), ("x".to_string(), x)].into_iter();
+            stylesheet.add_action_from_name_values(&mut nv).unwrap();
+        }
+        assert_eq!(stylesheet.style_of_rule(bad), Some("none"));
+        assert_eq!(stylesheet.link(), Err(vec![bad]));
+        assert_eq!(styled_x(&stylesheet, &d_pt, &d_g), vec![1, 2, 0]);
+    }
 }
@@ -3,6 +3,6 @@ mod rules;
 mod tree;
 mod tree_rules;
 pub use self::bitmask::{BitMask, BitMaskU32, BitMaskU64, BitMaskX};
-pub use self::rules::{Action, RuleFn, RuleResult, RuleSet};
+pub use self::rules::{Action, RuleFn, RuleResult, RuleSet, Specificity};
 pub use self::tree::{Tree, TreeIterOp};
 pub use self::tree_rules::{TreeApplicator32, TreeApplicator64, TreeApplicatorX};
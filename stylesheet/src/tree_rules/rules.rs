@@ -77,6 +77,39 @@ impl std::fmt::Display for RuleResult {
     }
 }
 
+//a Specificity
+//tp Specificity
+/// The specificity of a rule, similar to that of a CSS selector; this
+/// is the number of ids, classes (and other attributes) and types that
+/// the rule (and the rules it is a child of) match on, and its depth
+/// in the rule hierarchy
+///
+/// Specificities are compared by ids, then classes, then types, then depth
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Specificity {
+    /// Number of ids matched
+    pub ids: usize,
+    /// Number of classes (and other attributes) matched
+    pub classes: usize,
+    /// Number of node types matched
+    pub types: usize,
+    /// Depth of the rule in the hierarchy of rules; toplevel rules have a depth of 1
+    pub depth: usize,
+}
+
+//ip Add for Specificity
+impl std::ops::Add for Specificity {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self {
+            ids: self.ids + other.ids,
+            classes: self.classes + other.classes,
+            types: self.types + other.types,
+            depth: self.depth + other.depth,
+        }
+    }
+}
+
 //a Traits
 //tp trait RuleFn<T>
 /// This trait must be satisfied by a struct that a RuleSet can be made for
@@ -87,6 +120,18 @@ impl std::fmt::Display for RuleResult {
 pub trait RuleFn<T> {
     /// Apply the rule to the value, at a given depth in the tree
     fn apply(&self, depth: usize, value: &T) -> RuleResult;
+
+    /// The explicit priority of the rule; the actions of rules with a
+    /// higher priority take precedence over those with a lower priority
+    fn priority(&self) -> isize {
+        0
+    }
+
+    /// The specificity of the rule itself, excluding that of its
+    /// parent rules and its depth
+    fn specificity(&self) -> Specificity {
+        Specificity::default()
+    }
 }
 
 //tp trait Action<T>
@@ -115,6 +160,8 @@ struct Rule<T, F: RuleFn<T>> {
     parent: Option<usize>,
     action: Option<usize>,
     child_rules: Vec<usize>,
+    /// Priority and specificity (including that of the parents) of the rule
+    precedence: (isize, Specificity),
     phantom: std::marker::PhantomData<T>,
 }
 
@@ -124,11 +171,15 @@ impl<T, F: RuleFn<T>> Rule<T, F> {
     /// Create a new rule given a RuleFn to match against and an
     /// optional action to take if the rule is matched
     fn new(parent: Option<usize>, match_fn: F, action: Option<usize>) -> Self {
+        let mut specificity = match_fn.specificity();
+        specificity.depth = 1;
+        let precedence = (match_fn.priority(), specificity);
         Self {
             parent,
             match_fn,
             action,
             child_rules: Vec::new(),
+            precedence,
             phantom: std::marker::PhantomData,
         }
     }
@@ -206,6 +257,20 @@ impl<T, A: Action<T>, F: RuleFn<T>> RuleSet<T, A, F> {
         action_num
     }
 
+    //mp num_actions
+    /// Find the number of actions in the set
+    #[inline]
+    pub fn num_actions(&self) -> usize {
+        self.actions.len()
+    }
+
+    //mp borrow_action
+    /// Borrow an action of the set
+    #[inline]
+    pub fn borrow_action(&self, action: usize) -> &A {
+        &self.actions[action]
+    }
+
     //mp add_rule
     /// Add a rule to the set, and return its handle; it may be a
     /// child of a parent rule given by a handle, or a toplevel rule.
@@ -213,11 +278,12 @@ impl<T, A: Action<T>, F: RuleFn<T>> RuleSet<T, A, F> {
     /// Note that the rule's children will all have a greater index
     /// than the rule. This invariant is required
     pub fn add_rule(&mut self, parent: Option<usize>, match_fn: F, action: Option<usize>) -> usize {
-        let rule = Rule::new(parent, match_fn, action);
+        let mut rule = Rule::new(parent, match_fn, action);
         let rule_num = self.rules.len();
         if let Some(parent) = parent {
             assert!(parent < rule_num);
             self.rules[parent].add_child(rule_num);
+            rule.precedence.1 = rule.precedence.1 + self.rules[parent].precedence.1;
         }
         self.rules.push(rule);
         rule_num
@@ -230,6 +296,38 @@ impl<T, A: Action<T>, F: RuleFn<T>> RuleSet<T, A, F> {
         self.rules[rule].parent.is_none()
     }
 
+    //mp borrow_rule_fn
+    /// Borrow the RuleFn of a rule, that it matches values with
+    #[inline]
+    pub fn borrow_rule_fn(&self, rule: usize) -> &F {
+        &self.rules[rule].match_fn
+    }
+
+    //mp action
+    /// Get the action of a rule, if it has one
+    #[inline]
+    pub fn action(&self, rule: usize) -> Option<usize> {
+        self.rules[rule].action
+    }
+
+    //mp set_action
+    /// Set the action of a rule, replacing any it has
+    #[inline]
+    pub fn set_action(&mut self, rule: usize, action: Option<usize>) {
+        self.rules[rule].action = action;
+    }
+
+    //mp precedence
+    /// Get the precedence of a rule - its priority and then its
+    /// specificity; when more than one rule fires for a node, the
+    /// actions are taken in order of precedence (and then of rule
+    /// index), so that the values from the rule of highest precedence
+    /// are those used
+    #[inline]
+    pub fn precedence(&self, rule: usize) -> (isize, Specificity) {
+        self.rules[rule].precedence
+    }
+
     //mp apply
     #[inline]
     pub fn apply(&self, rule: usize, depth: usize, node: &T) -> RuleResult {
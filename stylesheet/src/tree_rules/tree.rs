@@ -192,7 +192,7 @@ impl<'a, 'b, V> Iterator for TreeIter<'a, 'b, V> {
 /// A TreeNode is never cloned.
 pub struct TreeNode<'a, V> {
     node: &'a mut V,
-    _parent: Option<usize>,
+    parent: Option<usize>,
     depth: usize,
     children: Vec<usize>,
 }
@@ -200,7 +200,7 @@ impl<'a, V> TreeNode<'a, V> {
     pub fn new(node: &'a mut V, parent: Option<usize>, depth: usize) -> Self {
         Self {
             node,
-            _parent: parent,
+            parent,
             depth,
             children: Vec::new(),
         }
@@ -337,5 +337,21 @@ impl<'a, V> Tree<'a, V> {
         self.nodes[node_index].borrow_mut()
     }
 
+    //mp num_nodes
+    /// Get the number of nodes in the tree; nodes are numbered in
+    /// the order they were added, so a parent precedes its children
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    //mp borrow_parent_and_mut
+    /// Borrow the parent of a node and the node itself mutably, if
+    /// it has a parent
+    pub fn borrow_parent_and_mut(&mut self, node_index: usize) -> Option<(&V, &mut V)> {
+        let parent = self.nodes[node_index].parent?;
+        let (before, after) = self.nodes.split_at_mut(node_index);
+        Some((before[parent].borrow(), after[0].borrow_mut()))
+    }
+
     //zz All done
 }
@@ -87,13 +87,21 @@ where
     //mi try_rules
     /// Try applying the active rules in the set to a node
     ///
-    ///
+    /// The actions of the rules that match are then fired in order of
+    /// precedence, so that for each style the value used is that of
+    /// the rule with the highest priority (and then specificity, and
+    /// then the last such rule)
     fn try_rules(&mut self, mut active_mask: M, node: &mut V) {
         if DEBUG_RULE_TREE {
             println!("Try mask {:?} to node content {:?}", active_mask, node);
         }
         let depth = self.active_stack.len();
         let mut result_mask = active_mask.clone();
+        let mut matched = Vec::new();
+        // Child rules enabled for the children of the node; these are
+        // set in the result mask after all the rules are tried, as a
+        // rule may both be enabled by a parent rule and have ended
+        let mut child_rules = Vec::new();
         for i in 0..self.num_rules {
             if active_mask.is_set(i) {
                 let action = {
@@ -114,17 +122,13 @@ where
                         }
                         // Match, and propagate just child rules to child nodes
                         RuleResult::MatchEndChildren => {
-                            for j in self.rules.iter_children(i) {
-                                result_mask.set(*j);
-                            }
+                            child_rules.extend(self.rules.iter_children(i));
                             result_mask.clear(i);
                             true
                         }
                         // Match, and dont propagate this and child rules to child nodes
                         RuleResult::MatchPropagateChildren => {
-                            for j in self.rules.iter_children(i) {
-                                result_mask.set(*j);
-                            }
+                            child_rules.extend(self.rules.iter_children(i));
                             true
                         }
                         // Match, and propagate just child rules to this and child nodes
@@ -145,10 +149,17 @@ where
                     }
                 };
                 if action {
-                    self.rules.fire(i, depth, node);
+                    matched.push(i);
                 }
             }
         }
+        for j in child_rules {
+            result_mask.set(j);
+        }
+        matched.sort_by_key(|i| self.rules.precedence(*i));
+        for i in matched {
+            self.rules.fire(i, depth, node);
+        }
         if DEBUG_RULE_TREE {
             println!(
                 "Depth after matching now {} with mask {:?}",
@@ -2,7 +2,7 @@
 use serde::{Serialize, Serializer};
 use std::any::{Any, TypeId};
 
-use crate::{TypeValue, ValueError};
+use crate::{TypeValue, ValueError, Variables};
 
 //a StyleTypeValue
 //tp StyleTypeValue
@@ -305,10 +305,23 @@ impl StyleTypeValue {
         self.clone()
     }
     //mp from_string
-    /// Set the value from a string
+    /// Set the value from a string, evaluating any arithmetic or
+    /// color functions in it
     #[inline]
     pub fn from_string<'a>(&'a mut self, s: &str) -> Result<&'a mut Self, ValueError> {
-        self.value.parse_string(s, false)?;
+        self.from_string_with(s, &Variables::default())
+    }
+
+    //mp from_string_with
+    /// Set the value from a string, evaluating any variables,
+    /// arithmetic or color functions in it
+    pub fn from_string_with<'a>(
+        &'a mut self,
+        s: &str,
+        variables: &Variables,
+    ) -> Result<&'a mut Self, ValueError> {
+        let s = variables.evaluate(s)?;
+        self.value.parse_string(&s, false)?;
         Ok(self)
     }
 
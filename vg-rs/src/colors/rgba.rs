@@ -102,7 +102,6 @@ impl Rgba {
     /// This is accessed from the 'From' trait (or, therefore, Into)
     fn from_tuple_rgb((r, g, b): (u8, u8, u8)) -> Self {
         let rgb = (b as u32) | ((g as u32) << 8) | ((r as u32) << 16);
-        dbg!(r, g, b, &rgb);
         Self(rgb)
     }
 
@@ -127,18 +126,17 @@ impl Rgba {
             match u32::from_str_radix(s.split_at(1).1, 16) {
                 Ok(rgb) => {
                     if short_rgb {
-                        let b = (rgb >> 8) & 0xf;
+                        let r = (rgb >> 8) & 0xf;
                         let g = (rgb >> 4) & 0xf;
-                        let r = rgb & 0xf;
+                        let b = rgb & 0xf;
                         let r = (r | (r << 4)) as u8;
                         let g = (g | (g << 4)) as u8;
                         let b = (b | (b << 4)) as u8;
                         Some((r, g, b).into())
                     } else {
-                        let b = ((rgb >> 16) & 0xff) as u8;
+                        let r = ((rgb >> 16) & 0xff) as u8;
                         let g = ((rgb >> 8) & 0xff) as u8;
-                        let r = (rgb & 0xff) as u8;
-                        eprintln!("{:x}, {}, {}, {}", rgb, r, g, b);
+                        let b = (rgb & 0xff) as u8;
                         Some((r, g, b).into())
                     }
                 }
@@ -272,6 +270,25 @@ impl Rgba {
         }
     }
 
+    //cp of_hsl
+    /// Create an opaque Rgba from a hue (in degrees), saturation and
+    /// lightness (both 0. to 1.)
+    pub fn of_hsl(h: f32, s: f32, l: f32) -> Self {
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        let h = h.rem_euclid(360.0) / 60.0;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as usize {
+            0 => (c, x, 0.),
+            1 => (x, c, 0.),
+            2 => (0., c, x),
+            3 => (0., x, c),
+            4 => (x, 0., c),
+            _ => (c, 0., x),
+        };
+        let m = l - c / 2.0;
+        (r + m, g + m, b + m).into()
+    }
+
     //ap as_cmyk
     /// Get the CMYK as four f32 values
     pub fn as_cmyk(&self) -> (f32, f32, f32, f32) {
@@ -450,6 +467,12 @@ fn convert() {
     );
     approx_eq_t3(rgb.as_hsv(), (100.0, 0.675, 0.906), "RGB as hsv");
     approx_eq_t3(rgb.as_hsl(), (100.0, 0.765, 0.600), "RGB as hsl");
+    let (h, s, l) = rgb.as_hsl();
+    approx_eq_t3(
+        Rgba::of_hsl(h, s, l).as_tuple_rgb_f32(),
+        rgb.as_tuple_rgb_f32(),
+        "HSL and back",
+    );
     approx_eq_t4(rgb.as_cmyk(), (0.450, 0.0, 0.675, 0.094), "RGB as cmyk");
     approx_eq_t3(
         Rgba::cie_xyz_d6500_to_srgb(rgb.srgb_to_cie_xyz_d6500()),
@@ -524,3 +547,16 @@ fn convert() {
         "RGB as sRGBB as CIE XYZ at D6500",
     );
 }
+
+#[test]
+fn parse() {
+    assert_eq!(
+        Rgba::of_str("#f80").unwrap().as_tuple_rgba(),
+        (255, 136, 0, 255)
+    );
+    assert_eq!(
+        Rgba::of_str("#3366cc").unwrap().as_tuple_rgba(),
+        (0x33, 0x66, 0xcc, 255)
+    );
+    assert!(Rgba::of_str("3366cc").is_none());
+}
@@ -95,6 +95,41 @@ impl EquationSet {
         self.results[end] += inv_growth * length;
     }
 
+    //fp add_ratio_link
+    /// Add a link with some growth that holds the size between two
+    /// elements at a ratio of the size between two others
+    ///
+    /// The energy of the link is:
+    /// ```raw
+    ///    ((end-start) - ratio*(other_end-other_start)) ^ 2 / growth
+    /// ```
+    /// and so its change by a change in each of the four positions is
+    /// that of the bracketed term times the coefficient of the
+    /// position in it (-1, 1, ratio and -ratio)
+    pub fn add_ratio_link(
+        &mut self,
+        start: usize,
+        end: usize,
+        other_start: usize,
+        other_end: usize,
+        ratio: f64,
+        growth: f64,
+    ) {
+        let size = self.size;
+        let inv_growth = 1.0 / growth;
+        let coeffs = [
+            (start, -1.),
+            (end, 1.),
+            (other_start, ratio),
+            (other_end, -ratio),
+        ];
+        for (n, cn) in coeffs {
+            for (m, cm) in coeffs {
+                self.matrix[n * size + m] += inv_growth * cn * cm;
+            }
+        }
+    }
+
     //fp force_value
     /// Force index `n` to have a certain `value`
     ///
@@ -32,6 +32,9 @@ pub enum GridData<N: NodeId> {
     Growth(N, N, f64),
     /// Specify the placement of a node
     Place(N, f64),
+    /// Specify that the width between two nodes is a ratio of the
+    /// width between two other nodes
+    Ratio(N, N, N, N, f64),
 }
 
 //ip GridData
@@ -53,6 +56,14 @@ impl<N: NodeId> GridData<N> {
     pub fn new_place(start: N, size: f64) -> Self {
         Self::Place(start, size)
     }
+
+    //fp new_ratio
+    /// Create a new [GridData] element requiring the width from
+    /// `start` to `end` to be `ratio` times that from `other_start` to
+    /// `other_end`
+    pub fn new_ratio(start: N, end: N, other_start: N, other_end: N, ratio: f64) -> Self {
+        Self::Ratio(start, end, other_start, other_end, ratio)
+    }
 }
 
 //ip Display for GridData
@@ -64,6 +75,7 @@ impl<N: NodeId> std::fmt::Display for GridData<N> {
             Self::Width(s, e, w) => write!(f, "[{}->{}:{}]", s, e, w),
             Self::Growth(s, e, g) => write!(f, "[{}->{}:+{}]", s, e, g),
             Self::Place(s, p) => write!(f, "[{}@{}]", s, p),
+            Self::Ratio(s, e, os, oe, r) => write!(f, "[{}->{}:*{} {}->{}]", s, e, r, os, oe),
         }
     }
 
@@ -24,6 +24,11 @@ use indent_display::{IndentedDisplay, IndentedOptions, Indenter};
 //a Global constants for debug
 const DEBUG_GRID_PLACEMENT: bool = false;
 
+//a Constants
+/// The number of times the minimum sizes of the grid may be widened
+/// to meet its ratios before it is deemed that they cannot be met
+const MAX_RATIO_ITERATIONS: usize = 32;
+
 //a Public GridPlacement type
 //tp GridPlacement
 /// This contains a vector of the placement of each element within a grid dimension
@@ -36,6 +41,14 @@ pub struct GridPlacement<N: NodeId> {
     cell_data: Vec<GridCellDataEntry<N>>,
     /// Elasticity between node pairs
     growth_data: Vec<(N, N, f64)>,
+    /// Ratios between the sizes of node pairs
+    ratio_data: Vec<(N, N, N, N, f64)>,
+    /// Sizes between node pairs added to meet the ratios (or the
+    /// aspect ratios of a layout), until cleared
+    widened_data: Vec<GridCellDataEntry<N>>,
+    /// True if the ratios were not all met when the desired geometry
+    /// was last calculated
+    ratios_unmet: bool,
     /// Desired range
     desired_range: Range,
     resolver: Resolver<N>,
@@ -57,6 +70,10 @@ impl<N: NodeId> GridPlacement<N> {
                 GridData::Growth(start, end, growth) => {
                     self.growth_data.push((*start, *end, *growth));
                 }
+                GridData::Ratio(start, end, other_start, other_end, ratio) => {
+                    self.ratio_data
+                        .push((*start, *end, *other_start, *other_end, *ratio));
+                }
                 _ => {
                     todo!();
                 }
@@ -64,14 +81,79 @@ impl<N: NodeId> GridPlacement<N> {
         }
     }
 
+    //mp widen
+    /// Require a size between two nodes, to widen the span between
+    /// them until [GridPlacement::clear_widened] is invoked
+    pub fn widen(&mut self, start: N, end: N, size: f64) {
+        self.widened_data
+            .push(GridCellDataEntry::new(start, end, size));
+    }
+
+    //mp clear_widened
+    /// Clear the sizes added to meet ratios, so that the desired
+    /// geometry may be calculated afresh
+    pub fn clear_widened(&mut self) {
+        self.widened_data.clear();
+    }
+
+    //ap ratios_met
+    /// Return true if the ratios between sizes were all met when the
+    /// desired geometry was last calculated
+    pub fn ratios_met(&self) -> bool {
+        !self.ratios_unmet
+    }
+
     //mp get_desired_geometry
     /// calculates the positions of the elements in the grid given a center and expansion
     ///
     /// For a desired geometry this should be invoked with 0. for both arguments
     ///
     /// Given an actual size, centered on a value, expand the grid as required, and translate so that it is centered on the value.
+    ///
+    /// Any ratios between sizes are met by widening the smaller of
+    /// the two sizes, and resolving again, until all are met; if they
+    /// cannot be then [GridPlacement::ratios_met] returns false
+    /// afterwards
     pub fn get_desired_geometry(&mut self) -> Range {
-        self.resolver = Resolver::new(&mut self.cell_data.iter().map(|x| (x.start, x.end, x.size)));
+        let mut met = false;
+        for _ in 0..MAX_RATIO_ITERATIONS {
+            self.resolve_min_positions();
+            met = self.widen_to_ratios();
+            if met {
+                break;
+            }
+        }
+        self.ratios_unmet = !met;
+        if !met {
+            self.resolve_min_positions();
+        }
+        for (start, end, other_start, other_end, ratio) in &self.ratio_data {
+            if [start, end, other_start, other_end]
+                .iter()
+                .all(|n| self.resolver.has_node(**n))
+            {
+                self.resolver
+                    .add_ratio(*start, *end, *other_start, *other_end, *ratio);
+            }
+        }
+        self.desired_range = self.resolver.find_bounds();
+        self.size = self.desired_range.size();
+        // Centre on the origin
+        self.desired_range -= self.size * 0.5;
+        self.desired_range
+    }
+
+    //mi resolve_min_positions
+    /// Create the resolver from the cell and growth data, and assign
+    /// the minimum positions of its nodes
+    fn resolve_min_positions(&mut self) {
+        self.resolver = Resolver::new(
+            &mut self
+                .cell_data
+                .iter()
+                .chain(self.widened_data.iter())
+                .map(|x| (x.start, x.end, x.size)),
+        );
         for (start, end, growth) in &self.growth_data {
             if self.resolver.has_node(*start) && self.resolver.has_node(*end) {
                 if let Err(x) = self.resolver.set_growth_data(*start, *end, *growth) {
@@ -84,11 +166,47 @@ impl<N: NodeId> GridPlacement<N> {
         }
         self.resolver.place_roots_to_resolve(0.);
         self.resolver.assign_min_positions();
-        self.desired_range = self.resolver.find_bounds();
-        self.size = self.desired_range.size();
-        // Centre on the origin
-        self.desired_range -= self.size * 0.5;
-        self.desired_range
+    }
+
+    //mi widen_to_ratios
+    /// Widen the smaller of the sizes of each ratio
+    /// that the minimum positions do not meet; return true if they
+    /// are all met
+    fn widen_to_ratios(&mut self) -> bool {
+        let mut met = true;
+        for (start, end, other_start, other_end, ratio) in &self.ratio_data {
+            if ![start, end, other_start, other_end]
+                .iter()
+                .all(|n| self.resolver.has_node(**n))
+            {
+                continue;
+            }
+            let (s0, s1) = self.get_span(*start, *end);
+            let (o0, o1) = self.get_span(*other_start, *other_end);
+            let (size, other_size) = (s1 - s0, o1 - o0);
+            let epsilon = 1.0E-6 * (1. + size.abs());
+            if size < other_size * ratio - epsilon {
+                self.widened_data
+                    .push(GridCellDataEntry::new(*start, *end, other_size * ratio));
+                met = false;
+            } else if size > other_size * ratio + epsilon {
+                self.widened_data.push(GridCellDataEntry::new(
+                    *other_start,
+                    *other_end,
+                    size / ratio,
+                ));
+                met = false;
+            }
+        }
+        met
+    }
+
+    //mp set_span_sizes
+    /// Set the sizes required between pairs of nodes when the
+    /// positions are next calculated, where the links between them
+    /// can grow to those sizes
+    pub fn set_span_sizes(&mut self, span_sizes: Vec<(N, N, f64)>) {
+        self.resolver.set_span_sizes(span_sizes);
     }
 
     //mp calculate_positions
@@ -249,4 +367,70 @@ mod test_grid_placement {
         assert_eq!(gp.get_span(2, 8), (4., 10.));
         assert_eq!(gp.get_span(8, 10), (10., 14.));
     }
+    //fi check_sizes
+    fn check_sizes(gp: &GridPlacement<usize>, exp: &[(usize, usize, f64)]) {
+        for (s, e, size) in exp {
+            let (p0, p1) = gp.get_span(*s, *e);
+            assert!(
+                (p1 - p0 - size).abs() < 1E-4,
+                "For {}->{} expected size {} got {}",
+                s,
+                e,
+                size,
+                p1 - p0
+            );
+        }
+    }
+    //ft test_ratio
+    #[test]
+    fn test_ratio() {
+        let mut gp = GridPlacement::default();
+        gp.add_cell_data(&[
+            GridData::new_width(1, 2, 10.),
+            GridData::new_width(2, 3, 4.),
+            GridData::new_width(3, 4, 6.),
+            GridData::new_ratio(3, 4, 1, 2, 1.),
+            GridData::new_ratio(2, 3, 1, 2, 0.5),
+        ]);
+        let range = gp.get_desired_geometry();
+        assert_eq!(range.size(), 25.);
+        assert!(gp.ratios_met());
+        check_sizes(&gp, &[(1, 2, 10.), (2, 3, 5.), (3, 4, 10.)]);
+    }
+    //ft test_ratio_unmet
+    #[test]
+    fn test_ratio_unmet() {
+        // The second span cannot be both the same size as the first
+        // and twice its size
+        let mut gp = GridPlacement::default();
+        gp.add_cell_data(&[
+            GridData::new_width(1, 2, 10.),
+            GridData::new_width(2, 3, 4.),
+            GridData::new_ratio(2, 3, 1, 2, 1.),
+            GridData::new_ratio(2, 3, 1, 2, 2.),
+        ]);
+        gp.get_desired_geometry();
+        assert!(!gp.ratios_met());
+    }
+    //ft test_ratio_expand
+    #[test]
+    fn test_ratio_expand() {
+        let mut gp = GridPlacement::default();
+        gp.add_cell_data(&[
+            GridData::new_width(1, 2, 10.),
+            GridData::new_width(2, 3, 4.),
+            GridData::new_width(3, 4, 6.),
+            GridData::new_growth(1, 4, 1.),
+            GridData::new_ratio(3, 4, 1, 2, 1.),
+        ]);
+        let range = gp.get_desired_geometry();
+        assert_eq!(range.size(), 24.);
+        gp.calculate_positions(34., 0., 1.);
+        check_positions(&gp, &vec![(1, -17.), (4, 17.)]);
+        let third = 10. / 3.;
+        check_sizes(
+            &gp,
+            &[(1, 2, 10. + third), (2, 3, 4. + third), (3, 4, 10. + third)],
+        );
+    }
 }
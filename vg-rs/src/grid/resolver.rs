@@ -25,6 +25,12 @@ use crate::Range;
 //a Global constants for debug
 const DEBUG_CELL_DATA: bool = 1 == 0;
 
+//a Constants
+/// The growth of the links that hold sizes and ratios of sizes; this
+/// is much stiffer than the growth of any link that is expected to
+/// grow
+const RIGID_GROWTH: f64 = 1.0E-6;
+
 //a Resolver
 //tp Resolver
 /// This provides a means to resolve the requirements for a grid
@@ -55,6 +61,12 @@ pub struct Resolver<N: NodeId> {
     node_resolution_order: Vec<N>,
     /// Unresoved nodes
     unresolved_nodes: HashSet<N>,
+    /// Ratios required between the sizes of pairs of nodes, as (start,
+    /// end, other start, other end, ratio)
+    ratios: Vec<(N, N, N, N, f64)>,
+    /// Sizes required between pairs of nodes (beyond their minimum
+    /// sizes) when the energy is minimized
+    span_sizes: Vec<(N, N, f64)>,
 }
 
 //ip Resolver
@@ -115,9 +127,26 @@ impl<N: NodeId> Resolver<N> {
             leaves,
             node_resolution_order,
             unresolved_nodes,
+            ratios: Vec::new(),
+            span_sizes: Vec::new(),
         }
     }
 
+    //mp add_ratio
+    /// Require the size between two NodeId's to be a ratio of that
+    /// between two others when the energy is minimized
+    pub fn add_ratio(&mut self, start: N, end: N, other_start: N, other_end: N, ratio: f64) {
+        self.ratios
+            .push((start, end, other_start, other_end, ratio));
+    }
+
+    //mp set_span_sizes
+    /// Set the sizes required between pairs of NodeId's when the
+    /// energy is minimized, replacing any set before
+    pub fn set_span_sizes(&mut self, span_sizes: Vec<(N, N, f64)>) {
+        self.span_sizes = span_sizes;
+    }
+
     //mp set_growth_data
     /// Set a link between two NodeId's to have a certain growth factor
     pub fn set_growth_data(&mut self, start: N, end: N, growth: f64) -> Result<(), String> {
@@ -305,8 +334,12 @@ impl<N: NodeId> Resolver<N> {
     ///
     /// All leaves are fixed to the right-hand edge
     ///
-    /// If any node is unconstrained (which should not happen!) then
-    /// force it to its min position
+    /// If any node is unconstrained by the links (which should not
+    /// happen!) then force it to its min position
+    ///
+    /// The ratios and sizes required between nodes are then added as
+    /// rigid links; these only move nodes that the links permit to
+    /// move
     pub fn create_energy_matrix(&self) -> EquationSet {
         let num_nodes = self.node_ids.len();
         let mut eqns = EquationSet::new(num_nodes);
@@ -319,15 +352,32 @@ impl<N: NodeId> Resolver<N> {
                 eqns.add_growth_link(s, e, length, growth);
             }
         }
+        let unconstrained: Vec<usize> = (0..num_nodes).filter(|i| eqns.row_is_zero(*i)).collect();
+        for (s, e, os, oe, ratio) in self.ratios.iter() {
+            let index = |n| self.nodes[n].index;
+            eqns.add_ratio_link(
+                index(s),
+                index(e),
+                index(os),
+                index(oe),
+                *ratio,
+                RIGID_GROWTH,
+            );
+        }
+        for (s, e, size) in self.span_sizes.iter() {
+            let s = self.nodes[s].index;
+            let e = self.nodes[e].index;
+            eqns.add_growth_link(s, e, *size, RIGID_GROWTH);
+        }
         for (_, node) in self.nodes.iter() {
             if node.is_placed() {
                 println!("Placed {} at {}", node.index, node.get_position());
                 eqns.force_value(node.index, node.get_position());
             }
         }
-        for i in 0..num_nodes {
-            if eqns.row_is_zero(i) {
-                let node_id = self.node_ids[i];
+        for i in unconstrained {
+            let node_id = self.node_ids[i];
+            if !self.nodes[&node_id].is_placed() {
                 println!(
                     "Row {} = {} is zero, force to {}",
                     i,
@@ -30,7 +30,7 @@ mod layout_box;
 mod layout_record;
 mod placement;
 
-pub use layout::Layout;
+pub use layout::{AspectData, Layout};
 pub use layout_box::LayoutBox;
 pub use layout_record::LayoutRecord;
 pub use placement::Placements;
@@ -17,6 +17,7 @@ limitations under the License.
  */
 
 //a Imports
+use geo_nd::Vector;
 use std::collections::HashMap;
 
 use crate::{BBox, Point, Transform};
@@ -27,6 +28,16 @@ use crate::layout::Placements;
 //a Constants
 const DEBUG_LAYOUT: bool = 1 == 0;
 
+/// The number of times the grid may be widened to meet its aspect
+/// ratios before it is deemed that they cannot be met
+const MAX_ASPECT_ITERATIONS: usize = 32;
+
+//a Types
+//tp AspectData
+/// An aspect ratio (width to height) required of an area of the grid,
+/// given by its start and end grid elements (X then Y)
+pub type AspectData = ((usize, usize), (usize, usize), f64);
+
 //a Layout
 //tp Layout
 /// A layout
@@ -35,6 +46,15 @@ pub struct Layout {
     grid_placements: (GridPlacement<usize>, GridPlacement<usize>),
     /// 0. to 1. for each dimension to expand layout to fill its parent
     grid_expand: (f64, f64),
+    /// -1. to 1. for each dimension to anchor the grid to the minimum
+    /// or maximum of the layout if it is not expanded to fill it
+    grid_anchor: (f64, f64),
+    /// Aspect ratios (width to height) required of areas of the grid,
+    /// given by their start and end grid elements
+    aspect_data: Vec<AspectData>,
+    /// True if the aspect ratios were not all met when the desired
+    /// geometry was last calculated
+    aspects_unmet: bool,
     direct_placements: (Placements, Placements),
     desired_grid: BBox,
     desired_placement: BBox,
@@ -72,6 +92,26 @@ impl Layout {
             self.grid_expand.1 = expand;
         }
     }
+    //ap grid_anchor
+    /// Get the anchor of the grid
+    pub fn grid_anchor(&self, x: bool) -> f64 {
+        if x {
+            self.grid_anchor.0
+        } else {
+            self.grid_anchor.1
+        }
+    }
+    //mp set_grid_anchor
+    /// Set the anchor of the grid, where -1. places it at the minimum
+    /// of the space it does not expand to fill, 0. centres it, and 1.
+    /// places it at the maximum
+    pub fn set_grid_anchor(&mut self, x: bool, anchor: f64) {
+        if x {
+            self.grid_anchor.0 = anchor;
+        } else {
+            self.grid_anchor.1 = anchor;
+        }
+    }
     //mp find_grid_id
     /// Get the element number (if any) for an ID in the grid for X or Y
     pub fn find_grid_id(&self, x: bool, s: &str) -> Option<&usize> {
@@ -150,14 +190,90 @@ impl Layout {
         self.grid_placements.1.add_cell_data(y);
     }
 
+    //mp add_aspect
+    /// Require the area of the grid between two grid elements to have
+    /// an aspect ratio, of its width to its height
+    pub fn add_aspect(&mut self, start: (usize, usize), end: (usize, usize), ratio: f64) {
+        self.aspect_data.push((start, end, ratio));
+    }
+
+    //ap constraints_met
+    /// Return true if the aspect ratios of areas of the grid, and the
+    /// ratios between sizes of its spans, were all met when the
+    /// desired geometry was last calculated
+    pub fn constraints_met(&self) -> bool {
+        !self.aspects_unmet
+            && self.grid_placements.0.ratios_met()
+            && self.grid_placements.1.ratios_met()
+    }
+
+    //mi aspect_sizes
+    /// Get the width and height of the area of each aspect ratio of
+    /// the grid, if the grid has positions for it
+    fn aspect_sizes(&self) -> Vec<Option<(f64, f64)>> {
+        let span = |gp: &GridPlacement<usize>, start, end| {
+            Some(gp.get_position(end)? - gp.get_position(start)?)
+        };
+        self.aspect_data
+            .iter()
+            .map(|(start, end, _)| {
+                let width = span(&self.grid_placements.0, start.0, end.0)?;
+                let height = span(&self.grid_placements.1, start.1, end.1)?;
+                Some((width, height))
+            })
+            .collect()
+    }
+
+    //mi widen_to_aspects
+    /// Widen the width or height of each area of the
+    /// grid that does not meet its aspect ratio; return true if they
+    /// are all met
+    fn widen_to_aspects(&mut self) -> bool {
+        let mut met = true;
+        let sizes = self.aspect_sizes();
+        for ((start, end, ratio), size) in self.aspect_data.iter().zip(sizes) {
+            let Some((width, height)) = size else {
+                continue;
+            };
+            let epsilon = 1.0E-6 * (1. + width.abs());
+            if width < height * ratio - epsilon {
+                self.grid_placements.0.widen(start.0, end.0, height * ratio);
+                met = false;
+            } else if width > height * ratio + epsilon {
+                self.grid_placements.1.widen(start.1, end.1, width / ratio);
+                met = false;
+            }
+        }
+        met
+    }
+
     //mp get_desired_geometry
     /// With all elements placed the layout will have a desired geometry
     ///
     /// Any placements provide a true bbox
     /// A grid has a desired width and height, centred on 0,0
+    ///
+    /// Any aspect ratios of areas of the grid are met by widening the
+    /// width or height of the area, until all are met; if they (or
+    /// the ratios between sizes of spans of the grid) cannot be then
+    /// [Layout::constraints_met] returns false afterwards
+    ///
+    /// Any widening from an earlier invocation is discarded first
     pub fn get_desired_geometry(&mut self) -> BBox {
-        let grid_x = self.grid_placements.0.get_desired_geometry();
-        let grid_y = self.grid_placements.1.get_desired_geometry();
+        self.grid_placements.0.clear_widened();
+        self.grid_placements.1.clear_widened();
+        let mut grid_x = self.grid_placements.0.get_desired_geometry();
+        let mut grid_y = self.grid_placements.1.get_desired_geometry();
+        let mut met = self.widen_to_aspects();
+        for _ in 0..MAX_ASPECT_ITERATIONS {
+            if met {
+                break;
+            }
+            grid_x = self.grid_placements.0.get_desired_geometry();
+            grid_y = self.grid_placements.1.get_desired_geometry();
+            met = self.widen_to_aspects();
+        }
+        self.aspects_unmet = !met;
 
         let place_x_pt = self.direct_placements.0.get_desired_geometry();
         let place_y_pt = self.direct_placements.1.get_desired_geometry();
@@ -195,9 +311,18 @@ impl Layout {
     //mp layout
     /// All the placement data must have been provided, and a layout of the box can be performed.
     ///
-    /// For any grid within the layout this requires a possibly expansion, plus a translation
+    /// The desired geometry (placements and grid together) is
+    /// translated so that it is centred on the rectangle it is laid
+    /// out within; this is the `content_to_actual` transform. Any grid
+    /// within the layout is then centred where the transform places
+    /// its desired centre, and may expand into any space beyond the
+    /// desired geometry; any of that space it does not expand into is
+    /// split either side of it as given by its anchor
+    ///
+    /// The grid is laid out in X and then in Y; the heights of areas
+    /// of the grid with aspect ratios are held to match their widths,
+    /// as far as the grid in Y can grow
     pub fn layout(&mut self, within: &BBox) {
-        // expand_default:(f64,f64), expand:Vec<(isize,f64)>, cell_data:&'a Vec<GridCellData>) -> Self {
         if DEBUG_LAYOUT {
             println!(
                 "Laying out Layout {} : {} : {} within rectangle {}",
@@ -205,17 +330,39 @@ impl Layout {
             );
         }
         let (ac, aw, ah) = within.get_cwh();
-        let (dc, _dw, _dh) = self.desired_geometry.get_cwh();
-        if DEBUG_LAYOUT {
-            println!("Why not centre on ac {}?", ac);
+        let (dc, dw, dh) = self.desired_geometry.get_cwh();
+        // An offset that is only rounding error would give a
+        // meaningless transform to the content, so it is dropped
+        let mut offset = ac - dc;
+        for (i, size) in [aw, ah].iter().enumerate() {
+            if offset[i].abs() < 1E-10 * (1. + size.abs()) {
+                offset[i] = 0.;
+            }
+        }
+        self.content_to_actual = Transform::of_translation(offset);
+        if !self.desired_grid.is_none() {
+            let (gc, gw, gh) = self.desired_grid.get_cwh();
+            let gc = gc + offset;
+            let slack = |size: f64, expand: f64, anchor: f64| (1. - expand) * size * anchor * 0.5;
+            let gx = gc[0] + slack(aw - dw, self.grid_expand.0, self.grid_anchor.0);
+            let gy = gc[1] + slack(ah - dh, self.grid_expand.1, self.grid_anchor.1);
+            self.grid_placements
+                .0
+                .calculate_positions(gw + aw - dw, gx, self.grid_expand.0);
+            // The heights of areas with aspect ratios follow their widths
+            let span_sizes = self
+                .aspect_data
+                .iter()
+                .zip(self.aspect_sizes())
+                .filter_map(|((start, end, ratio), size)| {
+                    size.map(|(width, _)| (start.1, end.1, width / ratio))
+                })
+                .collect();
+            self.grid_placements.1.set_span_sizes(span_sizes);
+            self.grid_placements
+                .1
+                .calculate_positions(gh + ah - dh, gy, self.grid_expand.1);
         }
-        self.grid_placements
-            .0
-            .calculate_positions(aw, 0., self.grid_expand.0);
-        self.grid_placements
-            .1
-            .calculate_positions(ah, 0., self.grid_expand.1);
-        self.content_to_actual = Transform::of_translation(ac - dc);
     }
 
     //ap layout_transform
@@ -233,10 +380,17 @@ impl Layout {
         BBox::new(x0, y0, x1, y1)
     }
 
-    //mp get_place_rectangle
-    /// Get the place rectangle, whatever that means
-    pub fn get_placed_rectangle(&self, _pt: &Point, _ref_pt: &Option<Point>) -> BBox {
-        BBox::new(0., 0., 10., 10.)
+    //mp get_placed_rectangle
+    /// Get the actual rectangle of an element placed at a point,
+    /// given the reference point within the element (if any, else
+    /// its origin) and the element's desired bounding box
+    ///
+    /// The desired bounding box is translated so that the reference
+    /// point is at the placement point, and then mapped by the
+    /// `content_to_actual` transform of the layout
+    pub fn get_placed_rectangle(&self, pt: &Point, ref_pt: &Option<Point>, bbox: &BBox) -> BBox {
+        let ref_pt = ref_pt.unwrap_or_else(Point::zero);
+        (*bbox + (*pt - ref_pt)).transform(&self.content_to_actual)
     }
 
     //mp get_grid_positions
@@ -275,6 +429,131 @@ impl Layout {
     //zz All done
 }
 
+//mt Test for Layout
+#[cfg(test)]
+mod test_layout {
+    use super::*;
+    //fi bbox_eq
+    fn bbox_eq(bbox: &BBox, x0: f64, y0: f64, x1: f64, y1: f64) {
+        let exp = BBox::new(x0, y0, x1, y1);
+        for (a, e) in [bbox.x, bbox.y].iter().zip([exp.x, exp.y].iter()) {
+            assert!(
+                (a[0] - e[0]).abs() < 1E-8 && (a[1] - e[1]).abs() < 1E-8,
+                "Expected bbox {} got {}",
+                exp,
+                bbox
+            );
+        }
+    }
+    //fi grid_and_place
+    /// Create a layout with a 20 by 10 grid cell and a 10 by 10
+    /// element placed with its reference point (5,0) at (30,0)
+    fn grid_and_place() -> Layout {
+        let mut layout = Layout::default();
+        let x0 = layout.add_grid_id(true, "1");
+        let y0 = layout.add_grid_id(false, "1");
+        let x1 = layout.add_grid_id(true, "2");
+        let y1 = layout.add_grid_id(false, "2");
+        layout.add_grid_element("grid", (x0, y0), (x1, y1), (20., 10.));
+        layout.add_placed_element(
+            "place",
+            &Point::from_array([30., 0.]),
+            &Some(Point::from_array([5., 0.])),
+            &BBox::new(-5., -5., 5., 5.),
+        );
+        layout
+    }
+    //ft test_place
+    #[test]
+    fn test_place() {
+        let mut layout = grid_and_place();
+        let desired = layout.get_desired_geometry();
+        bbox_eq(&desired, -10., -5., 30., 5.);
+        layout.layout(&(desired + Point::from_array([100., 50.])));
+        let placed = layout.get_placed_rectangle(
+            &Point::from_array([30., 0.]),
+            &Some(Point::from_array([5., 0.])),
+            &BBox::new(-5., -5., 5., 5.),
+        );
+        bbox_eq(&placed, 120., 45., 130., 55.);
+        bbox_eq(&layout.grid_bbox((0, 0), (1, 1)), 90., 45., 110., 55.);
+    }
+    //ft test_place_expand
+    #[test]
+    fn test_place_expand() {
+        let mut layout = grid_and_place();
+        layout.set_grid_expand(true, 1.);
+        let desired = layout.get_desired_geometry();
+        layout.layout(&BBox::new(-10., -5., 50., 5.));
+        bbox_eq(&layout.grid_bbox((0, 0), (1, 1)), -10., -5., 30., 5.);
+        let placed = layout.get_placed_rectangle(&Point::zero(), &None, &BBox::new(0., 0., 1., 1.));
+        bbox_eq(&placed, 10., 0., 11., 1.);
+        bbox_eq(&desired, -10., -5., 30., 5.);
+    }
+    //ft test_rounding
+    #[test]
+    fn test_rounding() {
+        let mut layout = grid_and_place();
+        layout.get_desired_geometry();
+        layout.layout(&BBox::new(-10., -5. - 3E-15, 30., 5.));
+        assert!(layout.layout_transform().is_identity());
+    }
+    //ft test_grid_anchor
+    #[test]
+    fn test_grid_anchor() {
+        let mut layout = grid_and_place();
+        layout.set_grid_anchor(true, -1.);
+        layout.set_grid_anchor(false, 1.);
+        layout.get_desired_geometry();
+        layout.layout(&BBox::new(-10., -15., 50., 15.));
+        bbox_eq(&layout.grid_bbox((0, 0), (1, 1)), -10., 5., 10., 15.);
+    }
+    //ft test_aspect
+    #[test]
+    fn test_aspect() {
+        // A 10 by 4 cell that must be square, above a 10 by 2 cell,
+        // with the grid able to grow in both X and Y
+        let mut layout = Layout::default();
+        layout.add_grid_element("a", (0, 0), (1, 1), (10., 4.));
+        layout.add_grid_element("b", (0, 1), (1, 2), (10., 2.));
+        layout.add_cell_data(
+            &[GridData::new_growth(0, 1, 1.)],
+            &[GridData::new_growth(0, 2, 1.)],
+        );
+        layout.add_aspect((0, 0), (1, 1), 1.);
+        let desired = layout.get_desired_geometry();
+        bbox_eq(&desired, -5., -6., 5., 6.);
+        assert!(layout.constraints_met());
+        // A second calculation must not widen the grid further
+        let desired = layout.get_desired_geometry();
+        bbox_eq(&desired, -5., -6., 5., 6.);
+        layout.set_grid_expand(true, 1.);
+        layout.set_grid_expand(false, 1.);
+        layout.layout(&BBox::new(-10., -15., 10., 15.));
+        let round = |x: f64| (x * 1000.).round() / 1000.;
+        let bbox = layout.grid_bbox((0, 0), (1, 1));
+        let bbox = BBox::new(
+            round(bbox.x.min()),
+            round(bbox.y.min()),
+            round(bbox.x.max()),
+            round(bbox.y.max()),
+        );
+        bbox_eq(&bbox, -10., -15., 10., 5.);
+    }
+    //ft test_aspect_unmet
+    #[test]
+    fn test_aspect_unmet() {
+        // A cell that must be both square and twice as wide as it is
+        // high
+        let mut layout = Layout::default();
+        layout.add_grid_element("a", (0, 0), (1, 1), (10., 4.));
+        layout.add_aspect((0, 0), (1, 1), 1.);
+        layout.add_aspect((0, 0), (1, 1), 2.);
+        layout.get_desired_geometry();
+        assert!(!layout.constraints_met());
+    }
+}
+
 /*
 
 impl Layout {
@@ -167,6 +167,40 @@ impl BezierPath {
         self.elements.iter()
     }
 
+    //mp map_pts
+    /// Map all the points (including control points) of the path
+    pub fn map_pts<Map: Fn(Point) -> Point>(&mut self, map: Map) {
+        for b in self.elements.iter_mut() {
+            b.map_pts(&map);
+        }
+    }
+
+    //mp length
+    /// Get the length of the whole path
+    pub fn length(&self, straightness: f64) -> f64 {
+        self.elements.iter().map(|b| b.length(straightness)).sum()
+    }
+
+    //mp point_at_fraction
+    /// Get the point and the tangent at a fraction (0 to 1) of the
+    /// length along the path; the fraction is clamped to that range
+    pub fn point_at_fraction(&self, straightness: f64, fraction: f64) -> (Point, Point) {
+        if self.elements.is_empty() {
+            return (Point::zero(), Point::zero());
+        }
+        let mut distance = self.length(straightness) * fraction.clamp(0., 1.);
+        let n = self.elements.len();
+        for (i, b) in self.elements.iter().enumerate() {
+            let l = b.length(straightness);
+            if distance <= l || i == n - 1 {
+                let (t, _in_bezier) = b.t_of_distance(straightness, distance.min(l));
+                return (b.point_at(t), b.tangent_at(t));
+            }
+            distance -= l;
+        }
+        unreachable!();
+    }
+
     //zz All done
 }
 
@@ -253,4 +287,26 @@ mod test_path {
         bezier_eq(&bp.elements[6], vec![(0., 0.9), (0., 0.1)]);
         assert_eq!(bp.elements.len(), 8, "Path should be 8 elements");
     }
+    #[test]
+    fn test_point_at_fraction() {
+        let p0 = Point::zero();
+        let p1 = Point::from_array([1., 0.]);
+        let p2 = Point::from_array([1., 3.]);
+        let mut bp = BezierPath::default();
+        bp.add_bezier(Bezier::line(&p0, &p1));
+        bp.add_bezier(Bezier::line(&p1, &p2));
+
+        assert!(
+            (bp.length(1E-6) - 4.).abs() < 1E-8,
+            "Path length should be 4"
+        );
+        pt_eq(&bp.point_at_fraction(1E-6, 0.).0, 0., 0.);
+        pt_eq(&bp.point_at_fraction(1E-6, 0.125).0, 0.5, 0.);
+        pt_eq(&bp.point_at_fraction(1E-6, 0.5).0, 1., 1.);
+        pt_eq(&bp.point_at_fraction(1E-6, 1.).0, 1., 3.);
+        pt_eq(&bp.point_at_fraction(1E-6, 2.).0, 1., 3.);
+
+        bp.map_pts(|p| p + Point::from_array([2., 1.]));
+        pt_eq(&bp.point_at_fraction(1E-6, 0.5).0, 3., 2.);
+    }
 }